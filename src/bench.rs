@@ -0,0 +1,354 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use vulkano::sync::{self, GpuFuture};
+use vulkano_util::context::VulkanoContext;
+
+use crate::{
+    device_info::DeviceLimits,
+    game_compute_pipeline::{GameComputePipeline, RenderSettings, LDR_FORMAT},
+    init_error::InitError,
+    pattern::Pattern,
+    rule::Rule,
+    seeding::{self, SeedSettings},
+    soup::{SAMPLE_INTERVAL, STABLE_SAMPLES},
+};
+
+/// Grid sides measured by `--sweep`, doubling from 256. The largest don't fit every device, they're
+/// skipped by `run`.
+pub const SWEEP_SIZES: [u32; 6] = [256, 512, 1024, 2048, 4096, 8192];
+
+// Generations submitted at once, enough that the submission overhead doesn't dominate
+const BATCH_GENERATIONS: u32 = 16;
+
+// Each size keeps running batches until both of these are reached
+const MIN_DURATION: Duration = Duration::from_secs(2);
+const MIN_GENERATIONS: u64 = 256;
+
+/// Grid sides tried by `suggest_grid`, smallest first, each a multiple of the workgroup size
+pub const SUGGEST_SIZES: [u32; 9] = [256, 512, 768, 1024, 1536, 2048, 3072, 4096, 6144];
+
+// Shorter than a full benchmark since it runs on every start
+const SUGGEST_DURATION: Duration = Duration::from_millis(100);
+const SUGGEST_GENERATIONS: u64 = 32;
+
+// Fraction of a frame the simulation may take, the rest goes to coloring, rendering and the UI
+const SIMULATION_SHARE: f64 = 0.5;
+
+// Grid side and generations simulated by `verify_batching`
+const VERIFY_SIZE: u32 = 256;
+const VERIFY_GENERATIONS: u32 = 64;
+
+// Generations per submission compared against submitting one generation at a time, 0 is a
+// single submission
+const VERIFY_BATCHES: [u32; 4] = [0, 1, 7, 16];
+
+// Generations of each frame of the interactive run of `verify_batching`, `VERIFY_GENERATIONS` in
+// all, paused frames included
+const VERIFY_FRAMES: [u32; 8] = [1, 0, 3, 16, 0, 7, 1, 36];
+
+// Shift applied by `verify_shift`, large enough to wrap the glider across both edges
+const VERIFY_SHIFT: [i32; 2] = [-100, 37];
+
+/// Generations a `--stats-out` run simulates at most, unless given
+pub const DEFAULT_RUN_GENERATIONS: u64 = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BenchFormat {
+    Table,
+    Csv,
+}
+
+/// Time the simulation of random square grids without a window and print the throughput, sizes
+/// that can't be created are reported and skipped
+pub fn run(context: &VulkanoContext, sizes: &[u32], format: BenchFormat) {
+    match format {
+        BenchFormat::Table => println!("{:>8} {:>14} {:>16}", "size", "generations/s", "cells/s"),
+        BenchFormat::Csv => println!("size,generations_per_second,cells_per_second"),
+    }
+
+    for &size in sizes {
+        let generations_per_second = match measure(context, size, MIN_DURATION, MIN_GENERATIONS) {
+            Ok(generations_per_second) => generations_per_second,
+            Err(err) => {
+                eprintln!("{}x{} skipped, {}", size, size, err);
+                continue;
+            }
+        };
+        let cells_per_second = generations_per_second * (size as f64 * size as f64);
+        match format {
+            BenchFormat::Table => println!(
+                "{:>8} {:>14.1} {:>16.3e}",
+                format!("{}x{}", size, size),
+                generations_per_second,
+                cells_per_second
+            ),
+            BenchFormat::Csv => println!(
+                "{},{:.3},{:.0}",
+                size, generations_per_second, cells_per_second
+            ),
+        }
+    }
+}
+
+/// The largest grid `suggest_grid` found to keep up with the frame rate
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GridSuggestion {
+    pub side: u32,
+    pub target_fps: u32,
+    /// Measured on the suggested grid
+    pub generations_per_second: f64,
+}
+
+/// Time a few generations on growing square grids, up to the largest the device supports, and
+/// return the largest that simulates `target_fps` frames of one generation each in
+/// `SIMULATION_SHARE` of the frame time. `None` if even the smallest can't.
+pub fn suggest_grid(
+    context: &VulkanoContext,
+    target_fps: u32,
+) -> Result<Option<GridSuggestion>, InitError> {
+    let max_side = DeviceLimits::query(context).max_grid_side();
+    let required = target_fps as f64 / SIMULATION_SHARE;
+    let mut suggestion = None;
+    for side in SUGGEST_SIZES.into_iter().filter(|&side| side <= max_side) {
+        let generations_per_second =
+            match measure(context, side, SUGGEST_DURATION, SUGGEST_GENERATIONS) {
+                Ok(generations_per_second) => generations_per_second,
+                // Out of memory on a larger grid, the smaller ones still count
+                Err(_) if suggestion.is_some() => break,
+                Err(err) => return Err(err),
+            };
+        // Larger grids are only slower
+        if generations_per_second < required {
+            break;
+        }
+        suggestion = Some(GridSuggestion {
+            side,
+            target_fps,
+            generations_per_second,
+        });
+    }
+    Ok(suggestion)
+}
+
+/// Generations per second on a `size` x `size` grid, running batches until both the duration
+/// and generations are reached
+fn measure(
+    context: &VulkanoContext,
+    size: u32,
+    min_duration: Duration,
+    min_generations: u64,
+) -> Result<f64, InitError> {
+    let mut pipeline = GameComputePipeline::new(context, [size, size], LDR_FORMAT)?;
+    // Only the simulation is measured
+    pipeline.set_color(false);
+    let rule = Rule::default();
+    let settings = RenderSettings::default();
+    let submit = |pipeline: &mut GameComputePipeline| {
+        let before = sync::now(context.device()).boxed();
+        // Already flushed, waiting on the pipeline covers the returned future
+        let _ = pipeline.compute_n(before, BATCH_GENERATIONS, &rule, &settings);
+        pipeline.wait();
+    };
+
+    // The first submission includes one-off costs like uploading the random grid
+    submit(&mut pipeline);
+
+    let start = Instant::now();
+    let mut generations = 0;
+    while start.elapsed() < min_duration || generations < min_generations {
+        submit(&mut pipeline);
+        generations += BATCH_GENERATIONS as u64;
+    }
+    Ok(generations as f64 / start.elapsed().as_secs_f64())
+}
+
+/// Simulate the same seeded grid with generations batched into submissions of different sizes
+/// and check each ends up the same as submitting one generation at a time. Each batch size is
+/// run both in one call without coloring and a frame at a time like the window does, with the
+/// color passes, stats and frame capture on. Prints a line per run and returns whether they all
+/// matched.
+pub fn verify_batching(context: &VulkanoContext) -> Result<bool, InitError> {
+    let size = [VERIFY_SIZE, VERIFY_SIZE];
+    let start = seeding::seeded_grid(size, &SeedSettings::default());
+    let rule = Rule::default();
+    let settings = RenderSettings::default();
+    let run = |generations_per_submit: u32, frames: &[u32], interactive: bool| {
+        let mut pipeline = GameComputePipeline::new(context, size, LDR_FORMAT)?;
+        pipeline.set_color(interactive);
+        pipeline.set_collect_stats(interactive);
+        pipeline.set_capture_frame(interactive);
+        pipeline.set_generations_per_submit(generations_per_submit);
+        pipeline.load_pattern(&start, [0, 0]);
+        for &generations in frames {
+            let before = sync::now(context.device()).boxed();
+            let _ = pipeline.compute_n(before, generations, &rule, &settings);
+            pipeline.wait();
+        }
+        Ok(pipeline.snapshot())
+    };
+
+    let expected = run(0, &[1; VERIFY_GENERATIONS as usize], false)?;
+    let mut all_match = true;
+    for batch in VERIFY_BATCHES {
+        for interactive in [false, true] {
+            let matches = if interactive {
+                run(batch, &VERIFY_FRAMES, true)?
+            } else {
+                run(batch, &[VERIFY_GENERATIONS], false)?
+            } == expected;
+            all_match &= matches;
+            println!(
+                "{:>4} generations per submit{}: {}",
+                batch,
+                if interactive { ", interactive" } else { "" },
+                if matches { "ok" } else { "MISMATCH" }
+            );
+        }
+    }
+    Ok(all_match)
+}
+
+/// Simulate a glider crossing the wrapped edges after shifting the grid, and check it ends up
+/// where simulating first and rolling the cells on the CPU puts it. Prints the result and returns
+/// whether they matched.
+pub fn verify_shift(context: &VulkanoContext) -> Result<bool, InitError> {
+    let size = [VERIFY_SIZE, VERIFY_SIZE];
+    let glider = Pattern::new([3, 3], vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+    let rule = Rule::default();
+    let settings = RenderSettings::default();
+    let run = |shift: [i32; 2]| -> Result<Vec<u8>, InitError> {
+        let mut pipeline = GameComputePipeline::new(context, size, LDR_FORMAT)?;
+        pipeline.set_color(false);
+        pipeline.clear();
+        // Heading down and right off the corner
+        pipeline.load_pattern(&glider, [VERIFY_SIZE as i32 - 8, VERIFY_SIZE as i32 - 8]);
+        pipeline.shift(shift[0], shift[1]);
+        let before = sync::now(context.device()).boxed();
+        let _ = pipeline.compute_n(before, VERIFY_GENERATIONS, &rule, &settings);
+        pipeline.wait();
+        Ok(pipeline.snapshot())
+    };
+
+    let unshifted = run([0, 0])?;
+    let side = VERIFY_SIZE as i32;
+    let mut expected = vec![0; unshifted.len()];
+    for (i, &cell) in unshifted.iter().enumerate() {
+        let [x, y] = [i as i32 % side, i as i32 / side];
+        let [x, y] = [0, 1].map(|axis| ([x, y][axis] + VERIFY_SHIFT[axis]).rem_euclid(side));
+        expected[(y * side + x) as usize] = cell;
+    }
+
+    let matches = run(VERIFY_SHIFT)? == expected;
+    println!(
+        "shift by {},{}: {}",
+        VERIFY_SHIFT[0],
+        VERIFY_SHIFT[1],
+        if matches { "ok" } else { "MISMATCH" }
+    );
+    Ok(matches)
+}
+
+/// How a headless run ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Extinct,
+    /// The population stopped changing, like a settled soup
+    Stabilized,
+    /// Still changing when the generation limit was reached
+    Running,
+}
+
+/// Summary of a headless run, with the seed and rule to repeat it
+#[derive(Clone, Debug, Serialize)]
+pub struct RunSummary {
+    pub seed: u64,
+    pub density: f32,
+    pub rule: Rule,
+    pub size: [u32; 2],
+    pub generations: u64,
+    pub outcome: RunOutcome,
+    /// Generation the grid died out or its population settled, to the nearest sample
+    pub settled_at: Option<u64>,
+    pub final_population: u64,
+    pub peak_population: u64,
+    pub peak_generation: u64,
+    /// Minimum and maximum (inclusive) coordinates of the live cells at the end
+    pub bounding_box: Option<([u32; 2], [u32; 2])>,
+}
+
+/// Simulate a grid filled from `seed` for up to `max_generations`, sampling the population every
+/// `SAMPLE_INTERVAL` generations. Stops early once it dies out or settles, like a soup search.
+pub fn summarize_run(
+    context: &VulkanoContext,
+    size: [u32; 2],
+    seed: &SeedSettings,
+    rule: Rule,
+    max_generations: u64,
+) -> Result<RunSummary, InitError> {
+    let mut pipeline = GameComputePipeline::new(context, size, LDR_FORMAT)?;
+    pipeline.set_color(false);
+    pipeline.set_collect_stats(true);
+    // Filled like the window fills it, so the same seed gives the same run
+    pipeline.seed(seed);
+    let settings = RenderSettings::default();
+    let before = sync::now(context.device()).boxed();
+    let _ = pipeline.compute_n(before, 0, &rule, &settings);
+
+    let mut generation = 0;
+    let mut stats = None;
+    let start_population = pipeline
+        .snapshot()
+        .iter()
+        .filter(|&&cell| cell != 0)
+        .count() as u64;
+    let mut peak = (start_population, 0);
+    let mut last_population = None;
+    let mut stable_samples = 0;
+    let mut stable_since = 0;
+    let mut outcome = RunOutcome::Running;
+    while generation < max_generations {
+        let batch = (max_generations - generation).min(SAMPLE_INTERVAL as u64) as u32;
+        let before = sync::now(context.device()).boxed();
+        let _ = pipeline.compute_n(before, batch, &rule, &settings);
+        generation += batch as u64;
+        let sample = pipeline
+            .stats()
+            .expect("stats are collected every generation");
+        stats = Some(sample);
+
+        if sample.population > peak.0 {
+            peak = (sample.population, generation);
+        }
+        if last_population == Some(sample.population) {
+            stable_samples += 1;
+        } else {
+            last_population = Some(sample.population);
+            stable_samples = 0;
+            stable_since = generation;
+        }
+        if sample.population == 0 {
+            outcome = RunOutcome::Extinct;
+            break;
+        }
+        if stable_samples >= STABLE_SAMPLES {
+            outcome = RunOutcome::Stabilized;
+            break;
+        }
+    }
+
+    Ok(RunSummary {
+        seed: seed.seed,
+        density: seed.density,
+        rule,
+        size,
+        generations: generation,
+        outcome,
+        settled_at: (outcome != RunOutcome::Running).then_some(stable_since),
+        final_population: stats.map_or(0, |stats| stats.population),
+        peak_population: peak.0,
+        peak_generation: peak.1,
+        bounding_box: stats.and_then(|stats| stats.bounding_box),
+    })
+}
@@ -0,0 +1,238 @@
+use crate::{
+    pattern::{self, Pattern},
+    rule::Rule,
+    self_test,
+};
+
+// Larger live areas can't be any of the catalog patterns, checked before comparing
+const MAX_SIDE: u32 = 32;
+
+// Dead cells around a catalog pattern while its phases are simulated, more than any of them
+// grows or moves in a period
+const PHASE_MARGIN: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::StillLife => "still life",
+            Kind::Oscillator => "oscillator",
+            Kind::Spaceship => "spaceship",
+        }
+    }
+}
+
+/// A well known Conway's Life pattern, in one of its phases
+pub struct NamedPattern {
+    pub name: &'static str,
+    pub kind: Kind,
+    /// Generations until it repeats, 1 for still lifes
+    pub period: u32,
+    /// Plaintext cells
+    cells: &'static str,
+}
+
+impl NamedPattern {
+    /// The listed phase, trimmed to its live cells
+    pub fn pattern(&self) -> Pattern {
+        pattern::parse_plaintext(self.cells, MAX_SIDE)
+            .expect("catalog pattern is valid")
+            .trimmed()
+    }
+}
+
+pub const CATALOG: [NamedPattern; 6] = [
+    NamedPattern {
+        name: "Block",
+        kind: Kind::StillLife,
+        period: 1,
+        cells: "OO\nOO",
+    },
+    NamedPattern {
+        name: "Blinker",
+        kind: Kind::Oscillator,
+        period: 2,
+        cells: "OOO",
+    },
+    NamedPattern {
+        name: "Toad",
+        kind: Kind::Oscillator,
+        period: 2,
+        cells: ".OOO\nOOO.",
+    },
+    NamedPattern {
+        name: "Beacon",
+        kind: Kind::Oscillator,
+        period: 2,
+        cells: "OO..\nOO..\n..OO\n..OO",
+    },
+    NamedPattern {
+        name: "Pulsar",
+        kind: Kind::Oscillator,
+        period: 3,
+        cells: "\
+..OOO...OOO..
+.............
+O....O.O....O
+O....O.O....O
+O....O.O....O
+..OOO...OOO..
+.............
+..OOO...OOO..
+O....O.O....O
+O....O.O....O
+O....O.O....O
+.............
+..OOO...OOO..",
+    },
+    NamedPattern {
+        name: "Glider",
+        kind: Kind::Spaceship,
+        period: 4,
+        cells: ".O.\n..O\nOOO",
+    },
+];
+
+/// The catalog entry called `name`
+pub fn named(name: &str) -> Option<&'static NamedPattern> {
+    CATALOG.iter().find(|entry| entry.name == name)
+}
+
+/// Size and cells of the orientation that sorts first, the same for every rotation and
+/// reflection of a pattern
+type Canonical = ([u32; 2], Vec<u8>);
+
+/// The catalog pattern the live cells of `pattern` form, in any of its phases, at any position
+/// and in any orientation. Only meaningful under Conway's rule.
+pub fn recognize(pattern: &Pattern) -> Option<&'static NamedPattern> {
+    let trimmed = pattern.trimmed();
+    let [width, height] = trimmed.size();
+    if width == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return None;
+    }
+
+    let canonical = canonical(&trimmed);
+    CATALOG.iter().find(|entry| {
+        phases(entry)
+            .iter()
+            .any(|phase| canonical == self::canonical(phase))
+    })
+}
+
+/// Every phase of a catalog pattern trimmed to its live cells, simulated from the listed one
+fn phases(entry: &NamedPattern) -> Vec<Pattern> {
+    let start = entry.pattern();
+    let [width, height] = start.size();
+    let size = [width + 2 * PHASE_MARGIN, height + 2 * PHASE_MARGIN];
+    let mut cells = vec![0; (size[0] * size[1]) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y + PHASE_MARGIN) * size[0] + x + PHASE_MARGIN;
+            cells[index as usize] = start.get(x, y) as u8;
+        }
+    }
+
+    let mut phases = vec![start];
+    for _ in 1..entry.period {
+        cells = self_test::reference_run(&cells, size, &Rule::CONWAY, false, 1);
+        phases.push(Pattern::new(size, cells.clone()).trimmed());
+    }
+    phases
+}
+
+fn canonical(pattern: &Pattern) -> Canonical {
+    (0..8)
+        .map(|orientation| oriented(pattern, orientation))
+        .min()
+        .unwrap()
+}
+
+/// One of the 4 rotations, transposed first for orientations 4 to 7
+fn oriented(pattern: &Pattern, orientation: u32) -> Canonical {
+    let [width, height] = pattern.size();
+    let transposed = orientation >= 4;
+    let size = if transposed {
+        [height, width]
+    } else {
+        [width, height]
+    };
+    // Rotations by a quarter turn swap the sides again
+    let size = if orientation % 2 == 1 {
+        [size[1], size[0]]
+    } else {
+        size
+    };
+
+    let cells = (0..size[1])
+        .flat_map(|y| (0..size[0]).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            // Back to the transposed pattern's coordinates, then to the pattern's
+            let [sw, sh] = [size[0] - 1, size[1] - 1];
+            let [x, y] = match orientation % 4 {
+                0 => [x, y],
+                1 => [y, sw - x],
+                2 => [sw - x, sh - y],
+                _ => [sh - y, x],
+            };
+            let [x, y] = if transposed { [y, x] } else { [x, y] };
+            pattern.get(x, y) as u8
+        })
+        .collect();
+    (size, cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `pattern` with its top left corner at `offset` in an empty grid of `size`
+    fn placed(pattern: &Pattern, size: [u32; 2], offset: [u32; 2]) -> Pattern {
+        let [width, height] = pattern.size();
+        let cells = (0..size[1])
+            .flat_map(|y| (0..size[0]).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let inside = (offset[0]..offset[0] + width).contains(&x)
+                    && (offset[1]..offset[1] + height).contains(&y);
+                (inside && pattern.get(x - offset[0], y - offset[1])) as u8
+            })
+            .collect();
+        Pattern::new(size, cells)
+    }
+
+    #[test]
+    fn every_phase_and_orientation_is_recognized() {
+        for entry in &CATALOG {
+            for phase in phases(entry) {
+                for orientation in 0..8 {
+                    let (size, cells) = oriented(&phase, orientation);
+                    let oriented = Pattern::new(size, cells);
+                    for offset in [[0, 0], [3, 2], [7, 11]] {
+                        let grid = placed(&oriented, [size[0] + 12, size[1] + 14], offset);
+                        let found = recognize(&grid).map(|found| found.name);
+                        assert_eq!(
+                            found,
+                            Some(entry.name),
+                            "orientation {} at {:?}",
+                            orientation,
+                            offset
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn other_shapes_are_not_recognized() {
+        // R-pentomino
+        let r_pentomino = pattern::parse_plaintext(".OO\nOO.\n.O.", MAX_SIDE).unwrap();
+        assert!(recognize(&placed(&r_pentomino, [10, 10], [4, 3])).is_none());
+        assert!(recognize(&Pattern::new([8, 8], vec![0; 64])).is_none());
+    }
+}
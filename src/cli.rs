@@ -0,0 +1,367 @@
+use std::{
+    env,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use lifetime::{
+    bench::{BenchFormat, DEFAULT_RUN_GENERATIONS},
+    rule::Rule,
+    seeding::SeedSource,
+};
+
+use crate::settings::SwapchainImages;
+
+const USAGE: &str = "\
+Usage: lifetime [OPTIONS]
+
+Options:
+    --pattern <FILE>    Start with an .rle or .cells pattern in the center of the grid
+    --stdin             Start paused with an RLE or plaintext pattern read from stdin, e.g.
+                        cat glider.rle | lifetime --stdin
+    --cells <CELLS>     Start with these cells alive, as space separated x,y pairs
+                        (e.g. \"10,10 11,10 12,10\"), - reads them from stdin
+    --seed-from <WHEN>  Fill the grid from a seed taken from the date (a new grid each day)
+                        or the time (a new grid every run) rather than seed 0
+    --swapchain-images <N>
+                        Present through at least 2 (double buffering) or 3 (triple buffering)
+                        swapchain images rather than the surface's minimum
+    --grid <SIDE>       Start with a SIDE x SIDE grid, or auto for the largest that keeps up
+                        with 60 fps on this GPU. Without it the grid is 2000 x 2000 and the
+                        panel shows the suggested size
+    --kiosk             Fullscreen without the panel or cursor, for a screensaver. Seeds from
+                        the date unless --seed-from is given and restarts when the grid dies out
+    --bench             Measure the simulation speed without opening a window
+    --sweep             Benchmark a series of grid sizes from 256 to 8192 (implies --bench)
+    --format <FORMAT>   Benchmark output, table (default) or csv
+    --stats-out <FILE>  Simulate a seeded grid without opening a window until it dies out,
+                        settles or reaches --generations, and write a JSON summary to the file
+    --seed <SEED>       Seed of the grid --stats-out simulates (default 0)
+    --rule <RULE>       Rule --stats-out simulates, like B36/S23 (default B3/S23)
+    --generations <N>   Most generations --stats-out simulates (default 10000)
+    --device-info       Print the device's compute limits and the largest grid it supports
+    --verify-batching   Check that batching generations into one submission matches
+                        submitting them one at a time
+    --verify-shift      Check that shifting the grid doesn't change how a glider moves
+    --test              Check known patterns (gliders, oscillators, still lifes, birth and
+                        death, wrapping) and glider counting against the simulation and exit
+                        with 1 on a failure
+    -h, --help          Print this message";
+
+/// Side of the main window's grid
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GridSide {
+    /// The default side, with the side `bench::suggest_grid` finds shown in the panel
+    Default,
+    /// The side `bench::suggest_grid` finds
+    Auto,
+    /// Skips `bench::suggest_grid`
+    Fixed(u32),
+}
+
+/// How to seed the main window (either option starts from an empty grid instead of a random
+/// one), or whether to run the benchmark instead
+pub struct CliArgs {
+    pub pattern: Option<PathBuf>,
+    /// Pattern text read by `--stdin`, parsed when the grid is seeded so bad input only
+    /// leaves the grid random
+    pub stdin: Option<String>,
+    /// Cells set alive after the pattern is loaded, may lie outside the grid
+    pub cells: Vec<[i64; 2]>,
+    pub seed_source: Option<SeedSource>,
+    pub grid: GridSide,
+    pub kiosk: bool,
+    pub swapchain_images: SwapchainImages,
+    pub bench: bool,
+    /// Benchmark every size in `bench::SWEEP_SIZES` rather than just the default grid
+    pub sweep: bool,
+    pub bench_format: BenchFormat,
+    /// Where to write the summary of a headless run, `None` to skip it
+    pub stats_out: Option<PathBuf>,
+    pub seed: u64,
+    pub rule: Rule,
+    pub generations: u64,
+    pub device_info: bool,
+    pub verify_batching: bool,
+    pub verify_shift: bool,
+    /// Run `self_test::run` and exit
+    pub test: bool,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        Self {
+            pattern: None,
+            stdin: None,
+            cells: vec![],
+            seed_source: None,
+            grid: GridSide::Default,
+            kiosk: false,
+            swapchain_images: SwapchainImages::Default,
+            bench: false,
+            sweep: false,
+            bench_format: BenchFormat::Table,
+            stats_out: None,
+            seed: 0,
+            rule: Rule::default(),
+            generations: DEFAULT_RUN_GENERATIONS,
+            device_info: false,
+            verify_batching: false,
+            verify_shift: false,
+            test: false,
+        }
+    }
+}
+
+impl CliArgs {
+    /// Parse the process arguments, prints the usage and exits on `--help` or a bad argument
+    pub fn parse() -> Self {
+        match Self::try_parse(env::args().skip(1)) {
+            Ok(args) => args,
+            Err(err) => {
+                eprintln!("{}\n\n{}", err, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    fn try_parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--pattern" => {
+                    let path = args.next().ok_or("--pattern needs a file")?;
+                    parsed.pattern = Some(PathBuf::from(path));
+                }
+                "--stdin" => {
+                    let mut text = String::new();
+                    io::stdin()
+                        .read_to_string(&mut text)
+                        .map_err(|err| format!("failed to read the pattern from stdin: {}", err))?;
+                    parsed.stdin = Some(text);
+                }
+                "--cells" => {
+                    let cells = args.next().ok_or("--cells needs a list of cells")?;
+                    let cells = if cells == "-" {
+                        let mut stdin = String::new();
+                        io::stdin()
+                            .read_to_string(&mut stdin)
+                            .map_err(|err| format!("failed to read cells from stdin: {}", err))?;
+                        stdin
+                    } else {
+                        cells
+                    };
+                    parsed.cells.extend(parse_cells(&cells)?);
+                }
+                "--seed-from" => {
+                    parsed.seed_source = match args.next().as_deref() {
+                        Some("date") => Some(SeedSource::Date),
+                        Some("time") => Some(SeedSource::Time),
+                        _ => return Err("--seed-from needs date or time".to_string()),
+                    };
+                }
+                "--grid" => {
+                    parsed.grid = match args.next().as_deref() {
+                        Some("auto") => GridSide::Auto,
+                        side => side
+                            .and_then(|side| side.parse::<u32>().ok())
+                            .filter(|&side| side > 0)
+                            // Rounded up to the workgroup size like the panel does
+                            .map(|side| GridSide::Fixed(side.next_multiple_of(8)))
+                            .ok_or("--grid needs a side in cells or auto")?,
+                    };
+                }
+                "--kiosk" => parsed.kiosk = true,
+                "--swapchain-images" => {
+                    parsed.swapchain_images = match args.next().as_deref() {
+                        Some("2") => SwapchainImages::Double,
+                        Some("3") => SwapchainImages::Triple,
+                        _ => return Err("--swapchain-images needs 2 or 3".to_string()),
+                    };
+                }
+                "--bench" => parsed.bench = true,
+                "--sweep" => {
+                    parsed.bench = true;
+                    parsed.sweep = true;
+                }
+                "--format" => {
+                    parsed.bench_format = match args.next().as_deref() {
+                        Some("table") => BenchFormat::Table,
+                        Some("csv") => BenchFormat::Csv,
+                        _ => return Err("--format needs table or csv".to_string()),
+                    };
+                }
+                "--stats-out" => {
+                    let path = args.next().ok_or("--stats-out needs a file")?;
+                    parsed.stats_out = Some(PathBuf::from(path));
+                }
+                "--seed" => {
+                    parsed.seed = args
+                        .next()
+                        .and_then(|seed| seed.parse().ok())
+                        .ok_or("--seed needs a whole number")?;
+                }
+                "--rule" => {
+                    let rule = args.next().ok_or("--rule needs a rule like B3/S23")?;
+                    parsed.rule = rule.parse()?;
+                }
+                "--generations" => {
+                    parsed.generations = args
+                        .next()
+                        .and_then(|generations| generations.parse().ok())
+                        .ok_or("--generations needs a whole number")?;
+                }
+                "--device-info" => parsed.device_info = true,
+                "--verify-batching" => parsed.verify_batching = true,
+                "--verify-shift" => parsed.verify_shift = true,
+                "--test" => parsed.test = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                arg => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+        if parsed.pattern.is_some() && parsed.stdin.is_some() {
+            return Err("--pattern and --stdin can't both be given".to_string());
+        }
+        Ok(parsed)
+    }
+
+    /// Whether the grid should start empty rather than random
+    pub fn seeds_grid(&self) -> bool {
+        self.pattern.is_some() || !self.cells.is_empty()
+    }
+}
+
+/// Parse whitespace separated `x,y` pairs
+fn parse_cells(text: &str) -> Result<Vec<[i64; 2]>, String> {
+    text.split_whitespace()
+        .map(|pair| {
+            let coordinates = pair
+                .split_once(',')
+                .and_then(|(x, y)| Some([x.trim().parse().ok()?, y.trim().parse().ok()?]));
+            coordinates.ok_or_else(|| format!("invalid cell '{}', expected x,y", pair))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        CliArgs::try_parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_flags_is_the_default() {
+        let parsed = parse(&[]).unwrap();
+        assert!(parsed.grid == GridSide::Default);
+        assert!(!parsed.bench && !parsed.seeds_grid());
+        assert_eq!(parsed.generations, DEFAULT_RUN_GENERATIONS);
+    }
+
+    #[test]
+    fn valid_flags() {
+        let parsed = parse(&[
+            "--pattern",
+            "glider.rle",
+            "--cells",
+            "1,2 -3,4",
+            "--seed-from",
+            "date",
+            "--grid",
+            "100",
+            "--sweep",
+            "--format",
+            "csv",
+            "--seed",
+            "42",
+            "--rule",
+            "B36/S23",
+            "--generations",
+            "500",
+            "--swapchain-images",
+            "3",
+            "--kiosk",
+        ])
+        .unwrap();
+        assert_eq!(parsed.pattern, Some(PathBuf::from("glider.rle")));
+        assert_eq!(parsed.cells, vec![[1, 2], [-3, 4]]);
+        assert_eq!(parsed.seed_source, Some(SeedSource::Date));
+        // Rounded up to a multiple of 8
+        assert!(parsed.grid == GridSide::Fixed(104));
+        assert!(parsed.bench && parsed.sweep);
+        assert!(parsed.bench_format == BenchFormat::Csv);
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.rule, "B36/S23".parse().unwrap());
+        assert_eq!(parsed.generations, 500);
+        assert_eq!(parsed.swapchain_images, SwapchainImages::Triple);
+        assert!(parsed.kiosk);
+        assert!(parse(&["--grid", "auto"]).unwrap().grid == GridSide::Auto);
+    }
+
+    #[test]
+    fn cells_add_up() {
+        let parsed = parse(&["--cells", "0,0", "--cells", "5,6"]).unwrap();
+        assert_eq!(parsed.cells, vec![[0, 0], [5, 6]]);
+    }
+
+    #[test]
+    fn unknown_flag() {
+        assert_eq!(
+            parse(&["--glider"]).err(),
+            Some("unknown argument '--glider'".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_values() {
+        for flag in [
+            "--pattern",
+            "--cells",
+            "--seed-from",
+            "--grid",
+            "--swapchain-images",
+            "--format",
+            "--stats-out",
+            "--seed",
+            "--rule",
+            "--generations",
+        ] {
+            assert!(parse(&[flag]).is_err(), "{} without a value", flag);
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(parse(&["--grid", "0"]).is_err());
+        assert!(parse(&["--grid", "big"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--format", "json"]).is_err());
+        assert!(parse(&["--swapchain-images", "4"]).is_err());
+        assert!(parse(&["--rule", "B3"]).is_err());
+    }
+
+    #[test]
+    fn cell_lists() {
+        assert_eq!(parse_cells(""), Ok(vec![]));
+        assert_eq!(
+            parse_cells("  1,2\n3,4\t-5,-6 "),
+            Ok(vec![[1, 2], [3, 4], [-5, -6]])
+        );
+    }
+
+    #[test]
+    fn malformed_cell_lists() {
+        for text in ["1", "1,", ",2", "1,2,3", "a,b", "1;2", "1.5,2"] {
+            assert_eq!(
+                parse_cells(text),
+                Err(format!("invalid cell '{}', expected x,y", text)),
+            );
+        }
+        // One bad pair fails the whole list
+        assert!(parse_cells("1,2 3").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+
+use vulkano_util::context::VulkanoContext;
+
+use crate::init_error::InitError;
+
+// Bytes per pixel of the largest per cell storage buffer (the vec4 colors)
+const BYTES_PER_CELL: u64 = 16;
+
+/// Vulkan limits of the device that bound the grid size and the compute workgroups
+#[derive(Clone, Debug)]
+pub struct DeviceLimits {
+    pub name: String,
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_work_group_invocations: u32,
+    pub max_compute_work_group_count: [u32; 3],
+    /// In bytes
+    pub max_storage_buffer_range: u32,
+    pub max_image_dimension_2d: u32,
+}
+
+impl DeviceLimits {
+    pub fn query(context: &VulkanoContext) -> Self {
+        let device = context.device();
+        let physical_device = device.physical_device();
+        let properties = physical_device.properties();
+        Self {
+            name: context.device_name().to_string(),
+            max_compute_work_group_size: properties.max_compute_work_group_size,
+            max_compute_work_group_invocations: properties.max_compute_work_group_invocations,
+            max_compute_work_group_count: properties.max_compute_work_group_count,
+            max_storage_buffer_range: properties.max_storage_buffer_range,
+            max_image_dimension_2d: properties.max_image_dimension2_d,
+        }
+    }
+
+    /// Side of the largest square grid the device can simulate, a multiple of the workgroup size
+    pub fn max_grid_side(&self) -> u32 {
+        let buffer_side = ((self.max_storage_buffer_range as u64 / BYTES_PER_CELL) as f64).sqrt();
+        let side = (buffer_side as u32).min(self.max_image_dimension_2d);
+        // One workgroup per 8x8 cells
+        let side = side.min(self.max_compute_work_group_count[0].saturating_mul(8));
+        side / 8 * 8
+    }
+
+    /// Whether every buffer and image of a grid this size fits the limits
+    pub fn fits_grid(&self, size: [u32; 2]) -> bool {
+        let cells = size[0] as u64 * size[1] as u64;
+        size.iter().all(|&side| {
+            side <= self.max_image_dimension_2d
+                && side.div_ceil(8) <= self.max_compute_work_group_count[0]
+        }) && cells * BYTES_PER_CELL <= self.max_storage_buffer_range as u64
+    }
+
+    /// `InitError::GridTooLarge` unless the grid fits the limits, checked before allocating
+    pub fn check_grid(&self, size: [u32; 2]) -> Result<(), InitError> {
+        if self.fits_grid(size) {
+            Ok(())
+        } else {
+            Err(InitError::GridTooLarge {
+                size,
+                max_side: self.max_grid_side(),
+            })
+        }
+    }
+
+    /// Label and value of every limit, in display order
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let [x, y, z] = self.max_compute_work_group_size;
+        let [count_x, count_y, count_z] = self.max_compute_work_group_count;
+        vec![
+            ("Device", self.name.clone()),
+            ("Max workgroup size", format!("{} x {} x {}", x, y, z)),
+            (
+                "Max workgroup invocations",
+                self.max_compute_work_group_invocations.to_string(),
+            ),
+            (
+                "Max workgroup count",
+                format!("{} x {} x {}", count_x, count_y, count_z),
+            ),
+            (
+                "Max storage buffer range",
+                format!("{} MiB", self.max_storage_buffer_range / (1024 * 1024)),
+            ),
+            (
+                "Max image dimension 2D",
+                self.max_image_dimension_2d.to_string(),
+            ),
+            ("Max grid size", format!("{0} x {0}", self.max_grid_side())),
+        ]
+    }
+}
+
+impl fmt::Display for DeviceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (label, value) in self.rows() {
+            writeln!(f, "{:<26} {}", format!("{}:", label), value)?;
+        }
+        Ok(())
+    }
+}
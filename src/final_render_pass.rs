@@ -30,6 +30,11 @@ use vulkano_util::{
     renderer::{DeviceImageView, SwapchainImageView},
 };
 
+use lifetime::{
+    game_compute_pipeline::{CellShape, RenderSettings, WORKGROUP_SIZE},
+    init_error::InitError,
+};
+
 /// Simple read-only buffer type
 type Buffer<T> = Arc<CpuAccessibleBuffer<[T]>>;
 
@@ -42,6 +47,10 @@ struct QuadVertex {
 impl_vertex!(QuadVertex, position, tex_coords);
 
 pub type ViewportTransform = vs::ty::Uniforms;
+type Overlay = fs::ty::Overlay;
+
+// How far from a whole number of pixels per texel the zoom may be for the offset to snap, relative
+const SNAP_TOLERANCE: f32 = 0.02;
 
 pub struct FinalRenderPass {
     device: Arc<Device>,
@@ -51,37 +60,64 @@ pub struct FinalRenderPass {
 
     // Viewport quad
     vertex_buffer: Buffer<QuadVertex>,
+    // Viewport quad with the image repeated once on every side, drawn when the grid wraps
+    tiled_vertex_buffer: Buffer<QuadVertex>,
     index_buffer: Buffer<u32>,
+    clamp_sampler: Arc<Sampler>,
+    repeat_sampler: Arc<Sampler>,
     uniform_buffer: CpuBufferPool<ViewportTransform>,
+    overlay_buffer: CpuBufferPool<Overlay>,
 }
 
 impl FinalRenderPass {
-    pub fn new(context: &VulkanoContext, format: Format) -> Self {
-        let render_pass = Self::create_render_pass(context.device(), format);
-        let pipeline = Self::create_pipeline(context.device(), render_pass.clone());
-        let (vertex_buffer, index_buffer) = Self::create_viewport_quad(context.device());
+    pub fn new(context: &VulkanoContext, format: Format) -> Result<Self, InitError> {
+        let render_pass = Self::create_render_pass(context.device(), format)?;
+        let pipeline = Self::create_pipeline(context.device(), render_pass.clone())?;
+        let (vertex_buffer, index_buffer) = Self::create_viewport_quad(context.device(), 0.0)?;
+        let (tiled_vertex_buffer, _) = Self::create_viewport_quad(context.device(), 1.0)?;
+        let clamp_sampler =
+            Self::create_sampler(context.device(), SamplerAddressMode::ClampToBorder)?;
+        let repeat_sampler = Self::create_sampler(context.device(), SamplerAddressMode::Repeat)?;
         let uniform_buffer =
             CpuBufferPool::<ViewportTransform>::new(context.device(), BufferUsage::all());
+        let overlay_buffer = CpuBufferPool::<Overlay>::new(context.device(), BufferUsage::all());
 
-        Self {
+        Ok(Self {
             device: context.device(),
             graphics_queue: context.graphics_queue(),
             render_pass,
             pipeline,
             vertex_buffer,
+            tiled_vertex_buffer,
             index_buffer,
+            clamp_sampler,
+            repeat_sampler,
             uniform_buffer,
-        }
+            overlay_buffer,
+        })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render<F>(
         &self,
         before_future: F,
         target: SwapchainImageView,
         gui: &mut Gui,
         viewport_view: DeviceImageView,
+        bloom_view: DeviceImageView,
         viewport_bounds: Viewport,
         viewport_transform: ViewportTransform,
+        settings: &RenderSettings,
+        // Show the grid repeating past its edges, matching a simulation that wraps around
+        wrap: bool,
+        // Draw the cells as hexagons, odd rows shifted half a cell right
+        hex: bool,
+        // The image holds cell states to color here (see `GameComputePipeline::raw_states`)
+        raw_states: bool,
+        // Minimum and maximum (inclusive) grid coordinates of the live cells, outlined if set
+        bounding_box: Option<([u32; 2], [u32; 2])>,
+        // Image to morph from into `viewport_view` and how far along the morph is, `0.0..=1.0`
+        morph: Option<(DeviceImageView, f32)>,
     ) -> Box<dyn GpuFuture>
     where
         F: GpuFuture + 'static,
@@ -130,20 +166,116 @@ impl FinalRenderPass {
         )
         .unwrap();
 
-        let descriptor_set = self.create_descriptor_set(viewport_view, viewport_transform);
-        secondary_builder
-            .bind_pipeline_graphics(self.pipeline.clone())
-            .set_viewport(0, vec![viewport_bounds])
-            .bind_vertex_buffers(0, self.vertex_buffer.clone())
-            .bind_index_buffer(self.index_buffer.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                0,
-                descriptor_set,
-            )
-            .draw_indexed(self.index_buffer.len() as u32, 1, 0, 0, 0)
-            .unwrap();
+        // A viewport without area (the panel covers the window) is invalid, only draw the gui
+        if viewport_bounds.dimensions.iter().all(|&side| side >= 1.0) {
+            let grid_size = viewport_view.image().dimensions().width_height();
+            let push_constants = fs::ty::PushConstants {
+                background_color: settings.grid_color,
+                life_color: settings.life_color,
+                dead_color: settings.dead_color,
+                highlight_color: settings.highlight_color,
+                wall_color: settings.wall_color,
+                grid_size: [grid_size[0] as i32, grid_size[1] as i32],
+                cell_shape: settings.cell_shape as i32,
+                antialias: settings.antialias_cells as i32,
+                tone_map: settings.hdr as i32,
+                wrap: wrap as i32,
+                hex: hex as i32,
+                bloom_intensity: if settings.bloom && settings.mode.has_effects() {
+                    settings.bloom_intensity
+                } else {
+                    0.0
+                },
+                // Each image pixel covers a block of `decimation` cells squared
+                debug_tile: if settings.debug_tiles {
+                    WORKGROUP_SIZE as f32 / settings.decimation.max(1) as f32
+                } else {
+                    0.0
+                },
+                raw_states: raw_states as i32,
+                morph: morph
+                    .as_ref()
+                    .map_or(1.0, |(_, progress)| progress.clamp(0.0, 1.0)),
+            };
+
+            // In image pixels, the box edges run along the outside of the edge cells
+            let decimation = settings.decimation.max(1) as f32;
+            let (box_min, box_max) = bounding_box.unwrap_or_default();
+            // Toward the light in image pixels, y grows downwards
+            let light = settings.light_angle.to_radians();
+            let bevel_depth = if settings.bevel && settings.cell_shape != CellShape::Pixel {
+                settings.bevel_depth
+            } else {
+                0.0
+            };
+            let [red, green, blue] = [0, 1, 2].map(|i| {
+                if settings.channels_visible[i] {
+                    settings.channel_opacity[i].clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            });
+            let overlay = Overlay {
+                crosshair_color: settings.crosshair_color,
+                box_color: settings.bounding_box_color,
+                bounds: [
+                    box_min[0] as f32 / decimation,
+                    box_min[1] as f32 / decimation,
+                    (box_max[0] + 1) as f32 / decimation,
+                    (box_max[1] + 1) as f32 / decimation,
+                ],
+                bevel: [light.cos(), -light.sin(), bevel_depth, 0.0],
+                channels: [red, green, blue, 0.0],
+                viewport: [
+                    viewport_bounds.origin[0],
+                    viewport_bounds.origin[1],
+                    viewport_bounds.dimensions[0],
+                    viewport_bounds.dimensions[1],
+                ],
+                vignette: if settings.vignette {
+                    [
+                        settings.vignette_intensity.clamp(0.0, 1.0),
+                        // The falloff needs some room before the corners
+                        settings.vignette_radius.clamp(0.0, 0.95),
+                        0.0,
+                        0.0,
+                    ]
+                } else {
+                    [0.0; 4]
+                },
+                crosshair: settings.crosshair as i32,
+                axes: settings.axes as i32,
+                bounding_box: bounding_box.is_some() as i32,
+            };
+            let previous_view = morph.map_or(viewport_view.clone(), |(view, _)| view);
+            let descriptor_set = self.create_descriptor_set(
+                viewport_view,
+                bloom_view,
+                previous_view,
+                viewport_transform,
+                overlay,
+                wrap,
+            );
+            let vertex_buffer = if wrap {
+                self.tiled_vertex_buffer.clone()
+            } else {
+                self.vertex_buffer.clone()
+            };
+            secondary_builder
+                .bind_pipeline_graphics(self.pipeline.clone())
+                .set_viewport(0, vec![viewport_bounds])
+                .bind_vertex_buffers(0, vertex_buffer)
+                .bind_index_buffer(self.index_buffer.clone())
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    self.pipeline.layout().clone(),
+                    0,
+                    descriptor_set,
+                )
+                .push_constants(self.pipeline.layout().clone(), 0, push_constants)
+                .draw_indexed(self.index_buffer.len() as u32, 1, 0, 0, 0)
+                .unwrap();
+        }
 
         // Render viewport
         let cb = secondary_builder.build().unwrap();
@@ -174,18 +306,17 @@ impl FinalRenderPass {
     fn create_descriptor_set(
         &self,
         viewport_view: Arc<dyn ImageViewAbstract>,
+        bloom_view: Arc<dyn ImageViewAbstract>,
+        previous_view: Arc<dyn ImageViewAbstract>,
         viewport_transform: ViewportTransform,
+        overlay: Overlay,
+        wrap: bool,
     ) -> Arc<PersistentDescriptorSet> {
-        let sampler = Sampler::new(
-            self.graphics_queue.device().clone(),
-            SamplerCreateInfo {
-                mag_filter: Filter::Linear,
-                min_filter: Filter::Linear,
-                address_mode: [SamplerAddressMode::ClampToBorder; 3],
-                ..Default::default()
-            },
-        )
-        .unwrap();
+        let sampler = if wrap {
+            self.repeat_sampler.clone()
+        } else {
+            self.clamp_sampler.clone()
+        };
 
         let uniform_buffer_subbuffer = self.uniform_buffer.next(viewport_transform).unwrap();
 
@@ -194,13 +325,35 @@ impl FinalRenderPass {
             layout.clone(),
             [
                 WriteDescriptorSet::buffer(0, uniform_buffer_subbuffer),
-                WriteDescriptorSet::image_view_sampler(1, viewport_view.clone(), sampler),
+                WriteDescriptorSet::image_view_sampler(1, viewport_view.clone(), sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(2, bloom_view, sampler.clone()),
+                WriteDescriptorSet::buffer(3, self.overlay_buffer.next(overlay).unwrap()),
+                WriteDescriptorSet::image_view_sampler(4, previous_view, sampler),
             ],
         )
         .unwrap()
     }
 
-    fn create_render_pass(device: Arc<Device>, format: Format) -> Arc<RenderPass> {
+    fn create_sampler(
+        device: Arc<Device>,
+        address_mode: SamplerAddressMode,
+    ) -> Result<Arc<Sampler>, InitError> {
+        Sampler::new(
+            device,
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [address_mode; 3],
+                ..Default::default()
+            },
+        )
+        .map_err(|err| InitError::creation("a sampler", err))
+    }
+
+    fn create_render_pass(
+        device: Arc<Device>,
+        format: Format,
+    ) -> Result<Arc<RenderPass>, InitError> {
         vulkano::ordered_passes_renderpass!(
             device,
             attachments: {
@@ -216,12 +369,17 @@ impl FinalRenderPass {
                 { color: [color], depth_stencil: {}, input: [] }  // Gui render pass
             ]
         )
-        .expect("error creating final render pass")
+        .map_err(|err| InitError::creation("the final render pass", err))
     }
 
-    fn create_pipeline(device: Arc<Device>, render_pass: Arc<RenderPass>) -> Arc<GraphicsPipeline> {
-        let vs = vs::load(device.clone()).expect("failed to create shader module");
-        let fs = fs::load(device.clone()).expect("failed to create shader module");
+    fn create_pipeline(
+        device: Arc<Device>,
+        render_pass: Arc<RenderPass>,
+    ) -> Result<Arc<GraphicsPipeline>, InitError> {
+        let vs = vs::load(device.clone())
+            .map_err(|err| InitError::creation("the vertex shader", err))?;
+        let fs = fs::load(device.clone())
+            .map_err(|err| InitError::creation("the fragment shader", err))?;
 
         GraphicsPipeline::start()
             .vertex_input_state(BuffersDefinition::new().vertex::<QuadVertex>())
@@ -231,26 +389,33 @@ impl FinalRenderPass {
             .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
             .render_pass(Subpass::from(render_pass, 0).unwrap())
             .build(device)
-            .expect("error creating pipeline")
+            .map_err(|err| InitError::creation("the graphics pipeline", err))
     }
 
-    fn create_viewport_quad(device: Arc<Device>) -> (Buffer<QuadVertex>, Buffer<u32>) {
+    /// Quad covering the image plus `margin` image sizes on every side, the position to texture
+    /// coordinate mapping is the same for any margin
+    fn create_viewport_quad(
+        device: Arc<Device>,
+        margin: f32,
+    ) -> Result<(Buffer<QuadVertex>, Buffer<u32>), InitError> {
+        let (low, high) = (-margin, 1.0 + margin);
+        let (low_pos, high_pos) = (low * 2.0 - 1.0, high * 2.0 - 1.0);
         let verticies = vec![
             QuadVertex {
-                position: [-1.0, -1.0],
-                tex_coords: [0.0, 0.0],
+                position: [low_pos, low_pos],
+                tex_coords: [low, low],
             },
             QuadVertex {
-                position: [-1.0, 1.0],
-                tex_coords: [0.0, 1.0],
+                position: [low_pos, high_pos],
+                tex_coords: [low, high],
             },
             QuadVertex {
-                position: [1.0, 1.0],
-                tex_coords: [1.0, 1.0],
+                position: [high_pos, high_pos],
+                tex_coords: [high, high],
             },
             QuadVertex {
-                position: [1.0, -1.0],
-                tex_coords: [1.0, 0.0],
+                position: [high_pos, low_pos],
+                tex_coords: [high, low],
             },
         ];
         let vertex_buffer = CpuAccessibleBuffer::<[QuadVertex]>::from_iter(
@@ -259,7 +424,7 @@ impl FinalRenderPass {
             false,
             verticies,
         )
-        .expect("failed to create quad vertex buffer");
+        .map_err(|err| InitError::allocation("the quad vertex buffer", err))?;
 
         let indicies = vec![0, 2, 1, 0, 3, 2];
         let index_buffer = CpuAccessibleBuffer::<[u32]>::from_iter(
@@ -268,9 +433,9 @@ impl FinalRenderPass {
             false,
             indicies,
         )
-        .expect("failed to create quad index buffer");
+        .map_err(|err| InitError::allocation("the quad index buffer", err))?;
 
-        (vertex_buffer, index_buffer)
+        Ok((vertex_buffer, index_buffer))
     }
 
     pub fn viewport_subpass(&self) -> Subpass {
@@ -305,8 +470,9 @@ void main() {
         ",
         types_meta: {
             use bytemuck::{Pod, Zeroable};
+            use serde::{Deserialize, Serialize};
 
-            #[derive(Clone, Copy, Zeroable, Pod)]
+            #[derive(Clone, Copy, Zeroable, Pod, Serialize, Deserialize)]
         }
     }
 }
@@ -321,6 +487,69 @@ impl Default for vs::ty::Uniforms {
     }
 }
 
+impl vs::ty::Uniforms {
+    /// Texture coordinates (`0.0..=1.0` across the image) under a point in normalized device
+    /// coordinates, the inverse of the vertex shader transform
+    pub fn tex_coords(&self, ndc: [f32; 2]) -> [f32; 2] {
+        let x = (ndc[0] - self.offset[0]) / self.scale;
+        let y = (ndc[1] - self.offset[1]) / (self.scale * self.aspect_ratio);
+        [(x + 1.0) / 2.0, (y + 1.0) / 2.0]
+    }
+
+    /// Normalized device coordinates of a point at `tex_coords` in the image
+    pub fn ndc(&self, tex_coords: [f32; 2]) -> [f32; 2] {
+        let [x, y] = tex_coords.map(|t| t * 2.0 - 1.0);
+        [
+            self.scale * x + self.offset[0],
+            self.scale * y * self.aspect_ratio + self.offset[1],
+        ]
+    }
+
+    /// This transform with the aspect ratio of a viewport `viewport` pixels in size, which draws
+    /// the image square in it
+    pub fn fitted(&self, viewport: [f32; 2]) -> Self {
+        Self {
+            aspect_ratio: viewport[0] / viewport[1],
+            ..*self
+        }
+    }
+
+    /// Width and height in pixels the image is drawn at in a viewport `viewport` pixels in size
+    pub fn drawn_size(&self, viewport: [f32; 2]) -> [f32; 2] {
+        [
+            self.scale * viewport[0],
+            self.scale * self.aspect_ratio * viewport[1],
+        ]
+    }
+
+    /// This transform with the offset rounded so texel edges fall on whole pixels of a viewport
+    /// `viewport` pixels in size, moving in steps of whole texels. Only along axes where the
+    /// scale puts a whole number of pixels on each texel of `image_size` or texels on each pixel.
+    pub fn snapped(&self, viewport: [f32; 2], image_size: [u32; 2]) -> Self {
+        let mut snapped = *self;
+        // Half the image size in normalized device coordinates
+        let extent = [self.scale, self.scale * self.aspect_ratio];
+        for i in 0..2 {
+            let texel = extent[i] * viewport[i] / image_size[i] as f32;
+            let ratio = if texel >= 1.0 { texel } else { 1.0 / texel };
+            if !ratio.is_finite() || (ratio - ratio.round()).abs() > SNAP_TOLERANCE * ratio {
+                continue;
+            }
+            let step = texel.max(1.0).round() * 2.0 / viewport[i];
+            let edge = self.offset[i] - extent[i] + 1.0;
+            snapped.offset[i] = (edge / step).round() * step + extent[i] - 1.0;
+        }
+        snapped
+    }
+
+    /// Offset that puts the point at `tex_coords` (`0.0..=1.0` across the image) in the center
+    /// of the viewport at the current scale
+    pub fn centering_offset(&self, tex_coords: [f32; 2]) -> [f32; 2] {
+        let [x, y] = tex_coords.map(|t| t * 2.0 - 1.0);
+        [-self.scale * x, -self.scale * y * self.aspect_ratio]
+    }
+}
+
 mod fs {
     vulkano_shaders::shader! {
         ty: "fragment",
@@ -331,10 +560,258 @@ layout(location = 0) in vec2 v_tex_coords;
 layout(location = 0) out vec4 f_color;
 
 layout(set = 0, binding = 1) uniform sampler2D tex;
+// Glow around the bright cells, added on top before tone mapping
+layout(set = 0, binding = 2) uniform sampler2D bloom;
+
+layout(push_constant) uniform PushConstants {
+    // Drawn in the gaps between shaped cells
+    vec4 background_color;
+    // Colors of the cell states when raw_states is set
+    vec4 life_color;
+    vec4 dead_color;
+    vec4 highlight_color;
+    vec4 wall_color;
+    ivec2 grid_size;
+    // Index into CellShape, 0 samples the image directly
+    int cell_shape;
+    int antialias;
+    int tone_map;
+    float bloom_intensity;
+    // The image repeats past its edges (sampled with a repeating sampler)
+    int wrap;
+    // Cells are hexagons in odd-r offset coordinates
+    int hex;
+    // Side of the workgroup tiles in image pixels, outlined if over 0
+    float debug_tile;
+    // The image holds alive in red, the change highlight in green and walls in blue
+    int raw_states;
+    // How far to blend from the previous image to this one, 1 shows only this one
+    float morph;
+} push_constants;
+
+// The image before the last steps, morphed into tex for slow motion
+layout(set = 0, binding = 4) uniform sampler2D previous;
+
+// Markers drawn on top of the grid, the cell bevel, the channel mask and the vignette (out of room
+// in the push constants)
+layout(set = 0, binding = 3) uniform Overlay {
+    vec4 crosshair_color;
+    vec4 box_color;
+    // Corners of the live cell bounding box in image pixels, drawn if bounding_box is set
+    vec4 bounds;
+    // Direction towards the light in xy and the bevel width in cells in z, no bevel if it's 0
+    vec4 bevel;
+    // Weight of each color channel over the background, 0 hides it
+    vec4 channels;
+    // Origin in xy and size in zw of the viewport in framebuffer pixels
+    vec4 viewport;
+    // Darkening at the viewport corners in x and where it starts in y (a fraction of the way from
+    // the center to the corners), none if x is 0
+    vec4 vignette;
+    int crosshair;
+    int axes;
+    int bounding_box;
+} overlay;
+
+const vec4 DEBUG_TILE_COLOR = vec4(1.0, 0.0, 1.0, 1.0);
+
+// Length in screen pixels of each arm of the center crosshair
+const float CROSSHAIR_ARM = 12.0;
+
+// Brightening of the edges facing the light and darkening of the ones facing away
+const float BEVEL_CONTRAST = 0.5;
+
+// Colors up to TONE_MAP_KNEE are left as is, brighter ones are compressed smoothly towards 1.0
+const float TONE_MAP_KNEE = 0.8;
+
+vec4 tone_map(vec4 color) {
+    vec3 over = max(color.rgb - TONE_MAP_KNEE, 0.0);
+    vec3 compressed = TONE_MAP_KNEE + (1.0 - TONE_MAP_KNEE) * (1.0 - exp(-over / (1.0 - TONE_MAP_KNEE)));
+    return vec4(min(color.rgb, compressed), color.a);
+}
+
+// Same mapping as the color pass of the compute shader, filtered states blend the colors
+vec4 cell_color(vec4 texel) {
+    if (push_constants.raw_states == 0) {
+        return texel;
+    }
+
+    vec4 color = mix(push_constants.dead_color, push_constants.life_color, texel.r);
+    color = mix(color, push_constants.highlight_color, texel.g);
+    return mix(color, push_constants.wall_color, 0.75 * texel.b);
+}
+
+// The image at a point, part way through the morph from the previous one
+vec4 morphed(vec2 tex_coords) {
+    return mix(texture(previous, tex_coords), texture(tex, tex_coords), push_constants.morph);
+}
+
+vec4 morphed_texel(ivec2 index) {
+    return mix(texelFetch(previous, index, 0), texelFetch(tex, index, 0), push_constants.morph);
+}
+
+// Signed distance (in cells) from a point relative to the cell center to the edge of the cell shape
+float cell_distance(vec2 p) {
+    if (push_constants.cell_shape == 3) {
+        return length(p) - 0.45;
+    }
+
+    float radius = push_constants.cell_shape == 2 ? 0.15 : 0.0;
+    vec2 q = abs(p) - vec2(0.45 - radius);
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+// Light a shaped cell as if its edges were sloped, scaling the color so dark cells stay dark. Fades
+// out while the bevel is under a couple of pixels wide.
+vec4 bevel(vec4 color, vec2 local, float d, float pixel) {
+    float depth = overlay.bevel.z;
+    if (depth <= 0.0) {
+        return color;
+    }
+
+    // The outward normal of the shape, from the gradient of its distance
+    vec2 e = vec2(0.01, 0.0);
+    vec2 gradient = vec2(
+        cell_distance(local + e.xy) - cell_distance(local - e.xy),
+        cell_distance(local + e.yx) - cell_distance(local - e.yx)
+    );
+    vec2 normal = length(gradient) > 0.0 ? normalize(gradient) : vec2(0.0);
+    float slope = 1.0 - clamp(-d / depth, 0.0, 1.0);
+    float visible = smoothstep(1.0, 3.0, depth / pixel);
+    float light = BEVEL_CONTRAST * slope * visible * dot(normal, overlay.bevel.xy);
+    return vec4(color.rgb * (1.0 + light), color.a);
+}
+
+// Find the hex cell containing a point, the one with the nearest center. Rows are a cell apart
+// so the hexagons are slightly stretched vertically, keeping the image to cell mapping square.
+void nearest_hex(vec2 cell, out ivec2 index, out vec2 local) {
+    float best = 1e9;
+    for (int dy = -1; dy <= 1; dy++) {
+        int row = int(floor(cell.y)) + dy;
+        float shift = (row & 1) == 1 ? 0.5 : 0.0;
+        for (int dx = -1; dx <= 1; dx++) {
+            int column = int(floor(cell.x - shift)) + dx;
+            vec2 offset = cell - vec2(float(column) + shift + 0.5, float(row) + 0.5);
+            float dist = dot(offset, offset);
+            if (dist < best) {
+                best = dist;
+                index = ivec2(column, row);
+                local = offset;
+            }
+        }
+    }
+}
+
+// Draw the workgroup tile boundaries over a color, about a pixel wide
+vec4 tile_overlay(vec4 color, vec2 cell, vec2 pixel) {
+    if (push_constants.debug_tile <= 0.0) {
+        return color;
+    }
+
+    vec2 tile = fract(cell / push_constants.debug_tile);
+    vec2 dist = min(tile, 1.0 - tile) * push_constants.debug_tile;
+    return any(lessThan(dist, pixel)) ? mix(color, DEBUG_TILE_COLOR, 0.75) : color;
+}
+
+// Mark the grid center (the top left corner of the cell at half the grid size) with lines a
+// pixel wide, they run along cell edges so cells stay visible however far zoomed in
+vec4 center_overlay(vec4 color, vec2 cell, vec2 pixel) {
+    if (overlay.crosshair == 0 && overlay.axes == 0) {
+        return color;
+    }
+
+    // Distance to the center along each axis in screen pixels
+    vec2 dist = abs(cell - vec2(push_constants.grid_size / 2)) / pixel;
+    bool on_axis = min(dist.x, dist.y) < 0.5;
+    bool marked = on_axis && (overlay.axes != 0 || (overlay.crosshair != 0 && max(dist.x, dist.y) < CROSSHAIR_ARM));
+    return marked ? mix(color, vec4(overlay.crosshair_color.rgb, 1.0), overlay.crosshair_color.a) : color;
+}
+
+// Outline the bounding box of the live cells with an antialiased line about a pixel wide
+vec4 box_overlay(vec4 color, vec2 cell, vec2 pixel) {
+    if (overlay.bounding_box == 0) {
+        return color;
+    }
+
+    // Distances to the edges in screen pixels, positive inside the box
+    vec2 low = (cell - overlay.bounds.xy) / pixel;
+    vec2 high = (overlay.bounds.zw - cell) / pixel;
+    vec2 outside = max(-low, 0.0) + max(-high, 0.0);
+    float dist = max(outside.x, outside.y) > 0.0
+        ? length(outside)
+        : min(min(low.x, low.y), min(high.x, high.y));
+    float coverage = 1.0 - smoothstep(0.5, 1.5, dist);
+    return mix(color, vec4(overlay.box_color.rgb, 1.0), overlay.box_color.a * coverage);
+}
+
+// Blend each channel over the background by its weight, so hidden channels show the background
+vec4 channel_mask(vec4 color) {
+    return vec4(mix(push_constants.background_color.rgb, color.rgb, overlay.channels.rgb), color.a);
+}
+
+// Darken towards the edges of the viewport, last so it covers the glow, background and markers
+vec4 vignette(vec4 color) {
+    if (overlay.vignette.x <= 0.0) {
+        return color;
+    }
+
+    // 0 at the viewport center and 1 at its corners
+    vec2 p = (gl_FragCoord.xy - overlay.viewport.xy) / overlay.viewport.zw * 2.0 - 1.0;
+    float dist = length(p) / sqrt(2.0);
+    float shade = overlay.vignette.x * smoothstep(overlay.vignette.y, 1.0, dist);
+    return vec4(color.rgb * (1.0 - shade), color.a);
+}
+
+// All the markers drawn over the grid
+vec4 overlays(vec4 color, vec2 cell, vec2 pixel) {
+    return tile_overlay(box_overlay(center_overlay(color, cell, pixel), cell, pixel), cell, pixel);
+}
 
 void main() {
-    f_color = texture(tex, v_tex_coords);
+    vec4 glow = vec4(push_constants.bloom_intensity * texture(bloom, v_tex_coords).rgb, 0.0);
+    vec4 filtered = cell_color(morphed(v_tex_coords));
+    vec2 cell = v_tex_coords * vec2(push_constants.grid_size);
+    // Screen pixel size in cells, taken before any branching so the derivatives are defined
+    vec2 cell_width = fwidth(cell);
+    ivec2 cell_index = ivec2(floor(cell));
+    vec2 local = fract(cell) - 0.5;
+    if (push_constants.hex != 0) {
+        nearest_hex(cell, cell_index, local);
+    }
+
+    if (push_constants.wrap != 0) {
+        cell_index = ivec2(mod(vec2(cell_index), vec2(push_constants.grid_size)));
+    }
+
+    bool outside = any(lessThan(cell_index, ivec2(0))) || any(greaterThanEqual(cell_index, push_constants.grid_size));
+    if ((push_constants.cell_shape == 0 && push_constants.hex == 0) || outside) {
+        filtered = channel_mask(filtered + glow);
+        filtered = push_constants.tone_map != 0 ? tone_map(filtered) : filtered;
+        f_color = vignette(overlays(filtered, cell, cell_width));
+        return;
+    }
+
+    // Size of a screen pixel in cells
+    float pixel = max(cell_width.x, cell_width.y);
+
+    // Hexagons without a shape fill their whole tile
+    float d = push_constants.cell_shape == 0 ? -1.0 : cell_distance(local);
+    float coverage = push_constants.antialias != 0
+        ? 1.0 - smoothstep(-0.5 * pixel, 0.5 * pixel, d)
+        : (d < 0.0 ? 1.0 : 0.0);
+    vec4 fill = bevel(cell_color(morphed_texel(cell_index)), local, d, pixel);
+    vec4 shaped = mix(push_constants.background_color, fill, coverage);
+
+    // Shapes smaller than a couple of pixels only shimmer, fade to the filtered image instead
+    vec4 color = channel_mask(mix(shaped, filtered, smoothstep(0.25, 0.75, pixel)) + glow);
+    color = push_constants.tone_map != 0 ? tone_map(color) : color;
+    f_color = vignette(overlays(color, cell, cell_width));
 }
-"
+",
+        types_meta: {
+            use bytemuck::{Pod, Zeroable};
+
+            #[derive(Clone, Copy, Zeroable, Pod)]
+        }
     }
 }
@@ -1,134 +1,1742 @@
-use rand::Rng;
+use crate::device_info::DeviceLimits;
+use crate::gradient::{Gradient, MAX_GRADIENT_STOPS};
+use crate::history;
+use crate::init_error::InitError;
+use crate::pattern::Pattern;
+use crate::readback::{FrameFence, Readback};
+use crate::rule::{BlockRule, KernelRule, LeniaRule, Rule, MAX_KERNEL_RADIUS, MAX_LENIA_RADIUS};
+use crate::seeding::{self, SeedMode, SeedSettings};
+use half::f16;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::BufferCopy;
 use vulkano::command_buffer::CommandBufferUsage;
+use vulkano::command_buffer::CopyBufferInfo;
+use vulkano::command_buffer::CopyBufferInfoTyped;
+use vulkano::command_buffer::CopyImageInfo;
+use vulkano::command_buffer::CopyImageToBufferInfo;
+use vulkano::command_buffer::FillBufferInfo;
 use vulkano::command_buffer::PrimaryAutoCommandBuffer;
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::descriptor_set::WriteDescriptorSet;
-use vulkano::image::ImageAccess;
 use vulkano::pipeline::ComputePipeline;
 use vulkano::pipeline::Pipeline;
 use vulkano::pipeline::PipelineBindPoint;
 use vulkano::{
-    buffer::{BufferUsage, CpuAccessibleBuffer},
+    buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, CpuBufferPool, DeviceLocalBuffer},
     device::{Device, Queue},
     format::Format,
     image::{ImageUsage, StorageImage},
-    sync::GpuFuture,
+    sync::{self, GpuFuture},
 };
 use vulkano_util::{context::VulkanoContext, renderer::DeviceImageView};
 
+/// Side of the square of cells (or pixels for the coloring passes) each workgroup covers, the
+/// local size the shader is specialized with
+pub const WORKGROUP_SIZE: u32 = 8;
+
+/// Default 8 bit output
+pub const LDR_FORMAT: Format = Format::R8G8B8A8_UNORM;
+/// Half float output for colors outside `0.0..=1.0`, tone mapped when presented
+pub const HDR_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+type Palette = compute_life_cs::ty::Palette;
+type StatsBuffer = compute_life_cs::ty::StatsBuffer;
+type KernelBuffer = compute_life_cs::ty::KernelBuffer;
+type BlockBuffer = compute_life_cs::ty::BlockBuffer;
+type LeniaBuffer = compute_life_cs::ty::LeniaBuffer;
+
+// Stats copies that can be in flight at once, results arrive this many frames late at most
+const STATS_READBACK_SLOTS: usize = 3;
+
+// Generations per submission in `benchmark`, waited on so the time covers the GPU work
+const BENCHMARK_BATCH: u32 = 16;
+
+// Matches the size of the weights array in the shader
+const MAX_KERNEL_WEIGHTS: usize =
+    ((2 * MAX_KERNEL_RADIUS + 1) * (2 * MAX_KERNEL_RADIUS + 1)) as usize;
+const MAX_LENIA_WEIGHTS: usize = ((2 * MAX_LENIA_RADIUS + 1) * (2 * MAX_LENIA_RADIUS + 1)) as usize;
+// Lenia values below this are stored as 0, which keeps any from having the bits of 1
+const LENIA_MIN_VALUE: f32 = 1e-6;
+
+/// How the simulation state is turned into colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderMode {
+    /// Color each cell by its own state
+    Cells,
+    /// Color each cell by the population of the window around it
+    Density,
+    /// Color each cell by how it changed in the last step, stays put while paused
+    Diff,
+    /// The cell buffer as it is, white for 1, black for 0 and magenta for anything else, without
+    /// the palette, trails, temporal filter or bloom. Changes are still highlighted.
+    Raw,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 4] = [
+        RenderMode::Cells,
+        RenderMode::Density,
+        RenderMode::Diff,
+        RenderMode::Raw,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderMode::Cells => "Cells",
+            RenderMode::Density => "Density map",
+            RenderMode::Diff => "Diff",
+            RenderMode::Raw => "Raw buffer",
+        }
+    }
+
+    /// Whether the trails, temporal filter and bloom are applied on top
+    pub fn has_effects(&self) -> bool {
+        *self != RenderMode::Raw
+    }
+}
+
+/// How each cell is drawn when the grid is magnified
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellShape {
+    /// Filtered image, cells blend into each other
+    Pixel,
+    Square,
+    RoundedSquare,
+    Circle,
+}
+
+impl CellShape {
+    pub const ALL: [CellShape; 4] = [
+        CellShape::Pixel,
+        CellShape::Square,
+        CellShape::RoundedSquare,
+        CellShape::Circle,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CellShape::Pixel => "Pixel",
+            CellShape::Square => "Square",
+            CellShape::RoundedSquare => "Rounded square",
+            CellShape::Circle => "Circle",
+        }
+    }
+}
+
+/// How cells are arranged, which decides their neighbors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Eight neighbors
+    Square,
+    /// Six neighbors, odd rows are shifted half a cell right
+    Hex,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 2] = [Topology::Square, Topology::Hex];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topology::Square => "Square",
+            Topology::Hex => "Hexagonal",
+        }
+    }
+
+    /// Most live neighbors a cell can have, the highest count a rule mask needs
+    pub fn neighbors(&self) -> u32 {
+        match self {
+            Topology::Square => 8,
+            Topology::Hex => 6,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub mode: RenderMode,
+    pub life_color: [f32; 4],
+    pub dead_color: [f32; 4],
+    /// Drawn in the gaps between shaped cells
+    pub grid_color: [f32; 4],
+    /// Side length of the square window averaged in `RenderMode::Density`
+    pub density_window: u32,
+    pub density_palette: Gradient,
+    /// Tint cells that flipped state this generation, fading out over the next frames
+    pub highlight_changes: bool,
+    pub highlight_color: [f32; 4],
+    /// Cells born and died in the last step in `RenderMode::Diff`, survivors get `life_color`
+    pub born_color: [f32; 4],
+    pub died_color: [f32; 4],
+    /// Mixed into cells marked as walls
+    pub wall_color: [f32; 4],
+    pub cell_shape: CellShape,
+    /// Smooth the edges of shaped cells
+    pub antialias_cells: bool,
+    /// Shade the edges of shaped cells as if they were raised, fading out once cells get too
+    /// small on screen
+    pub bevel: bool,
+    /// Width of the shaded edge as a fraction of a cell
+    pub bevel_depth: f32,
+    /// Direction the light comes from in degrees, counterclockwise from the right
+    pub light_angle: f32,
+    /// Color in `HDR_FORMAT` instead of `LDR_FORMAT`
+    pub hdr: bool,
+    /// Red, green and blue channels shown, hidden ones show the grid color instead
+    pub channels_visible: [bool; 3],
+    /// How much of each shown channel is blended over the grid color
+    pub channel_opacity: [f32; 3],
+    /// Each pixel of the colored image covers a block of this many cells squared, for previewing
+    /// grids much larger than the window
+    pub decimation: u32,
+    /// Blur the bright parts of the image and add them back on top (see
+    /// `GameComputePipeline::set_bloom`)
+    pub bloom: bool,
+    /// Brightness a color channel must exceed to glow
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    /// Darken the edges of the viewport, over everything drawn in it but not the panel
+    pub vignette: bool,
+    /// Darkening at the corners of the viewport, `0.0..=1.0`
+    pub vignette_intensity: f32,
+    /// Fraction of the way from the center to the corners the darkening starts at
+    pub vignette_radius: f32,
+    /// Add a fading history of where cells have been alive on top of any render mode (see
+    /// `GameComputePipeline::set_trails`)
+    pub trails: bool,
+    /// Fraction of a trail lost each generation
+    pub trail_fade: f32,
+    pub trail_color: [f32; 4],
+    /// Outline the blocks of cells simulated by each compute workgroup
+    pub debug_tiles: bool,
+    /// Mark the grid center with a small cross, and optionally the axes through it
+    pub crosshair: bool,
+    pub axes: bool,
+    /// Its alpha is the opacity of the marker
+    pub crosshair_color: [f32; 4],
+    /// Outline the bounding box of the live cells, from the stats of a generation or two back
+    pub bounding_box: bool,
+    /// Its alpha is the opacity of the outline
+    pub bounding_box_color: [f32; 4],
+    /// Blend each frame with the previous ones to damp flicker while cells are smaller than a
+    /// pixel (see `GameComputePipeline::set_temporal_filter`)
+    pub temporal_filter: bool,
+    /// Weight of the previous frames in the blend
+    pub temporal_blend: f32,
+    /// Leave coloring `RenderMode::Cells` to the final pass, so color changes show without
+    /// recomputing (see `GameComputePipeline::raw_states`)
+    pub fragment_coloring: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            mode: RenderMode::Cells,
+            life_color: [1.0, 0.0, 0.0, 1.0],
+            dead_color: [0.0, 0.0, 0.0, 1.0],
+            grid_color: [0.0, 0.0, 0.0, 1.0],
+            density_window: 16,
+            density_palette: Gradient::default(),
+            highlight_changes: false,
+            highlight_color: [0.2, 0.6, 1.0, 1.0],
+            born_color: [0.2, 1.0, 0.2, 1.0],
+            died_color: [1.0, 0.2, 0.2, 1.0],
+            wall_color: [0.5, 0.5, 0.5, 1.0],
+            cell_shape: CellShape::Pixel,
+            antialias_cells: true,
+            bevel: false,
+            bevel_depth: 0.15,
+            light_angle: 135.0,
+            hdr: false,
+            channels_visible: [true; 3],
+            channel_opacity: [1.0; 3],
+            decimation: 1,
+            bloom: false,
+            bloom_threshold: 0.5,
+            bloom_intensity: 1.0,
+            vignette: false,
+            vignette_intensity: 0.6,
+            vignette_radius: 0.4,
+            trails: false,
+            trail_fade: 0.05,
+            trail_color: [0.2, 0.4, 1.0, 1.0],
+            debug_tiles: false,
+            crosshair: false,
+            axes: false,
+            crosshair_color: [1.0, 1.0, 1.0, 0.6],
+            bounding_box: false,
+            bounding_box_color: [0.2, 1.0, 0.4, 0.8],
+            temporal_filter: false,
+            temporal_blend: 0.6,
+            fragment_coloring: false,
+        }
+    }
+}
+
+/// Summary of the live cells in a generation, reduced on the GPU
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridStats {
+    pub population: u64,
+    /// Average position of the live cells in grid coordinates, `None` if the grid is empty
+    pub centroid: Option<[f32; 2]>,
+    /// Minimum and maximum (inclusive) coordinates of the live cells
+    pub bounding_box: Option<([u32; 2], [u32; 2])>,
+}
+
+/// State of a single cell read back for the inspector
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellInfo {
+    pub alive: bool,
+    /// Live neighbors under the current topology and edge wrapping
+    pub live_neighbors: u32,
+}
+
+const SQUARE_NEIGHBORS: [[i32; 2]; 8] = [
+    [-1, -1],
+    [0, -1],
+    [1, -1],
+    [-1, 0],
+    [1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+];
+
+// Same odd-r offset neighbors as the shader, for even and odd rows
+const HEX_NEIGHBORS_EVEN: [[i32; 2]; 6] = [[-1, 0], [1, 0], [-1, -1], [0, -1], [-1, 1], [0, 1]];
+const HEX_NEIGHBORS_ODD: [[i32; 2]; 6] = [[-1, 0], [1, 0], [0, -1], [1, -1], [0, 1], [1, 1]];
+
+/// Cells uploaded from the CPU, copied into the grid before the next step
+struct PendingWrite {
+    staging: Arc<CpuAccessibleBuffer<[u32]>>,
+    regions: Vec<BufferCopy>,
+}
+
+/// Change to the grid applied before the next step, in the order they were made
+enum PendingEdit {
+    /// Copied into `life_in`
+    Cells(PendingWrite),
+    /// Copied into `walls`
+    Walls(PendingWrite),
+    /// Every cell and wall rolled this many cells right and down, wrapping around
+    Shift([u32; 2]),
+    /// Every cell filled on the GPU, alive with a chance of `density` hashed from the seed
+    Randomize { seed: u32, density: f32 },
+    /// `life_in` and `life_out` filled with dead cells
+    Clear,
+    /// `walls` filled with normal cells
+    ClearWalls,
+}
+
 pub struct GameComputePipeline {
     device: Arc<Device>,
     compute_queue: Arc<Queue>,
     pipeline: Arc<ComputePipeline>,
+    // Checked before allocating a grid
+    limits: DeviceLimits,
+    size: [u32; 2],
+    generation: u64,
+    // Cells outside this rectangle (min inclusive, max exclusive) never change
+    region: Option<([u32; 2], [u32; 2])>,
+    // Width of the band along the grid edges kept dead every generation
+    border: u32,
+    // Whether the edges wrap around (a torus) or everything past them is dead
+    wrap: bool,
+    topology: Topology,
     life_in: Arc<CpuAccessibleBuffer<[u32]>>,
     life_out: Arc<CpuAccessibleBuffer<[u32]>>,
+    pending_edits: Vec<PendingEdit>,
+    // Cells that keep their state whatever the rule says, 1 for a wall
+    walls: Arc<CpuAccessibleBuffer<[u32]>>,
+    // Whether any wall was placed since the walls were last cleared, the color passes skip
+    // looking for them otherwise
+    has_walls: bool,
+    // Horizontal window sums used by the density map
+    density: Arc<DeviceLocalBuffer<[f32]>>,
+    // Fading highlight strength of cells that recently changed
+    highlight: Arc<CpuAccessibleBuffer<[f32]>>,
+    // Per cell trail strength, 1 while alive and fading after, only allocated while trails are on
+    // (see `set_trails`)
+    trails: Option<Arc<CpuAccessibleBuffer<[f32]>>>,
+    // Bound in place of `trails` while it isn't allocated
+    no_trails: Arc<CpuAccessibleBuffer<[f32]>>,
+    palette_pool: CpuBufferPool<Palette>,
+    // Weighted neighborhood replacing the rule's masks, radius 0 in the buffer while unset
+    kernel_rule: Option<KernelRule>,
+    kernel: Arc<CpuAccessibleBuffer<KernelBuffer>>,
+    // Margolus rule replacing every other rule, inactive in the buffer while unset
+    block_rule: Option<BlockRule>,
+    block: Arc<CpuAccessibleBuffer<BlockBuffer>>,
+    // Continuous rule replacing every other one, inactive in the buffer while unset
+    lenia_rule: Option<LeniaRule>,
+    lenia: Arc<CpuAccessibleBuffer<LeniaBuffer>>,
+    // Reduction of the live cells, only filled in while `collect_stats` (or `count_gliders`) is set
+    stats: Arc<CpuAccessibleBuffer<StatsBuffer>>,
+    collect_stats: bool,
+    stats_valid: bool,
+    // Copies of `stats` read without waiting, see `poll_stats`
+    stats_readback: Readback<StatsBuffer>,
+    // Copies of the grid after each frame's generations, only allocated while recording history
+    history_readback: Option<Readback<[u32]>>,
+    count_gliders: bool,
+    gliders_valid: bool,
+    // Most generations recorded into one command buffer, 0 records every generation of a
+    // `compute_n` call into one
+    generations_per_submit: u32,
+    // Run the color (and bloom) passes, without them the image keeps showing an older generation
+    color: bool,
     out_view: DeviceImageView,
+    // Colors written to `out_view`, kept readable for the passes after coloring, only allocated
+    // while the buffers of one of them are
+    colors: Option<Arc<DeviceLocalBuffer<[[f32; 4]]>>>,
+    // Running blend of the previous frames' colors for the temporal filter, only allocated while
+    // it's on, and whether it holds a frame of the current image yet
+    history: Option<Arc<DeviceLocalBuffer<[[f32; 4]]>>>,
+    history_valid: bool,
+    // Whether the last color pass wrote cell states instead of colors
+    raw_states: bool,
+    // Horizontally blurred bright colors, the first half of the bloom blur, only allocated while
+    // bloom is on
+    bloom_rows: Option<Arc<DeviceLocalBuffer<[[f32; 4]]>>>,
+    // Bound in place of the color buffers that aren't allocated
+    no_pixels: Arc<DeviceLocalBuffer<[[f32; 4]]>>,
+    // Blurred bright colors, added on top of `out_view` by the final pass
+    bloom_view: DeviceImageView,
+    // `out_view` as it was before the last steps, only allocated while `set_keep_previous` is
+    // enabled, and whether a step has copied it since
+    previous_view: Option<DeviceImageView>,
+    previous_valid: bool,
+    format: Format,
+    // Whether the device can color into `HDR_FORMAT`, otherwise `LDR_FORMAT` is always used
+    hdr_supported: bool,
+    decimation: u32,
+    // Copy of the colored image, only filled in while `capture_frame` is set
+    frame: Arc<CpuAccessibleBuffer<[u8]>>,
+    capture_frame: bool,
+    frame_valid: bool,
+    // Signalled once the last submitted step has finished, used to wait before reading back
+    last_compute: Option<FrameFence>,
+    // Seed and density of the random fill being recorded (see `PendingEdit::Randomize`)
+    random_fill: (u32, f32),
+    // Cells flipped per generation on average while set, and the seed they're hashed from
+    cosmic_rays: Option<f32>,
+    cosmic_ray_seed: u64,
 }
 
-fn rand_grid(compute_queue: &Arc<Queue>, size: [u32; 2]) -> Arc<CpuAccessibleBuffer<[u32]>> {
+fn empty_grid(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+) -> Result<Arc<CpuAccessibleBuffer<[u32]>>, InitError> {
     CpuAccessibleBuffer::from_iter(
         compute_queue.device().clone(),
         BufferUsage::all(),
         false,
-        (0..(size[0] * size[1]))
-            .map(|_| rand::thread_rng().gen_range(0u32..=1))
-            .collect::<Vec<u32>>(),
+        (0..(size[0] * size[1])).map(|_| 0u32),
     )
-    .unwrap()
+    .map_err(|err| InitError::allocation("the grid", err))
 }
 
-impl GameComputePipeline {
-    pub fn new(context: &VulkanoContext, size: [u32; 2]) -> Self {
-        let life_in = rand_grid(&context.graphics_queue(), size);
-        let life_out = rand_grid(&context.graphics_queue(), size);
+fn highlight_buffer(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+) -> Result<Arc<CpuAccessibleBuffer<[f32]>>, InitError> {
+    CpuAccessibleBuffer::from_iter(
+        compute_queue.device().clone(),
+        BufferUsage::storage_buffer(),
+        false,
+        (0..(size[0] * size[1])).map(|_| 0.0f32),
+    )
+    .map_err(|err| InitError::allocation("a highlight buffer", err))
+}
 
-        let out_view = StorageImage::general_purpose_image_view(
-            context.graphics_queue(),
-            size,
-            Format::R8G8B8A8_UNORM,
-            ImageUsage {
-                sampled: true,
-                storage: true,
-                color_attachment: true,
-                transfer_dst: true,
-                ..ImageUsage::none()
-            },
+fn density_buffer(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+) -> Result<Arc<DeviceLocalBuffer<[f32]>>, InitError> {
+    DeviceLocalBuffer::array(
+        compute_queue.device().clone(),
+        (size[0] * size[1]) as u64,
+        BufferUsage::storage_buffer(),
+        [compute_queue.family()],
+    )
+    .map_err(|err| InitError::allocation("the density buffer", err))
+}
+
+fn pixel_buffer(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+) -> Result<Arc<DeviceLocalBuffer<[[f32; 4]]>>, InitError> {
+    DeviceLocalBuffer::array(
+        compute_queue.device().clone(),
+        (size[0] * size[1]) as u64,
+        BufferUsage::storage_buffer(),
+        [compute_queue.family()],
+    )
+    .map_err(|err| InitError::allocation("a color buffer", err))
+}
+
+// Allocate `buffer` for an image of `size` while it's `needed` (keeping the one there is), free it
+// otherwise
+fn update_pixel_buffer(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+    buffer: &mut Option<Arc<DeviceLocalBuffer<[[f32; 4]]>>>,
+    needed: bool,
+) -> Result<(), InitError> {
+    if !needed {
+        *buffer = None;
+    } else if buffer.is_none() {
+        *buffer = Some(pixel_buffer(compute_queue, size)?);
+    }
+    Ok(())
+}
+
+fn empty_stats() -> StatsBuffer {
+    StatsBuffer {
+        population: 0,
+        sum_x_low: 0,
+        sum_x_high: 0,
+        sum_y_low: 0,
+        sum_y_high: 0,
+        min_x: u32::MAX,
+        min_y: u32::MAX,
+        max_x: 0,
+        max_y: 0,
+        gliders: 0,
+    }
+}
+
+fn frame_buffer(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+    format: Format,
+) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, InitError> {
+    let texel_size = format.block_size().unwrap();
+    CpuAccessibleBuffer::from_iter(
+        compute_queue.device().clone(),
+        BufferUsage::transfer_dst(),
+        false,
+        (0..(size[0] * size[1]) as usize * texel_size as usize).map(|_| 0u8),
+    )
+    .map_err(|err| InitError::allocation("the frame capture buffer", err))
+}
+
+/// The compute pipeline with the shader variant that writes `format` images
+fn compute_pipeline(
+    device: Arc<Device>,
+    format: Format,
+) -> Result<Arc<ComputePipeline>, InitError> {
+    let shader = if format == HDR_FORMAT {
+        compute_life_hdr_cs::load(device.clone())
+    } else {
+        compute_life_cs::load(device.clone())
+    }
+    .map_err(|err| InitError::creation("the compute shader", err))?;
+    ComputePipeline::new(
+        device,
+        shader.entry_point("main").unwrap(),
+        // The same constants in both variants
+        &compute_life_cs::SpecializationConstants {
+            workgroup_size: WORKGROUP_SIZE,
+        },
+        None,
+        |_| {},
+    )
+    .map_err(|err| InitError::creation("the compute pipeline", err))
+}
+
+/// Whether colored images of `format` can be written by the compute shader and sampled when drawn
+fn supports_image_format(device: &Device, format: Format) -> bool {
+    let features = device
+        .physical_device()
+        .format_properties(format)
+        .optimal_tiling_features;
+    features.storage_image && features.sampled_image
+}
+
+fn out_image(
+    compute_queue: &Arc<Queue>,
+    size: [u32; 2],
+    format: Format,
+) -> Result<DeviceImageView, InitError> {
+    StorageImage::general_purpose_image_view(
+        compute_queue.clone(),
+        size,
+        format,
+        ImageUsage {
+            sampled: true,
+            storage: true,
+            color_attachment: true,
+            transfer_src: true,
+            transfer_dst: true,
+            ..ImageUsage::none()
+        },
+    )
+    .map_err(|err| InitError::allocation("the colored image", err))
+}
+
+impl GameComputePipeline {
+    /// `format` is the format of the colored image, either `LDR_FORMAT` or `HDR_FORMAT`
+    pub fn new(
+        context: &VulkanoContext,
+        size: [u32; 2],
+        format: Format,
+    ) -> Result<Self, InitError> {
+        // A grid past the limits fails somewhere in allocation or dispatch, catch it up front
+        let limits = DeviceLimits::query(context);
+        limits.check_grid(size)?;
+        let life_in = empty_grid(&context.graphics_queue(), size)?;
+        let life_out = empty_grid(&context.graphics_queue(), size)?;
+        let density = density_buffer(&context.graphics_queue(), size)?;
+        let highlight = highlight_buffer(&context.graphics_queue(), size)?;
+        let no_trails = highlight_buffer(&context.graphics_queue(), [1, 1])?;
+        let palette_pool = CpuBufferPool::uniform_buffer(context.device());
+        let stats = CpuAccessibleBuffer::from_data(
+            context.device(),
+            BufferUsage::all(),
+            false,
+            empty_stats(),
         )
-        .unwrap();
+        .map_err(|err| InitError::allocation("the stats buffer", err))?;
+        let hdr_supported = supports_image_format(&context.device(), HDR_FORMAT);
+        let format = if hdr_supported { format } else { LDR_FORMAT };
+        let kernel = kernel_buffer(&context.graphics_queue(), None)?;
+        let block = block_buffer(&context.graphics_queue(), None)?;
+        let lenia = lenia_buffer(&context.graphics_queue(), None)?;
+        let out_view = out_image(&context.graphics_queue(), size, format)?;
+        let no_pixels = pixel_buffer(&context.graphics_queue(), [1, 1])?;
+        let bloom_view = out_image(&context.graphics_queue(), size, format)?;
+        let frame = frame_buffer(&context.graphics_queue(), size, format)?;
 
-        let pipeline = {
-            let shader = compute_life_cs::load(context.device()).unwrap();
-            ComputePipeline::new(
-                context.device(),
-                shader.entry_point("main").unwrap(),
-                &(),
-                None,
-                |_| {},
-            )
-            .unwrap()
-        };
+        let pipeline = compute_pipeline(context.device(), format)?;
 
-        Self {
+        let mut pipeline = Self {
             device: context.device(),
             compute_queue: context.graphics_queue(),
+            limits,
             pipeline,
+            size,
+            generation: 0,
+            region: None,
+            border: 0,
+            wrap: true,
+            topology: Topology::Square,
             life_in,
             life_out,
+            pending_edits: vec![],
+            walls: empty_grid(&context.graphics_queue(), size)?,
+            has_walls: false,
+            density,
+            highlight,
+            trails: None,
+            no_trails,
+            palette_pool,
+            kernel_rule: None,
+            kernel,
+            block_rule: None,
+            block,
+            lenia_rule: None,
+            lenia,
+            stats,
+            collect_stats: false,
+            stats_valid: false,
+            stats_readback: Readback::new(context.device(), STATS_READBACK_SLOTS, empty_stats())?,
+            history_readback: None,
+            count_gliders: false,
+            gliders_valid: false,
+            generations_per_submit: 0,
+            color: true,
             out_view,
-        }
+            colors: None,
+            history: None,
+            history_valid: false,
+            raw_states: false,
+            bloom_rows: None,
+            no_pixels,
+            bloom_view,
+            previous_view: None,
+            previous_valid: false,
+            format,
+            hdr_supported,
+            decimation: 1,
+            frame,
+            capture_frame: false,
+            frame_valid: false,
+            last_compute: None,
+            random_fill: (0, 0.0),
+            cosmic_rays: None,
+            cosmic_ray_seed: 0,
+        };
+        pipeline.randomize(rand::random(), 0.5);
+        Ok(pipeline)
     }
 
     pub fn view(&self) -> DeviceImageView {
         self.out_view.clone()
     }
 
-    pub fn compute(
+    /// Blurred bright parts of the colored image, only up to date while bloom is enabled
+    pub fn bloom_view(&self) -> DeviceImageView {
+        self.bloom_view.clone()
+    }
+
+    /// The colored image before the last call to `compute_n` that ran any steps, only there while
+    /// `set_keep_previous` is enabled and once a step has run since
+    pub fn previous_view(&self) -> Option<DeviceImageView> {
+        self.previous_view.clone().filter(|_| self.previous_valid)
+    }
+
+    /// Keep a copy of the colored image from before each step (costs a copy of the image), to
+    /// morph from it to the next generation. Fails if the copy can't be allocated.
+    pub fn set_keep_previous(&mut self, keep_previous: bool) -> Result<(), InitError> {
+        if !keep_previous {
+            self.previous_view = None;
+        } else if self.previous_view.is_none() {
+            self.previous_view = Some(out_image(
+                &self.compute_queue,
+                self.image_size(),
+                self.format,
+            )?);
+            self.previous_valid = false;
+        }
+        Ok(())
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Number of steps computed since the grid was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    /// Block until the last submitted step has finished
+    pub fn wait(&self) {
+        if let Some(future) = &self.last_compute {
+            future.wait(None).unwrap();
+        }
+    }
+
+    /// Read back the current generation as one byte per cell (row by row), blocking until the
+    /// last step has finished. Edits made since the last `compute` are not included.
+    pub fn snapshot(&self) -> Vec<u8> {
+        if let Some(future) = &self.last_compute {
+            // Waiting also releases the buffers so the CPU can read them
+            future.wait(None).unwrap();
+        }
+
+        let cells = self.life_in.read().unwrap();
+        cells.iter().map(|&cell| cell as u8).collect()
+    }
+
+    /// Read back the cells from `min` (inclusive) to `max` (exclusive) like `snapshot`, clamped to
+    /// the grid. Returns the cells row by row and the size of the clamped rectangle.
+    pub fn snapshot_region(&self, min: [u32; 2], max: [u32; 2]) -> (Vec<u8>, [u32; 2]) {
+        if let Some(future) = &self.last_compute {
+            future.wait(None).unwrap();
+        }
+
+        let max = [0, 1].map(|i| max[i].min(self.size[i]));
+        let min = [0, 1].map(|i| min[i].min(max[i]));
+        let size = [0, 1].map(|i| max[i] - min[i]);
+        let cells = self.life_in.read().unwrap();
+        let region = (min[1]..max[1])
+            .flat_map(|y| {
+                let row = (y * self.size[0]) as usize;
+                cells[row + min[0] as usize..row + max[0] as usize].iter()
+            })
+            .map(|&cell| cell as u8)
+            .collect();
+        (region, size)
+    }
+
+    /// Read back one cell and its neighbors in the current generation, blocking until the last
+    /// step has finished. Edits made since the last `compute_n` are not included.
+    pub fn inspect_cell(&self, cell: [u32; 2]) -> CellInfo {
+        if let Some(future) = &self.last_compute {
+            future.wait(None).unwrap();
+        }
+
+        let cells = self.life_in.read().unwrap();
+        let [width, height] = self.size.map(|side| side as i32);
+        let alive_at = |x: i32, y: i32| {
+            if !self.wrap && (x < 0 || y < 0 || x >= width || y >= height) {
+                return false;
+            }
+            let (x, y) = (x.rem_euclid(width), y.rem_euclid(height));
+            cells[(y * width + x) as usize] == 1
+        };
+
+        let [x, y] = cell.map(|v| v as i32);
+        let offsets: &[[i32; 2]] = match self.topology {
+            Topology::Square => &SQUARE_NEIGHBORS,
+            Topology::Hex if y % 2 == 1 => &HEX_NEIGHBORS_ODD,
+            Topology::Hex => &HEX_NEIGHBORS_EVEN,
+        };
+        CellInfo {
+            alive: alive_at(x, y),
+            live_neighbors: offsets
+                .iter()
+                .filter(|[dx, dy]| alive_at(x + dx, y + dy))
+                .count() as u32,
+        }
+    }
+
+    /// Count neighbors with a weighted kernel instead of the rule passed to `compute_n`, `None`
+    /// goes back to the rule
+    pub fn set_kernel_rule(&mut self, kernel_rule: Option<&KernelRule>) {
+        if self.kernel_rule.as_ref() != kernel_rule {
+            self.kernel_rule = kernel_rule.cloned();
+            // A new buffer so steps still in flight keep reading the old one
+            self.kernel = kernel_buffer(&self.compute_queue, kernel_rule).unwrap();
+        }
+    }
+
+    /// Simulate a block rule on the Margolus neighborhood instead of `rule` (and the kernel rule).
+    /// The blocks only line up across wrapped edges on grids with even sides.
+    pub fn set_block_rule(&mut self, block_rule: Option<BlockRule>) {
+        if self.block_rule != block_rule {
+            self.block_rule = block_rule;
+            self.block = block_buffer(&self.compute_queue, block_rule).unwrap();
+        }
+    }
+
+    /// Simulate a continuous Lenia rule instead of every other rule. The cells then hold values
+    /// in `0.0..=1.0` (see `load_values`), the 0 and 1 of patterns and seeding carry over.
+    /// Cells are counted as alive from half up.
+    pub fn set_lenia_rule(&mut self, lenia_rule: Option<&LeniaRule>) {
+        if self.lenia_rule.as_ref() != lenia_rule {
+            self.lenia_rule = lenia_rule.cloned();
+            self.lenia = lenia_buffer(&self.compute_queue, lenia_rule).unwrap();
+        }
+    }
+
+    /// Enable the reduction pass that fills in `stats` (costs an extra pass over the grid)
+    pub fn set_collect_stats(&mut self, collect_stats: bool) {
+        self.collect_stats = collect_stats;
+    }
+
+    /// Only simulate the cells inside a rectangle (min inclusive, max exclusive), clamped to the
+    /// grid. `None` simulates the whole grid.
+    pub fn set_region(&mut self, region: Option<([u32; 2], [u32; 2])>) {
+        self.region = region.map(|(min, max)| {
+            let max = [max[0].min(self.size[0]), max[1].min(self.size[1])];
+            ([min[0].min(max[0]), min[1].min(max[1])], max)
+        });
+    }
+
+    pub fn region(&self) -> Option<([u32; 2], [u32; 2])> {
+        self.region
+    }
+
+    /// Kill every cell within `border` cells of the grid edges each generation, so nothing can
+    /// wrap around the torus
+    pub fn set_border(&mut self, border: u32) {
+        self.border = border;
+    }
+
+    /// Wrap the grid edges around like a torus, otherwise cells past the edges count as dead
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Arrangement of the cells, the rule masks then cover up to 8 (square) or 6 (hex) neighbors
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// Enable the color passes, disable them to only advance the simulation (the image and the
+    /// captured frame aren't updated)
+    pub fn set_color(&mut self, color: bool) {
+        self.color = color;
+    }
+
+    /// Enable blending each colored frame with the previous ones (by `temporal_blend` of the
+    /// render settings). Meant for zoomed out views, where patterns moving between texels flicker.
+    /// Fails if the blend can't be allocated.
+    pub fn set_temporal_filter(&mut self, temporal_filter: bool) -> Result<(), InitError> {
+        if self.history.is_none() {
+            // Restarts from the next frame alone instead of a stale blend
+            self.history_valid = false;
+        }
+        let size = self.image_size();
+        update_pixel_buffer(
+            &self.compute_queue,
+            size,
+            &mut self.history,
+            temporal_filter,
+        )?;
+        self.update_colors()
+    }
+
+    /// Fade a trail behind the live cells (drawn with `trail_color` of the render settings).
+    /// Trails start out cleared, fails if they can't be allocated.
+    pub fn set_trails(&mut self, trails: bool) -> Result<(), InitError> {
+        if !trails {
+            self.trails = None;
+        } else if self.trails.is_none() {
+            self.trails = Some(highlight_buffer(&self.compute_queue, self.size)?);
+        }
+        self.update_colors()
+    }
+
+    /// Blur the bright colors over their surroundings, fails if the blur can't be allocated
+    pub fn set_bloom(&mut self, bloom: bool) -> Result<(), InitError> {
+        let size = self.image_size();
+        update_pixel_buffer(&self.compute_queue, size, &mut self.bloom_rows, bloom)?;
+        self.update_colors()
+    }
+
+    // The colors are kept while any pass after coloring has its buffers
+    fn update_colors(&mut self) -> Result<(), InitError> {
+        let needed = self.trails.is_some() || self.history.is_some() || self.bloom_rows.is_some();
+        let size = self.image_size();
+        update_pixel_buffer(&self.compute_queue, size, &mut self.colors, needed)
+    }
+
+    /// Whether the image holds the states of the cells rather than their colors, left for the
+    /// final pass to color with the render settings. Alive in red, the change highlight in green
+    /// and walls in blue.
+    pub fn raw_states(&self) -> bool {
+        self.raw_states
+    }
+
+    /// Enable the pass that counts isolated gliders (costs an extra pass over the grid)
+    pub fn set_count_gliders(&mut self, count_gliders: bool) {
+        self.count_gliders = count_gliders;
+    }
+
+    /// Number of isolated gliders in the last computed generation, blocking until it has
+    /// finished. `None` if gliders weren't counted for it.
+    pub fn glider_count(&self) -> Option<u32> {
+        if !self.gliders_valid {
+            return None;
+        }
+        if let Some(future) = &self.last_compute {
+            future.wait(None).unwrap();
+        }
+
+        Some(self.stats.read().unwrap().gliders)
+    }
+
+    /// Population, centroid and bounding box of the last computed generation, blocking until it
+    /// has finished. `None` if stats weren't collected for it.
+    pub fn stats(&self) -> Option<GridStats> {
+        if !self.stats_valid {
+            return None;
+        }
+        if let Some(future) = &self.last_compute {
+            future.wait(None).unwrap();
+        }
+
+        Some(grid_stats(&self.stats.read().unwrap()))
+    }
+
+    /// Hand the stats of the generations computed since the last poll to `callback`, with their
+    /// generation and in order, without waiting on the GPU. They arrive a frame or two late and
+    /// generations whose stats weren't collected are skipped (so are some if polling falls
+    /// behind).
+    pub fn poll_stats(&mut self, mut callback: impl FnMut(u64, GridStats)) {
+        self.stats_readback
+            .poll(|generation, stats| callback(generation, grid_stats(stats)));
+    }
+
+    /// Average time of a step of the current grid over `generations` steps, submitted
+    /// `BENCHMARK_BATCH` at a time and waited on, then put the grid and generation back as they
+    /// were. Only the simulation is timed, the image keeps showing the current generation and
+    /// the trails are left as they were. The highlight and anti-flicker start over.
+    pub fn benchmark(
         &mut self,
-        before_future: Box<dyn GpuFuture>,
-        life_color: [f32; 4],
-        dead_color: [f32; 4],
-    ) -> Box<dyn GpuFuture> {
-        let mut builder = AutoCommandBufferBuilder::primary(
+        generations: u32,
+        rule: &Rule,
+        settings: &RenderSettings,
+    ) -> Duration {
+        let flags = (self.color, self.collect_stats, self.count_gliders);
+        (self.color, self.collect_stats, self.count_gliders) = (false, false, false);
+        let history_readback = self.history_readback.take();
+        // Trails would fade over the benchmarked generations, they're put back with the grid. The
+        // previous frame is only updated with the color passes, which are off.
+        let trails = self.trails.take();
+        // Apply the pending edits first so they're part of what's put back
+        self.flush_pending_edits(rule, settings);
+        let cells = self.life_in.read().unwrap().to_vec();
+        let generation = self.generation;
+
+        let start = Instant::now();
+        let mut remaining = generations;
+        while remaining > 0 {
+            let batch = remaining.min(BENCHMARK_BATCH);
+            let before = sync::now(self.device.clone()).boxed();
+            let _ = self.compute_n(before, batch, rule, settings);
+            self.wait();
+            remaining -= batch;
+        }
+        let elapsed = start.elapsed();
+
+        // Both buffers, so the diff and highlight see no change from the benchmarked generations
+        self.life_in.write().unwrap().copy_from_slice(&cells);
+        self.life_out.write().unwrap().copy_from_slice(&cells);
+        self.highlight.write().unwrap().fill(0.0);
+        self.history_valid = false;
+        self.generation = generation;
+        self.history_readback = history_readback;
+        self.trails = trails;
+        (self.color, self.collect_stats, self.count_gliders) = flags;
+        elapsed / generations.max(1)
+    }
+
+    /// Apply the edits made since the last `compute_n` and wait for them, without coloring
+    fn flush_pending_edits(&mut self, rule: &Rule, settings: &RenderSettings) {
+        let color = std::mem::replace(&mut self.color, false);
+        let before = sync::now(self.device.clone()).boxed();
+        let _ = self.compute_n(before, 0, rule, settings);
+        self.wait();
+        self.color = color;
+    }
+
+    /// Copy the grid back after every frame that simulates generations, see `poll_history`.
+    /// Recording stays off if its buffers can't be allocated.
+    pub fn set_record_history(&mut self, record_history: bool) -> Result<(), InitError> {
+        if !record_history {
+            self.history_readback = None;
+        } else if self.history_readback.is_none() {
+            let cells = self.size[0] as usize * self.size[1] as usize;
+            let readback = Readback::array(self.device.clone(), history::READBACK_SLOTS, cells, 0)?;
+            self.history_readback = Some(readback);
+        }
+        Ok(())
+    }
+
+    /// Hand the cells (row by row) of the generations recorded since the last poll to
+    /// `callback` like `poll_stats`, while recording history
+    pub fn poll_history(&mut self, callback: impl FnMut(u64, &[u32])) {
+        if let Some(readback) = &mut self.history_readback {
+            readback.poll(callback);
+        }
+    }
+
+    pub fn image_format(&self) -> Format {
+        self.format
+    }
+
+    /// Recreate the colored image with another format, the grid is kept. Falls back to
+    /// `LDR_FORMAT` if the device can't color into `HDR_FORMAT`. On failure the old image and
+    /// format are kept.
+    pub fn set_image_format(&mut self, format: Format) -> Result<(), InitError> {
+        let format = if self.hdr_supported {
+            format
+        } else {
+            LDR_FORMAT
+        };
+        let pipeline = compute_pipeline(self.device.clone(), format)?;
+        self.recreate_image(self.image_size(), format)?;
+        self.pipeline = pipeline;
+        self.format = format;
+        Ok(())
+    }
+
+    /// Whether the device can color into `HDR_FORMAT`, `LDR_FORMAT` always works
+    pub fn supports_hdr(&self) -> bool {
+        self.hdr_supported
+    }
+
+    pub fn decimation(&self) -> u32 {
+        self.decimation
+    }
+
+    /// Color one pixel per `decimation` x `decimation` block of cells (a block is drawn alive if
+    /// any of its cells are), the simulation still runs at full resolution. On failure the old
+    /// image and decimation are kept.
+    pub fn set_decimation(&mut self, decimation: u32) -> Result<(), InitError> {
+        let decimation = decimation.max(1);
+        self.recreate_image(self.size.map(|side| side.div_ceil(decimation)), self.format)?;
+        self.decimation = decimation;
+        Ok(())
+    }
+
+    /// Size of the colored image, the grid size divided by the decimation (rounded up)
+    pub fn image_size(&self) -> [u32; 2] {
+        self.size.map(|side| side.div_ceil(self.decimation))
+    }
+
+    /// Cells covered by the colored image, at least the grid size
+    pub fn displayed_size(&self) -> [u32; 2] {
+        self.image_size().map(|side| side * self.decimation)
+    }
+
+    // Everything is allocated before anything is replaced, the effects' buffers are allocated
+    // again for the new image by their setters
+    fn recreate_image(&mut self, image_size: [u32; 2], format: Format) -> Result<(), InitError> {
+        let out_view = out_image(&self.compute_queue, image_size, format)?;
+        let bloom_view = out_image(&self.compute_queue, image_size, format)?;
+        let frame = frame_buffer(&self.compute_queue, image_size, format)?;
+
+        self.out_view = out_view;
+        self.colors = None;
+        self.history = None;
+        self.history_valid = false;
+        self.bloom_rows = None;
+        self.bloom_view = bloom_view;
+        self.previous_view = None;
+        self.frame = frame;
+        self.frame_valid = false;
+        Ok(())
+    }
+
+    /// Copy the colored image back to the CPU after the next steps
+    pub fn set_capture_frame(&mut self, capture_frame: bool) {
+        self.capture_frame = capture_frame;
+    }
+
+    /// 8 bit RGBA pixels (row by row) of the last computed generation, blocking until it has
+    /// finished. `None` if the frame wasn't captured.
+    pub fn captured_frame(&self) -> Option<Vec<u8>> {
+        if !self.frame_valid {
+            return None;
+        }
+        if let Some(future) = &self.last_compute {
+            future.wait(None).unwrap();
+        }
+
+        let frame = self.frame.read().unwrap();
+        if self.format == HDR_FORMAT {
+            // Half floats are clamped rather than tone mapped
+            let pixels = frame
+                .chunks_exact(2)
+                .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]).to_f32())
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect();
+            Some(pixels)
+        } else {
+            Some(frame.to_vec())
+        }
+    }
+
+    /// Reallocate the grid with new dimensions (multiples of 8) and fill it randomly. The grid is
+    /// left as it was if the size exceeds the device limits or its buffers can't be allocated.
+    pub fn resize(&mut self, size: [u32; 2]) -> Result<(), InitError> {
+        self.limits.check_grid(size)?;
+        let life_in = empty_grid(&self.compute_queue, size)?;
+        let life_out = empty_grid(&self.compute_queue, size)?;
+        let density = density_buffer(&self.compute_queue, size)?;
+        let highlight = highlight_buffer(&self.compute_queue, size)?;
+        let walls = empty_grid(&self.compute_queue, size)?;
+        self.recreate_image(size.map(|side| side.div_ceil(self.decimation)), self.format)?;
+
+        self.size = size;
+        self.region = None;
+        self.life_in = life_in;
+        self.life_out = life_out;
+        self.density = density;
+        self.highlight = highlight;
+        self.walls = walls;
+        self.has_walls = false;
+        // Allocated again for the new size by the next `set_trails`, `set_keep_previous` and
+        // `set_record_history`
+        self.trails = None;
+        self.history_readback = None;
+        self.pending_edits.clear();
+        self.randomize(rand::random(), 0.5);
+        Ok(())
+    }
+
+    /// Reallocate the grid like `resize`, but keep the cells with the old grid's top left corner
+    /// at `offset` in the new one and the rest dead. Cells that end up outside are cropped, returns
+    /// how many of them were alive. Walls are cleared like `resize` does. Edits made since the
+    /// last `compute_n` are applied first with `rule` and `settings`, so they're kept too.
+    pub fn resize_preserving(
+        &mut self,
+        size: [u32; 2],
+        offset: [i32; 2],
+        rule: &Rule,
+        settings: &RenderSettings,
+    ) -> Result<u64, InitError> {
+        let old_size = self.size;
+        self.flush_pending_edits(rule, settings);
+        let cells = self.snapshot();
+        self.resize(size)?;
+
+        let cropped = cells
+            .iter()
+            .enumerate()
+            .filter(|&(i, &cell)| {
+                let old = [i as u32 % old_size[0], i as u32 / old_size[0]];
+                let moved = [0, 1].map(|axis| old[axis] as i64 + offset[axis] as i64);
+                cell != 0 && (0..2).any(|axis| !(0..size[axis] as i64).contains(&moved[axis]))
+            })
+            .count();
+        self.clear();
+        self.load_pattern(&Pattern::new(old_size, cells), offset);
+        Ok(cropped as u64)
+    }
+
+    /// Kill every cell before the next step, edits made after are kept
+    pub fn clear(&mut self) {
+        // Earlier cell edits would only be overwritten
+        self.pending_edits.retain(|edit| {
+            !matches!(
+                edit,
+                PendingEdit::Cells(_) | PendingEdit::Randomize { .. } | PendingEdit::Clear
+            )
+        });
+        self.pending_edits.push(PendingEdit::Clear);
+        self.stats_valid = false;
+        self.gliders_valid = false;
+        self.clear_trails();
+    }
+
+    /// Fill every cell at random on the GPU before the next step, alive with a chance of
+    /// `density`. The same seed always gives the same grid, without generating it on the CPU.
+    pub fn randomize(&mut self, seed: u64, density: f32) {
+        // Earlier cell edits would only be overwritten
+        self.pending_edits
+            .retain(|edit| !matches!(edit, PendingEdit::Cells(_)));
+        self.pending_edits.push(PendingEdit::Randomize {
+            seed: (seed ^ (seed >> 32)) as u32,
+            density,
+        });
+        self.stats_valid = false;
+        self.gliders_valid = false;
+        self.clear_trails();
+    }
+
+    /// Fill the grid from the seed settings, uniform fills on the GPU (see `randomize`) and noise
+    /// on the CPU like `seeding::seeded_grid`
+    pub fn seed(&mut self, settings: &SeedSettings) {
+        self.cosmic_ray_seed = settings.seed;
+        match settings.mode {
+            SeedMode::Uniform => self.randomize(settings.seed, settings.density),
+            SeedMode::Noise => {
+                self.clear();
+                self.load_pattern(&seeding::seeded_grid(self.size, settings), [0, 0]);
+            }
+        }
+    }
+
+    /// Remove every wall before the next step
+    pub fn clear_walls(&mut self) {
+        self.pending_edits
+            .retain(|edit| !matches!(edit, PendingEdit::Walls(_) | PendingEdit::ClearWalls));
+        self.pending_edits.push(PendingEdit::ClearWalls);
+        self.has_walls = false;
+    }
+
+    /// Make a cell a wall that keeps its current state (or a normal cell again), applied before
+    /// the next step like `set_cell`
+    pub fn set_wall(&mut self, cell: [u32; 2], wall: bool) {
+        let regions = self.copy_regions([1, 1], [cell[0] as i32, cell[1] as i32], [1, 1]);
+        if regions.is_empty() {
+            return;
+        }
+        let staging = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::transfer_src(),
+            false,
+            [wall as u32],
+        )
+        .unwrap();
+        self.pending_edits
+            .push(PendingEdit::Walls(PendingWrite { staging, regions }));
+        self.has_walls |= wall;
+    }
+
+    pub fn has_walls(&self) -> bool {
+        self.has_walls
+    }
+
+    /// Forget the history drawn by the trails, they start over cleared from the next `set_trails`
+    pub fn clear_trails(&mut self) {
+        self.trails = None;
+    }
+
+    /// Stamp a pattern with its top left corner at `offset`, the parts outside the grid are clipped
+    pub fn load_pattern(&mut self, pattern: &Pattern, offset: [i32; 2]) {
+        let regions = self.copy_regions(pattern.size(), offset, pattern.size());
+        self.stage(pattern.cells().iter().map(|&cell| cell as u32), regions);
+    }
+
+    /// Stamp `size` cell values of a Lenia rule (see `set_lenia_rule`), row by row, with the top
+    /// left corner at `offset`
+    pub fn load_values(&mut self, size: [u32; 2], values: &[f32], offset: [i32; 2]) {
+        let regions = self.copy_regions(size, offset, size);
+        // The shader reads the bits as a float, except 1 which patterns use for alive
+        let bits = values.iter().map(|&value| {
+            let value = value.clamp(0.0, 1.0);
+            if value < LENIA_MIN_VALUE {
+                0
+            } else {
+                value.to_bits()
+            }
+        });
+        self.stage(bits, regions);
+    }
+
+    /// Stamp copies of a pattern every `spacing` cells across the cells from `min` (inclusive) to
+    /// `max` (exclusive). Each copy is clipped to its tile and the rectangle, so copies never
+    /// overwrite each other or anything outside the rectangle.
+    pub fn tile_pattern(
+        &mut self,
+        pattern: &Pattern,
+        min: [u32; 2],
+        max: [u32; 2],
+        spacing: [u32; 2],
+    ) {
+        let spacing = spacing.map(|v| v.max(1));
+        let tile = pattern.cropped([0, 0], spacing);
+        let max = [0, 1].map(|i| max[i].min(self.size[i]));
+
+        // Every copy is read from the same staging buffer, the ones on the far edges only in part
+        let mut regions = vec![];
+        for y in (min[1]..max[1]).step_by(spacing[1] as usize) {
+            for x in (min[0]..max[0]).step_by(spacing[0] as usize) {
+                let size = [(max[0] - x).min(spacing[0]), (max[1] - y).min(spacing[1])];
+                regions.extend(self.copy_regions(tile.size(), [x as i32, y as i32], size));
+            }
+        }
+        self.stage(tile.cells().iter().map(|&cell| cell as u32), regions);
+    }
+
+    /// Row by row copies of the top left `clip` cells of a pattern `size` cells large from a
+    /// staging buffer into the grid at `offset`, leaving out the parts outside the grid
+    fn copy_regions(&self, size: [u32; 2], offset: [i32; 2], clip: [u32; 2]) -> Vec<BufferCopy> {
+        let [width, height] = [0, 1].map(|i| size[i].min(clip[i]));
+        let x_start = offset[0].max(0);
+        let x_end = (offset[0] + width as i32).min(self.size[0] as i32);
+        if x_start >= x_end {
+            return vec![];
+        }
+
+        (0..height as i32)
+            .filter(|y| (0..self.size[1] as i32).contains(&(offset[1] + y)))
+            .map(|y| BufferCopy {
+                src_offset: (y as u32 * size[0] + (x_start - offset[0]) as u32) as u64,
+                dst_offset: ((offset[1] + y) as u32 * self.size[0] + x_start as u32) as u64,
+                size: (x_end - x_start) as u64,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Upload cells to be copied into the grid before the next step
+    fn stage(&mut self, cells: impl ExactSizeIterator<Item = u32>, regions: Vec<BufferCopy>) {
+        if regions.is_empty() {
+            return;
+        }
+
+        let staging = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::transfer_src(),
+            false,
+            cells,
+        )
+        .unwrap();
+
+        self.pending_edits
+            .push(PendingEdit::Cells(PendingWrite { staging, regions }));
+    }
+
+    /// Roll every cell and wall `dx` cells right and `dy` cells down (negative for left and up),
+    /// what leaves one edge comes back on the opposite one. Applied before the next step like
+    /// `set_cell`, edits made before it are shifted along and edits made after it aren't.
+    pub fn shift(&mut self, dx: i32, dy: i32) {
+        let offset = [0, 1].map(|i| [dx, dy][i].rem_euclid(self.size[i] as i32) as u32);
+        if offset == [0, 0] {
+            return;
+        }
+        // Consecutive shifts add up into one copy
+        if let Some(PendingEdit::Shift(last)) = self.pending_edits.last_mut() {
+            *last = [0, 1].map(|i| (last[i] + offset[i]) % self.size[i]);
+        } else {
+            self.pending_edits.push(PendingEdit::Shift(offset));
+        }
+    }
+
+    /// Copy the cells rolled by `offset` into `life_out` and swap it in, and the walls into a
+    /// new buffer. Trails and the temporal filter's history would lag behind the shifted cells,
+    /// they start over.
+    fn record_shift(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        offset: [u32; 2],
+    ) {
+        if offset == [0, 0] {
+            return;
+        }
+        let regions = self.shift_regions(offset);
+        builder
+            .copy_buffer(CopyBufferInfoTyped {
+                regions: regions.iter().cloned().collect(),
+                ..CopyBufferInfoTyped::buffers(self.life_in.clone(), self.life_out.clone())
+            })
+            .unwrap();
+        std::mem::swap(&mut self.life_in, &mut self.life_out);
+
+        if self.has_walls {
+            let walls = empty_grid(&self.compute_queue, self.size).unwrap();
+            builder
+                .copy_buffer(CopyBufferInfoTyped {
+                    regions: regions.into_iter().collect(),
+                    ..CopyBufferInfoTyped::buffers(self.walls.clone(), walls.clone())
+                })
+                .unwrap();
+            self.walls = walls;
+        }
+
+        self.clear_trails();
+        self.history_valid = false;
+        self.stats_valid = false;
+        self.gliders_valid = false;
+    }
+
+    /// Copies of every row rolled by `offset`, each row in two pieces split where it wraps
+    fn shift_regions(&self, offset: [u32; 2]) -> Vec<BufferCopy> {
+        let [width, height] = self.size;
+        let [dx, dy] = offset;
+        (0..height)
+            .flat_map(|y| {
+                let src_row = (y * width) as u64;
+                let dst_row = ((y + dy) % height * width) as u64;
+                // Cells from 0 move to dx, the last dx cells wrap around to 0
+                [(0, dx, width - dx), (width - dx, 0, dx)]
+                    .into_iter()
+                    .filter(|&(_, _, size)| size > 0)
+                    .map(move |(src_x, dst_x, size)| BufferCopy {
+                        src_offset: src_row + src_x as u64,
+                        dst_offset: dst_row + dst_x as u64,
+                        size: size as u64,
+                        ..Default::default()
+                    })
+            })
+            .collect()
+    }
+
+    /// Set a single cell, applied before the next step like `load_pattern`
+    pub fn set_cell(&mut self, cell: [u32; 2], alive: bool) {
+        let pattern = Pattern::new([1, 1], vec![alive as u8]);
+        self.load_pattern(&pattern, [cell[0] as i32, cell[1] as i32]);
+    }
+
+    /// Set many cells to the same state from one staging buffer, applied before the next step
+    /// like `set_cell`
+    pub fn set_cells(&mut self, cells: &[[u32; 2]], alive: bool) {
+        let regions = cells
+            .iter()
+            .flat_map(|&[x, y]| self.copy_regions([1, 1], [x as i32, y as i32], [1, 1]))
+            .collect();
+        self.stage([alive as u32].into_iter(), regions);
+    }
+
+    /// Flip this many random cells per generation on average (`None` for none) after each step.
+    /// They're hashed from the seed the grid was last seeded with and the generation, so the same
+    /// seed always gets the same rays.
+    pub fn set_cosmic_rays(&mut self, cosmic_rays: Option<f32>) {
+        self.cosmic_rays = cosmic_rays;
+    }
+
+    // Seed of the current generation's cosmic rays and the chance of each cell being flipped
+    fn cosmic_ray_roll(&self) -> (u32, f32) {
+        let seed = self.cosmic_ray_seed ^ self.generation.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let cells = self.size[0] as f32 * self.size[1] as f32;
+        let rate = self.cosmic_rays.unwrap_or(0.0).max(0.0);
+        ((seed ^ (seed >> 32)) as u32, rate / cells)
+    }
+
+    /// Split the generations of a `compute_n` call into submissions of at most this many, 0
+    /// submits them all at once. Smaller batches keep a single submission from hogging the GPU.
+    pub fn set_generations_per_submit(&mut self, generations_per_submit: u32) {
+        self.generations_per_submit = generations_per_submit;
+    }
+
+    fn command_builder(&self) -> AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
+        AutoCommandBufferBuilder::primary(
             self.compute_queue.device().clone(),
             self.compute_queue.family(),
             CommandBufferUsage::OneTimeSubmit,
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    /// Record the life steps of `generations` generations (the swaps between them included),
+    /// each dispatch binds the buffers as they are when it's recorded. The automatic barriers
+    /// between the dispatches order each step after the previous one's writes.
+    fn record_generations(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        generations: u32,
+        rule: &Rule,
+        settings: &RenderSettings,
+        palette: &Arc<dyn BufferAccess>,
+    ) {
+        for _ in 0..generations {
+            self.dispatch(builder, rule, settings, palette.clone(), 0);
+            if self.cosmic_rays.is_some() {
+                self.dispatch(builder, rule, settings, palette.clone(), 14);
+            }
+            if self.trails.is_some() {
+                self.dispatch(builder, rule, settings, palette.clone(), 9);
+            }
+            std::mem::swap(&mut self.life_in, &mut self.life_out);
+            self.generation += 1;
+        }
+    }
+
+    /// Simulate `generations` steps, in one submission unless `generations_per_submit` is lower.
+    /// The stats and colors are only computed for the last one. With 0 generations the current
+    /// state is only recolored.
+    pub fn compute_n(
+        &mut self,
+        mut before_future: Box<dyn GpuFuture>,
+        generations: u32,
+        rule: &Rule,
+        settings: &RenderSettings,
+    ) -> Box<dyn GpuFuture> {
+        let mut builder = self.command_builder();
 
         // Dispatch will mutate the builder adding commands which won't be sent before we build the command buffer
         // after dispatches. This will minimize the commands we send to the GPU. For example, we could be doing
         // tens of dispatches here depending on our needs. Maybe we wanted to simulate 10 steps at a time...
 
-        // First compute the next state
-        self.dispatch(&mut builder, life_color, dead_color, 0);
+        if let (Some(previous_view), true) = (&self.previous_view, self.color && generations > 0) {
+            builder
+                .copy_image(CopyImageInfo::images(
+                    self.out_view.image().clone(),
+                    previous_view.image().clone(),
+                ))
+                .unwrap();
+            self.previous_valid = true;
+        }
+
+        let history_weight = if self.history_valid {
+            settings.temporal_blend
+        } else {
+            0.0
+        };
+        // The effects run while their buffers are allocated (see `set_trails`, `set_bloom` and
+        // `set_temporal_filter`), on top of the colors
+        let effects = settings.mode.has_effects() && self.colors.is_some();
+        let trails = self.trails.is_some() && effects;
+        let bloom = self.bloom_rows.is_some() && effects;
+        let temporal_filter = self.history.is_some() && effects;
+        // The passes after coloring work on colors, and captured frames have to be colored
+        if self.color {
+            self.raw_states = settings.fragment_coloring
+                && settings.mode == RenderMode::Cells
+                && !trails
+                && !bloom
+                && !temporal_filter
+                && !self.capture_frame;
+        }
+        let palette: Arc<dyn BufferAccess> = self
+            .palette_pool
+            .next(palette_uniform(
+                settings,
+                history_weight,
+                self.has_walls,
+                self.raw_states,
+                effects,
+            ))
+            .unwrap();
+
+        // Apply edits made since the last frame
+        for edit in std::mem::take(&mut self.pending_edits) {
+            match edit {
+                PendingEdit::Cells(write) => {
+                    builder
+                        .copy_buffer(CopyBufferInfoTyped {
+                            regions: write.regions.into_iter().collect(),
+                            ..CopyBufferInfoTyped::buffers(write.staging, self.life_in.clone())
+                        })
+                        .unwrap();
+                }
+                PendingEdit::Walls(write) => {
+                    builder
+                        .copy_buffer(CopyBufferInfoTyped {
+                            regions: write.regions.into_iter().collect(),
+                            ..CopyBufferInfoTyped::buffers(write.staging, self.walls.clone())
+                        })
+                        .unwrap();
+                }
+                PendingEdit::Shift(offset) => self.record_shift(&mut builder, offset),
+                PendingEdit::Randomize { seed, density } => {
+                    self.random_fill = (seed, density);
+                    self.dispatch(&mut builder, rule, settings, palette.clone(), 13);
+                }
+                PendingEdit::Clear => {
+                    for grid in [self.life_in.clone(), self.life_out.clone()] {
+                        builder
+                            .fill_buffer(FillBufferInfo::dst_buffer(grid))
+                            .unwrap();
+                    }
+                }
+                PendingEdit::ClearWalls => {
+                    builder
+                        .fill_buffer(FillBufferInfo::dst_buffer(self.walls.clone()))
+                        .unwrap();
+                }
+            }
+        }
+
+        // Steps before the last one only need the swap. Full batches are submitted on their own,
+        // the last one (however short) shares the command buffer with the passes below.
+        let mut early = generations.saturating_sub(1);
+        let batch = match self.generations_per_submit {
+            0 => u32::MAX,
+            batch => batch,
+        };
+        while early >= batch {
+            self.record_generations(&mut builder, batch, rule, settings, &palette);
+            early -= batch;
+            let command_buffer = std::mem::replace(&mut builder, self.command_builder())
+                .build()
+                .unwrap();
+            before_future = before_future
+                .then_execute(self.compute_queue.clone(), command_buffer)
+                .unwrap()
+                .then_signal_semaphore_and_flush()
+                .unwrap()
+                .boxed();
+        }
+        self.record_generations(&mut builder, early, rule, settings, &palette);
+
+        if generations > 0 {
+            // First compute the next state
+            self.dispatch(&mut builder, rule, settings, palette.clone(), 0);
+            if self.cosmic_rays.is_some() {
+                self.dispatch(&mut builder, rule, settings, palette.clone(), 14);
+            }
+            if self.trails.is_some() {
+                self.dispatch(&mut builder, rule, settings, palette.clone(), 9);
+            }
+        } else {
+            // The passes below read `life_out`, which then holds the current state (and `life_in`
+            // the previous one), swapped back at the end
+            std::mem::swap(&mut self.life_in, &mut self.life_out);
+        }
+        // Reduce the next state while it's fresh
+        if self.collect_stats || self.count_gliders {
+            builder
+                .update_buffer(Box::new(empty_stats()), self.stats.clone(), 0)
+                .unwrap();
+        }
+        if self.collect_stats {
+            self.dispatch(&mut builder, rule, settings, palette.clone(), 4);
+        }
+        if self.count_gliders {
+            self.dispatch(&mut builder, rule, settings, palette.clone(), 5);
+        }
+        self.stats_valid = self.collect_stats;
+        let stats_copy = if self.collect_stats {
+            self.stats_readback.reserve()
+        } else {
+            None
+        };
+        if let Some(copy) = stats_copy.clone() {
+            builder
+                .copy_buffer(CopyBufferInfo::buffers(self.stats.clone(), copy))
+                .unwrap();
+        }
+        self.gliders_valid = self.count_gliders;
+        let history_copy = match &mut self.history_readback {
+            Some(readback) if generations > 0 => readback.reserve(),
+            _ => None,
+        };
+        if let Some(copy) = history_copy.clone() {
+            builder
+                .copy_buffer(CopyBufferInfoTyped::buffers(self.life_out.clone(), copy))
+                .unwrap();
+        }
         // Then color based on the next state
-        self.dispatch(&mut builder, life_color, dead_color, 1);
+        if self.color {
+            match settings.mode {
+                RenderMode::Cells => {
+                    self.dispatch(&mut builder, rule, settings, palette.clone(), 1)
+                }
+                RenderMode::Density => {
+                    self.dispatch(&mut builder, rule, settings, palette.clone(), 2);
+                    self.dispatch(&mut builder, rule, settings, palette.clone(), 3);
+                }
+                RenderMode::Diff => self.dispatch(&mut builder, rule, settings, palette.clone(), 8),
+                RenderMode::Raw => self.dispatch(&mut builder, rule, settings, palette.clone(), 12),
+            }
+            // A shift among the edits clears the trails
+            if self.trails.is_some() && effects {
+                self.dispatch(&mut builder, rule, settings, palette.clone(), 10);
+            }
+            if temporal_filter {
+                self.dispatch(&mut builder, rule, settings, palette.clone(), 11);
+                self.history_valid = true;
+            } else {
+                self.history_valid = false;
+            }
+            // Blur the bright colors in two separable passes
+            if bloom {
+                self.dispatch(&mut builder, rule, settings, palette.clone(), 6);
+                self.dispatch(&mut builder, rule, settings, palette, 7);
+            }
+        }
+
+        let capture_frame = self.capture_frame && self.color;
+        if capture_frame {
+            builder
+                .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                    self.out_view.image().clone(),
+                    self.frame.clone(),
+                ))
+                .unwrap();
+        }
+        self.frame_valid = capture_frame;
 
         let command_buffer = builder.build().unwrap();
         let finished = before_future
             .then_execute(self.compute_queue.clone(), command_buffer)
-            .unwrap();
-        let after_pipeline = finished.then_signal_fence_and_flush().unwrap().boxed();
+            .unwrap()
+            .boxed();
+        // Shared so we can wait on it before reading back (vulkano only implements this for `Arc`)
+        #[allow(clippy::arc_with_non_send_sync)]
+        let after_pipeline = Arc::new(finished.then_signal_fence_and_flush().unwrap());
+        self.last_compute = Some(after_pipeline.clone());
 
         // Swap input and output so the output becomes the input for next frame
         std::mem::swap(&mut self.life_in, &mut self.life_out);
+        if generations > 0 {
+            self.generation += 1;
+        }
+        if stats_copy.is_some() {
+            self.stats_readback
+                .submit(self.generation, after_pipeline.clone());
+        }
+        if let (Some(readback), Some(_)) = (&mut self.history_readback, history_copy) {
+            readback.submit(self.generation, after_pipeline.clone());
+        }
 
-        after_pipeline
+        after_pipeline.boxed()
     }
 
     /// Build the command for a dispatch.
     fn dispatch(
         &mut self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
-        life_color: [f32; 4],
-        dead_color: [f32; 4],
+        rule: &Rule,
+        settings: &RenderSettings,
+        palette: Arc<dyn BufferAccess>,
         // Step determines whether we color or compute life (see branch in the shader)s
         step: i32,
     ) {
-        // Resize image if needed
-        let img_dims = self.out_view.image().dimensions().width_height();
+        // Coloring runs once per pixel of the (possibly decimated) image, everything else per cell
+        let dims = match step {
+            1 | 3 | 6 | 7 | 8 | 10 | 11 | 12 => self.image_size(),
+            _ => self.size,
+        };
+        let no_pixels = self.no_pixels.clone();
+        let no_trails = self.no_trails.clone();
         let pipeline_layout = self.pipeline.layout();
         let desc_layout = pipeline_layout.set_layouts().get(0).unwrap();
         let set = PersistentDescriptorSet::new(
@@ -137,100 +1745,220 @@ impl GameComputePipeline {
                 WriteDescriptorSet::image_view(0, self.view()),
                 WriteDescriptorSet::buffer(1, self.life_in.clone()),
                 WriteDescriptorSet::buffer(2, self.life_out.clone()),
+                WriteDescriptorSet::buffer(3, palette),
+                WriteDescriptorSet::buffer(4, self.density.clone()),
+                WriteDescriptorSet::buffer(5, self.highlight.clone()),
+                WriteDescriptorSet::buffer(6, self.stats.clone()),
+                WriteDescriptorSet::buffer(7, self.colors.clone().unwrap_or(no_pixels.clone())),
+                WriteDescriptorSet::buffer(8, self.bloom_rows.clone().unwrap_or(no_pixels.clone())),
+                WriteDescriptorSet::image_view(9, self.bloom_view.clone()),
+                WriteDescriptorSet::buffer(10, self.kernel.clone()),
+                WriteDescriptorSet::buffer(11, self.trails.clone().unwrap_or(no_trails)),
+                WriteDescriptorSet::buffer(12, self.block.clone()),
+                WriteDescriptorSet::buffer(13, self.history.clone().unwrap_or(no_pixels)),
+                WriteDescriptorSet::buffer(14, self.walls.clone()),
+                WriteDescriptorSet::buffer(15, self.lenia.clone()),
             ],
         )
         .unwrap();
 
+        let (region_min, region_max) = self.region.unwrap_or(([0, 0], self.size));
+        let (seed, fill_density) = match step {
+            14 => self.cosmic_ray_roll(),
+            _ => self.random_fill,
+        };
         let push_constants = compute_life_cs::ty::PushConstants {
-            life_color,
-            dead_color,
+            life_color: settings.life_color,
+            dead_color: settings.dead_color,
+            highlight_color: settings.highlight_color,
+            region: [
+                region_min[0] as i32,
+                region_min[1] as i32,
+                region_max[0] as i32,
+                region_max[1] as i32,
+            ],
+            grid_size: [self.size[0] as i32, self.size[1] as i32],
             step,
+            density_window: settings.density_window as i32,
+            highlight_changes: settings.highlight_changes as i32,
+            birth_mask: rule.birth as i32,
+            survival_mask: rule.survival as i32,
+            decimation: self.decimation as i32,
+            bloom_threshold: settings.bloom_threshold,
+            border: self.border as i32,
+            wrap: self.wrap as i32,
+            topology: self.topology as i32,
+            trail_fade: settings.trail_fade,
+            // Life steps are recorded before the generation is incremented
+            block_phase: (self.generation % 2) as i32,
+            seed,
+            fill_density,
         };
         builder
             .bind_pipeline_compute(self.pipeline.clone())
             .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
             .push_constants(pipeline_layout.clone(), 0, push_constants)
-            .dispatch([img_dims[0] / 8, img_dims[1] / 8, 1])
+            .dispatch([
+                dims[0].div_ceil(WORKGROUP_SIZE),
+                dims[1].div_ceil(WORKGROUP_SIZE),
+                1,
+            ])
             .unwrap();
     }
 }
 
+fn kernel_buffer(
+    compute_queue: &Arc<Queue>,
+    kernel_rule: Option<&KernelRule>,
+) -> Result<Arc<CpuAccessibleBuffer<KernelBuffer>>, InitError> {
+    let mut kernel = KernelBuffer {
+        radius: 0,
+        birth_low: 0.0,
+        birth_high: 0.0,
+        survival_low: 0.0,
+        survival_high: 0.0,
+        weights: [0.0; MAX_KERNEL_WEIGHTS],
+    };
+    if let Some(rule) = kernel_rule {
+        kernel.radius = rule.radius.min(MAX_KERNEL_RADIUS) as i32;
+        [kernel.birth_low, kernel.birth_high] = rule.birth;
+        [kernel.survival_low, kernel.survival_high] = rule.survival;
+        for (weight, &rule_weight) in kernel.weights.iter_mut().zip(&rule.weights) {
+            *weight = rule_weight;
+        }
+    }
+
+    CpuAccessibleBuffer::from_data(
+        compute_queue.device().clone(),
+        BufferUsage::storage_buffer(),
+        false,
+        kernel,
+    )
+    .map_err(|err| InitError::allocation("the kernel buffer", err))
+}
+
+fn block_buffer(
+    compute_queue: &Arc<Queue>,
+    block_rule: Option<BlockRule>,
+) -> Result<Arc<CpuAccessibleBuffer<BlockBuffer>>, InitError> {
+    let block = BlockBuffer {
+        active: block_rule.is_some() as i32,
+        table: block_rule.map_or([0; 16], |rule| rule.table()),
+    };
+
+    CpuAccessibleBuffer::from_data(
+        compute_queue.device().clone(),
+        BufferUsage::storage_buffer(),
+        false,
+        block,
+    )
+    .map_err(|err| InitError::allocation("the block rule buffer", err))
+}
+
+fn lenia_buffer(
+    compute_queue: &Arc<Queue>,
+    lenia_rule: Option<&LeniaRule>,
+) -> Result<Arc<CpuAccessibleBuffer<LeniaBuffer>>, InitError> {
+    let mut lenia = LeniaBuffer {
+        active: 0,
+        radius: 0,
+        growth_center: 0.0,
+        growth_width: 0.0,
+        time_step: 0.0,
+        weights: [0.0; MAX_LENIA_WEIGHTS],
+    };
+    if let Some(rule) = lenia_rule {
+        lenia.active = 1;
+        lenia.radius = rule.radius.clamp(1, MAX_LENIA_RADIUS) as i32;
+        lenia.growth_center = rule.growth_center;
+        lenia.growth_width = rule.growth_width.max(1e-4);
+        lenia.time_step = rule.time_step;
+        for (weight, rule_weight) in lenia.weights.iter_mut().zip(rule.weights()) {
+            *weight = rule_weight;
+        }
+    }
+
+    CpuAccessibleBuffer::from_data(
+        compute_queue.device().clone(),
+        BufferUsage::storage_buffer(),
+        false,
+        lenia,
+    )
+    .map_err(|err| InitError::allocation("the Lenia rule buffer", err))
+}
+
+fn grid_stats(stats: &StatsBuffer) -> GridStats {
+    let population = stats.population as u64;
+    if population == 0 {
+        return GridStats {
+            population,
+            centroid: None,
+            bounding_box: None,
+        };
+    }
+
+    let sum_x = (stats.sum_x_high as u64) << 32 | stats.sum_x_low as u64;
+    let sum_y = (stats.sum_y_high as u64) << 32 | stats.sum_y_low as u64;
+    GridStats {
+        population,
+        // Offset by half a cell so the centroid is at the center of the cells
+        centroid: Some([
+            (sum_x as f64 / population as f64) as f32 + 0.5,
+            (sum_y as f64 / population as f64) as f32 + 0.5,
+        ]),
+        bounding_box: Some(([stats.min_x, stats.min_y], [stats.max_x, stats.max_y])),
+    }
+}
+
+fn palette_uniform(
+    settings: &RenderSettings,
+    history_weight: f32,
+    walls: bool,
+    raw_states: bool,
+    keep_colors: bool,
+) -> Palette {
+    let gradient = &settings.density_palette;
+    let mut stops = [[0.0; 4]; MAX_GRADIENT_STOPS];
+    for (packed, stop) in stops.iter_mut().zip(gradient.stops()) {
+        // Position is packed into the alpha channel
+        let [r, g, b] = stop.color;
+        *packed = [r, g, b, stop.position];
+    }
+
+    Palette {
+        stops,
+        born_color: settings.born_color,
+        died_color: settings.died_color,
+        trail_color: settings.trail_color,
+        wall_color: settings.wall_color,
+        stop_count: gradient.stops().len() as i32,
+        history_weight,
+        walls: walls as i32,
+        raw_states: raw_states as i32,
+        keep_colors: keep_colors as i32,
+    }
+}
+
 mod compute_life_cs {
     vulkano_shaders::shader! {
         ty: "compute",
-        src: "
-#version 450
-
-layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
-
-layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
-layout(set = 0, binding = 1) buffer LifeInBuffer { uint life_in[]; };
-layout(set = 0, binding = 2) buffer LifeOutBuffer { uint life_out[]; };
-
-layout(push_constant) uniform PushConstants {
-    vec4 life_color;
-    vec4 dead_color;
-    int step;
-} push_constants;
-
-int get_index(ivec2 pos) {
-    ivec2 dims = ivec2(imageSize(img));
-    return pos.y * dims.x + pos.x;
-}
-
-// https://en.wikipedia.org/wiki/Conway%27s_Game_of_Life
-void compute_life() {
-    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
-    int index = get_index(pos);
-    
-    ivec2 up_left = pos + ivec2(-1, 1);
-    ivec2 up = pos + ivec2(0, 1);
-    ivec2 up_right = pos + ivec2(1, 1);
-    ivec2 right = pos + ivec2(1, 0);
-    ivec2 down_right = pos + ivec2(1, -1);
-    ivec2 down = pos + ivec2(0, -1);
-    ivec2 down_left = pos + ivec2(-1, -1);
-    ivec2 left = pos + ivec2(-1, 0);
-
-    int alive_count = 0;
-    if (life_out[get_index(up_left)] == 1) { alive_count += 1; }
-    if (life_out[get_index(up)] == 1) { alive_count += 1; }
-    if (life_out[get_index(up_right)] == 1) { alive_count += 1; }
-    if (life_out[get_index(right)] == 1) { alive_count += 1; }
-    if (life_out[get_index(down_right)] == 1) { alive_count += 1; }
-    if (life_out[get_index(down)] == 1) { alive_count += 1; }
-    if (life_out[get_index(down_left)] == 1) { alive_count += 1; }
-    if (life_out[get_index(left)] == 1) { alive_count += 1; }
-
-    // Dead becomes alive
-    if (life_out[index] == 0 && alive_count == 3) {
-        life_out[index] = 1;
-    } // Becomes dead
-    else if (life_out[index] == 1 && alive_count < 2 || alive_count > 3) {
-        life_out[index] = 0;
-    } // Else Do nothing
-    else {
-       
-        life_out[index] = life_in[index];
-    }
-}
-
-void compute_color() {
-    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
-    int index = get_index(pos);
-    if (life_out[index] == 1) {
-        imageStore(img, pos, push_constants.life_color);
-    } else {
-        imageStore(img, pos, push_constants.dead_color);
+        path: "src/shaders/compute_life.comp",
+        define: [("IMAGE_FORMAT", "rgba8")],
+        types_meta: {
+            use bytemuck::{Pod, Zeroable};
+
+            #[derive(Clone, Copy, Zeroable, Pod)]
+        }
     }
 }
 
-void main() {
-    if (push_constants.step == 0) {
-        compute_life();
-    } else {
-        compute_color();
-    }
-}"
+// The same shader writing `HDR_FORMAT` images. Only `load` is used, the types are the same as
+// `compute_life_cs`'s.
+#[allow(dead_code)]
+mod compute_life_hdr_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/compute_life.comp",
+        define: [("IMAGE_FORMAT", "rgba16f")],
     }
 }
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of stops a gradient can hold (limited by the shader palette size)
+pub const MAX_GRADIENT_STOPS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: [f32; 3],
+}
+
+/// Piecewise linear color ramp over `0.0..=1.0`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<GradientStop>", into = "Vec<GradientStop>")]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    pub fn new(mut stops: Vec<GradientStop>) -> Self {
+        assert!(
+            (1..=MAX_GRADIENT_STOPS).contains(&stops.len()),
+            "gradient must have between 1 and {} stops",
+            MAX_GRADIENT_STOPS
+        );
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self { stops }
+    }
+
+    /// Stops sorted by position
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    pub fn sample(&self, t: f32) -> [f32; 3] {
+        let first = self.stops[0];
+        if t <= first.position {
+            return first.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (lower, upper) = (pair[0], pair[1]);
+            if t <= upper.position {
+                let f = (t - lower.position) / (upper.position - lower.position).max(1e-6);
+                return [0, 1, 2].map(|i| lower.color[i] + (upper.color[i] - lower.color[i]) * f);
+            }
+        }
+
+        self.stops[self.stops.len() - 1].color
+    }
+}
+
+impl TryFrom<Vec<GradientStop>> for Gradient {
+    type Error = String;
+
+    fn try_from(stops: Vec<GradientStop>) -> Result<Self, Self::Error> {
+        if (1..=MAX_GRADIENT_STOPS).contains(&stops.len()) {
+            Ok(Self::new(stops))
+        } else {
+            Err(format!(
+                "gradient must have between 1 and {} stops",
+                MAX_GRADIENT_STOPS
+            ))
+        }
+    }
+}
+
+impl From<Gradient> for Vec<GradientStop> {
+    fn from(gradient: Gradient) -> Self {
+        gradient.stops
+    }
+}
+
+impl Default for Gradient {
+    /// Black body style heat palette
+    fn default() -> Self {
+        Self::new(vec![
+            GradientStop {
+                position: 0.0,
+                color: [0.0, 0.0, 0.0],
+            },
+            GradientStop {
+                position: 0.25,
+                color: [0.5, 0.0, 0.0],
+            },
+            GradientStop {
+                position: 0.5,
+                color: [1.0, 0.4, 0.0],
+            },
+            GradientStop {
+                position: 0.75,
+                color: [1.0, 0.9, 0.2],
+            },
+            GradientStop {
+                position: 1.0,
+                color: [1.0, 1.0, 1.0],
+            },
+        ])
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt;
+
+// Every other sample is dropped once there are more than this many, so a long measurement keeps
+// covering everything since it started at a coarser spacing
+const MAX_SAMPLES: usize = 512;
+
+// Fewer samples than this don't say anything about the trend
+const MIN_SAMPLES: usize = 16;
+
+// Growth over the window smaller than this fraction of the average population counts as bounded
+const BOUNDED_FRACTION: f64 = 0.05;
+
+// Share of the growth over the window the quadratic term must explain to count as quadratic
+const QUADRATIC_SHARE: f64 = 0.25;
+
+/// How the population scales with generations, with the fitted rate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Growth {
+    Bounded,
+    /// Cells gained per generation, like a gun
+    Linear {
+        rate: f64,
+    },
+    /// Cells gained per generation squared, like a breeder
+    Quadratic {
+        coefficient: f64,
+    },
+}
+
+impl fmt::Display for Growth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Growth::Bounded => write!(f, "bounded"),
+            Growth::Linear { rate } => write!(f, "linear growth, {:.3} cells/gen", rate),
+            Growth::Quadratic { coefficient } => {
+                write!(f, "quadratic growth, {:.5} cells/gen²", coefficient)
+            }
+        }
+    }
+}
+
+/// Population samples since the measurement started, fit against the generations passed to
+/// classify the growth. Measure from when the pattern starts, a quadratic curve sampled long
+/// after its start looks close to linear.
+#[derive(Default)]
+pub struct GrowthMeter {
+    // Generation and population, in increasing generations
+    samples: Vec<(u64, u64)>,
+}
+
+impl GrowthMeter {
+    /// Add the population of a generation. A generation that was already sampled is skipped,
+    /// an earlier one (the grid was reset) starts the measurement over.
+    pub fn sample(&mut self, generation: u64, population: u64) {
+        match self.samples.last() {
+            Some(&(last, _)) if last == generation => return,
+            Some(&(last, _)) if last > generation => self.reset(),
+            _ => (),
+        }
+
+        self.samples.push((generation, population));
+        if self.samples.len() > MAX_SAMPLES {
+            // Keep the latest sample so the window still reaches the current generation
+            let latest = self.samples[self.samples.len() - 1];
+            let mut index = 0;
+            self.samples.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            if self.samples.last() != Some(&latest) {
+                self.samples.push(latest);
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Generations covered by the samples
+    pub fn generations(&self) -> u64 {
+        match (self.samples.first(), self.samples.last()) {
+            (Some(&(first, _)), Some(&(last, _))) => last - first,
+            _ => 0,
+        }
+    }
+
+    /// Classify the growth from a least squares fit, `None` until there are enough samples
+    pub fn estimate(&self) -> Option<Growth> {
+        let span = self.generations() as f64;
+        if self.samples.len() < MIN_SAMPLES || span == 0.0 {
+            return None;
+        }
+
+        // Fit p = a + b * x + c * x^2 with x going from 0 to 1 across the window, so b and c
+        // are the growth each term adds over the whole window
+        let first = self.samples[0].0;
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .map(|&(generation, population)| {
+                ((generation - first) as f64 / span, population as f64)
+            })
+            .collect();
+        let [_, b, c] = fit_quadratic(&points)?;
+        let mean = points.iter().map(|&(_, p)| p).sum::<f64>() / points.len() as f64;
+
+        let growth = b.abs() + c.abs();
+        if growth <= BOUNDED_FRACTION * mean.max(1.0) {
+            Some(Growth::Bounded)
+        } else if c.abs() > QUADRATIC_SHARE * growth {
+            Some(Growth::Quadratic {
+                coefficient: c / (span * span),
+            })
+        } else {
+            Some(Growth::Linear {
+                rate: fit_slope(&points) / span,
+            })
+        }
+    }
+}
+
+/// Least squares line through the points, its slope
+fn fit_slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for &(x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x) * (x - mean_x);
+    }
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    }
+}
+
+/// Least squares parabola through the points, `[a, b, c]` of `a + b * x + c * x^2`. `None` if
+/// the points don't pin one down (fewer than three distinct x).
+fn fit_quadratic(points: &[(f64, f64)]) -> Option<[f64; 3]> {
+    // Normal equations, sums of x^(i + j) on the left and y * x^i on the right
+    let mut powers = [0.0; 5];
+    let mut rhs = [0.0; 3];
+    for &(x, y) in points {
+        let mut power = 1.0;
+        for (i, sum) in powers.iter_mut().enumerate() {
+            *sum += power;
+            if i < 3 {
+                rhs[i] += y * power;
+            }
+            power *= x;
+        }
+    }
+    let matrix = [0, 1, 2].map(|i| [powers[i], powers[i + 1], powers[i + 2]]);
+
+    // Cramer's rule, the system is only 3x3
+    let det = determinant(matrix);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some([0, 1, 2].map(|column| {
+        let mut replaced = matrix;
+        for (row, value) in replaced.iter_mut().zip(rhs) {
+            row[column] = value;
+        }
+        determinant(replaced) / det
+    }))
+}
+
+fn determinant(m: [[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meter(samples: impl IntoIterator<Item = (u64, u64)>) -> GrowthMeter {
+        let mut meter = GrowthMeter::default();
+        for (generation, population) in samples {
+            meter.sample(generation, population);
+        }
+        meter
+    }
+
+    // Repeatable jitter in -6..=6
+    fn noise(generation: u64) -> i64 {
+        (generation * 7919 % 13) as i64 - 6
+    }
+
+    #[test]
+    fn fit_quadratic_recovers_coefficients() {
+        let points: Vec<(f64, f64)> = (0..10)
+            .map(|i| {
+                let x = i as f64 / 9.0;
+                (x, 3.0 - 2.0 * x + 5.0 * x * x)
+            })
+            .collect();
+        let [a, b, c] = fit_quadratic(&points).unwrap();
+        assert!((a - 3.0).abs() < 1e-9);
+        assert!((b + 2.0).abs() < 1e-9);
+        assert!((c - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fit_quadratic_needs_three_distinct_x() {
+        assert_eq!(fit_quadratic(&[(0.0, 1.0), (1.0, 2.0), (1.0, 3.0)]), None);
+    }
+
+    #[test]
+    fn constant_is_bounded() {
+        let meter = meter((0..64).map(|generation| (generation, 500)));
+        assert_eq!(meter.estimate(), Some(Growth::Bounded));
+    }
+
+    #[test]
+    fn linear_rate() {
+        let meter = meter((0..100).map(|generation| (generation, 100 + 3 * generation)));
+        match meter.estimate() {
+            Some(Growth::Linear { rate }) => assert!((rate - 3.0).abs() < 1e-9),
+            other => panic!("expected linear growth, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quadratic_coefficient() {
+        let meter =
+            meter((0..100).map(|generation| (generation, 100 + generation * generation / 2)));
+        match meter.estimate() {
+            Some(Growth::Quadratic { coefficient }) => assert!((coefficient - 0.5).abs() < 0.01),
+            other => panic!("expected quadratic growth, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn short_series_has_no_estimate() {
+        let meter = meter((0..MIN_SAMPLES as u64 - 1).map(|generation| (generation, generation)));
+        assert_eq!(meter.estimate(), None);
+    }
+
+    #[test]
+    fn noise_around_a_constant_is_bounded() {
+        let meter =
+            meter((0..200).map(|generation| (generation, (1000 + noise(generation)) as u64)));
+        assert_eq!(meter.estimate(), Some(Growth::Bounded));
+    }
+
+    #[test]
+    fn noisy_linear_rate() {
+        let meter = meter((0..200).map(|generation| {
+            (
+                generation,
+                (1000 + 5 * generation as i64 + noise(generation)) as u64,
+            )
+        }));
+        match meter.estimate() {
+            Some(Growth::Linear { rate }) => assert!((rate - 5.0).abs() < 0.1),
+            other => panic!("expected linear growth, got {:?}", other),
+        }
+    }
+}
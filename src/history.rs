@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pattern::Pattern;
+
+/// Copies of the grid in flight from the GPU at once while recording, a `u32` per cell each
+pub const READBACK_SLOTS: usize = 3;
+
+/// How each past generation is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryStorage {
+    /// A byte per cell, nothing to unpack when stepping back
+    Full,
+    /// A bit per cell, an eighth of the memory
+    BitPacked,
+}
+
+impl HistoryStorage {
+    pub const ALL: [HistoryStorage; 2] = [HistoryStorage::Full, HistoryStorage::BitPacked];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoryStorage::Full => "Full",
+            HistoryStorage::BitPacked => "Bit packed",
+        }
+    }
+
+    /// Bytes one generation of a grid this size takes
+    pub fn bytes_per_generation(&self, size: [u32; 2]) -> u64 {
+        let cells = size[0] as u64 * size[1] as u64;
+        match self {
+            HistoryStorage::Full => cells,
+            HistoryStorage::BitPacked => cells.div_ceil(8),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    /// Past generations kept, fewer if they don't fit `budget_mib`
+    pub generations: u32,
+    pub storage: HistoryStorage,
+    /// Most memory the history may take, in MiB
+    pub budget_mib: u32,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            generations: 64,
+            storage: HistoryStorage::BitPacked,
+            budget_mib: 256,
+        }
+    }
+}
+
+impl HistorySettings {
+    /// Generations kept for a grid this size, `generations` cut down to what fits the budget
+    /// left after the readback buffers
+    pub fn capacity(&self, size: [u32; 2]) -> u32 {
+        let budget = (self.budget_mib as u64 * 1024 * 1024).saturating_sub(readback_bytes(size));
+        let fits = budget / self.storage.bytes_per_generation(size).max(1);
+        self.generations.min(fits.min(u32::MAX as u64) as u32)
+    }
+}
+
+/// Bytes of the buffers the grid is read back through while recording a history
+pub fn readback_bytes(size: [u32; 2]) -> u64 {
+    READBACK_SLOTS as u64 * size[0] as u64 * size[1] as u64 * 4
+}
+
+struct Frame {
+    generation: u64,
+    storage: HistoryStorage,
+    data: Vec<u8>,
+}
+
+/// Grid at each of the last few generations, newest last, to step back through
+#[derive(Default)]
+pub struct GridHistory {
+    frames: VecDeque<Frame>,
+    size: [u32; 2],
+}
+
+impl GridHistory {
+    /// Keep the cells (one value per cell, row by row) of a generation, dropping the oldest past
+    /// `capacity`. Generations from the same one on were undone (or the grid was reset), they're
+    /// dropped first.
+    pub fn push(
+        &mut self,
+        generation: u64,
+        size: [u32; 2],
+        cells: &[u32],
+        storage: HistoryStorage,
+        capacity: u32,
+    ) {
+        if size != self.size {
+            self.frames.clear();
+            self.size = size;
+        }
+        while matches!(self.frames.back(), Some(frame) if frame.generation >= generation) {
+            self.frames.pop_back();
+        }
+
+        let data = match storage {
+            HistoryStorage::Full => cells.iter().map(|&cell| cell as u8).collect(),
+            HistoryStorage::BitPacked => {
+                let mut packed = vec![0u8; cells.len().div_ceil(8)];
+                for (i, &cell) in cells.iter().enumerate() {
+                    packed[i / 8] |= ((cell & 1) as u8) << (i % 8);
+                }
+                packed
+            }
+        };
+        self.frames.push_back(Frame {
+            generation,
+            storage,
+            data,
+        });
+        self.truncate(capacity);
+    }
+
+    /// Drop the oldest generations past `capacity`
+    pub fn truncate(&mut self, capacity: u32) {
+        while self.frames.len() > capacity as usize {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Remove the newest generation before `current` and return it with its cells, dropping any
+    /// from `current` on
+    pub fn step_back(&mut self, current: u64) -> Option<(u64, Pattern)> {
+        while matches!(self.frames.back(), Some(frame) if frame.generation >= current) {
+            self.frames.pop_back();
+        }
+        let frame = self.frames.pop_back()?;
+        let cells = match frame.storage {
+            HistoryStorage::Full => frame.data,
+            HistoryStorage::BitPacked => (0..(self.size[0] * self.size[1]) as usize)
+                .map(|i| (frame.data[i / 8] >> (i % 8)) & 1)
+                .collect(),
+        };
+        Some((frame.generation, Pattern::new(self.size, cells)))
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Bytes taken by the kept generations
+    pub fn memory(&self) -> u64 {
+        self.frames
+            .iter()
+            .map(|frame| frame.data.len() as u64)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 35 cells, neither a whole number of bytes nor of `u32`s
+    const SIZE: [u32; 2] = [7, 5];
+
+    fn cells(seed: u32) -> Vec<u32> {
+        (0..SIZE[0] * SIZE[1])
+            .map(|i| (i * 7 + seed) % 3 % 2)
+            .collect()
+    }
+
+    fn values(pattern: &Pattern) -> Vec<u32> {
+        pattern.cells().iter().map(|&cell| cell as u32).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for storage in HistoryStorage::ALL {
+            let mut history = GridHistory::default();
+            let cells = cells(1);
+            history.push(10, SIZE, &cells, storage, 4);
+            assert_eq!(history.memory(), storage.bytes_per_generation(SIZE));
+
+            let (generation, pattern) = history.step_back(11).unwrap();
+            assert_eq!(generation, 10);
+            assert_eq!(pattern.size(), SIZE);
+            assert_eq!(values(&pattern), cells, "{}", storage.name());
+        }
+    }
+
+    #[test]
+    fn oldest_dropped_past_capacity() {
+        let mut history = GridHistory::default();
+        for generation in 0..6 {
+            history.push(
+                generation,
+                SIZE,
+                &cells(generation as u32),
+                HistoryStorage::BitPacked,
+                3,
+            );
+        }
+        assert_eq!(history.len(), 3);
+
+        for generation in [5, 4, 3] {
+            let (stepped, pattern) = history.step_back(generation + 1).unwrap();
+            assert_eq!(stepped, generation);
+            assert_eq!(values(&pattern), cells(generation as u32));
+        }
+        assert!(history.step_back(3).is_none());
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn truncate_keeps_the_newest() {
+        let mut history = GridHistory::default();
+        for generation in 0..5 {
+            history.push(generation, SIZE, &cells(0), HistoryStorage::Full, 5);
+        }
+        history.truncate(2);
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history.step_back(5).map(|(generation, _)| generation),
+            Some(4)
+        );
+        assert_eq!(
+            history.step_back(4).map(|(generation, _)| generation),
+            Some(3)
+        );
+        assert!(history.step_back(3).is_none());
+    }
+
+    #[test]
+    fn undone_generations_and_new_sizes_are_dropped() {
+        let mut history = GridHistory::default();
+        for generation in 0..4 {
+            history.push(generation, SIZE, &cells(0), HistoryStorage::Full, 8);
+        }
+        // Stepped back to 1 and simulated again
+        history.push(2, SIZE, &cells(0), HistoryStorage::Full, 8);
+        assert_eq!(history.len(), 3);
+        history.push(3, [8, 8], &[0; 64], HistoryStorage::Full, 8);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn capacity_fits_the_budget() {
+        let size = [1024, 1024];
+        let mut settings = HistorySettings {
+            generations: 10_000,
+            ..HistorySettings::default()
+        };
+        // 256 MiB less 12 MiB of readback buffers, at 128 KiB a generation
+        assert_eq!(settings.capacity(size), 244 * 8);
+        settings.storage = HistoryStorage::Full;
+        assert_eq!(settings.capacity(size), 244);
+        settings.generations = 64;
+        assert_eq!(settings.capacity(size), 64);
+        // Nothing left after the readback buffers
+        settings.budget_mib = 12;
+        assert_eq!(settings.capacity(size), 0);
+    }
+}
@@ -0,0 +1,49 @@
+use std::{error::Error, fmt};
+
+type Source = Box<dyn Error + Send + Sync>;
+
+/// Failure to create the GPU resources of the simulation or the renderer, with what was being
+/// created when it happened
+#[derive(Debug)]
+pub enum InitError {
+    /// A buffer or image couldn't be allocated, usually out of device memory
+    Allocation(&'static str, Source),
+    /// A shader, pipeline or other GPU object couldn't be created
+    Creation(&'static str, Source),
+    /// The grid's buffers or images would exceed the device limits, with the side of the
+    /// largest square grid that fits
+    GridTooLarge { size: [u32; 2], max_side: u32 },
+}
+
+impl InitError {
+    pub fn allocation(what: &'static str, err: impl Error + Send + Sync + 'static) -> Self {
+        InitError::Allocation(what, Box::new(err))
+    }
+
+    pub fn creation(what: &'static str, err: impl Error + Send + Sync + 'static) -> Self {
+        InitError::Creation(what, Box::new(err))
+    }
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Allocation(what, err) => write!(f, "failed to allocate {}: {}", what, err),
+            InitError::Creation(what, err) => write!(f, "failed to create {}: {}", what, err),
+            InitError::GridTooLarge { size, max_side } => write!(
+                f,
+                "grid size {}x{} too large for this GPU (max {}x{})",
+                size[0], size[1], max_side, max_side
+            ),
+        }
+    }
+}
+
+impl Error for InitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InitError::Allocation(_, err) | InitError::Creation(_, err) => Some(err.as_ref()),
+            InitError::GridTooLarge { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use lifetime::{pattern::Pattern, rule::Rule, seeding::SeedSettings};
+
+/// An interaction with the simulation, replayed in the order it was recorded
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Action {
+    /// Fill the grid from the seed and start over from generation 0
+    Seed(SeedSettings),
+    Rule(Rule),
+    /// Cells set alive (or dead) along a drawn line
+    Cells {
+        cells: Vec<[u32; 2]>,
+        alive: bool,
+    },
+    /// Cells made walls (or normal cells again) along a drawn line
+    Walls {
+        cells: Vec<[u32; 2]>,
+        wall: bool,
+    },
+    /// Pattern stamped with its top left corner at `offset`
+    Stamp {
+        offset: [i32; 2],
+        pattern: Pattern,
+    },
+    /// Every cell rolled this many cells right and down, wrapping around
+    Shift([i32; 2]),
+    /// Copies of a pattern tiled across the cells from `min` to `max`
+    Tile {
+        min: [u32; 2],
+        max: [u32; 2],
+        spacing: [u32; 2],
+        pattern: Pattern,
+    },
+    View {
+        offset: [f32; 2],
+        scale: f32,
+    },
+    /// Cells the cosmic rays flip per generation, `None` to turn them off
+    CosmicRays(Option<f32>),
+    /// Generations simulated in one frame
+    Generations(u32),
+}
+
+impl Action {
+    /// Whether the action changes the grid, edits are applied before the generations of the
+    /// frame they happen in
+    fn is_edit(&self) -> bool {
+        !matches!(self, Action::View { .. } | Action::Generations(_))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the recording started
+    pub time: f32,
+    pub action: Action,
+}
+
+/// Recorded sequence of actions, saved as JSON
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputMacro {
+    pub entries: Vec<Entry>,
+}
+
+impl InputMacro {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+pub struct MacroRecorder {
+    start: Instant,
+    recorded: InputMacro,
+    // Last recorded view, rule and cosmic rays, they're recorded whenever they change
+    pub last_view: Option<([f32; 2], f32)>,
+    pub last_rule: Option<Rule>,
+    pub last_cosmic_rays: Option<Option<f32>>,
+}
+
+impl MacroRecorder {
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            recorded: InputMacro::default(),
+            last_view: None,
+            last_rule: None,
+            last_cosmic_rays: None,
+        }
+    }
+
+    pub fn push(&mut self, action: Action) {
+        match action {
+            Action::View { offset, scale } => self.last_view = Some((offset, scale)),
+            Action::Rule(rule) => self.last_rule = Some(rule),
+            Action::CosmicRays(rate) => self.last_cosmic_rays = Some(rate),
+            _ => (),
+        }
+        self.recorded.entries.push(Entry {
+            time: self.start.elapsed().as_secs_f32(),
+            action,
+        });
+    }
+
+    pub fn finish(self) -> InputMacro {
+        self.recorded
+    }
+}
+
+/// Hands out the actions of a macro as their time comes. The grid ends up the same as when it was
+/// recorded however fast the frames are, only the pacing can differ.
+pub struct MacroPlayer {
+    start: Instant,
+    entries: Vec<Entry>,
+    next: usize,
+}
+
+impl MacroPlayer {
+    pub fn start(input_macro: InputMacro) -> Self {
+        Self {
+            start: Instant::now(),
+            entries: input_macro.entries,
+            next: 0,
+        }
+    }
+
+    /// Actions due this frame. Stops at an edit that follows generations, since applying it this
+    /// frame would move it before them, it's handed out next frame instead.
+    pub fn due(&mut self) -> Vec<Action> {
+        let now = self.start.elapsed();
+        let mut due = vec![];
+        let mut simulated = false;
+        while let Some(entry) = self.entries.get(self.next) {
+            if Duration::from_secs_f32(entry.time.max(0.0)) > now
+                || (simulated && entry.action.is_edit())
+            {
+                break;
+            }
+            simulated |= matches!(entry.action, Action::Generations(_));
+            due.push(entry.action.clone());
+            self.next += 1;
+        }
+        due
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.entries.len()
+    }
+
+    /// Fraction of the actions handed out so far
+    pub fn progress(&self) -> f32 {
+        self.next as f32 / self.entries.len().max(1) as f32
+    }
+}
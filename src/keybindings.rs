@@ -0,0 +1,180 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+/// Something a key can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Command {
+    Paste,
+    Copy,
+    CycleTheme,
+    TogglePanel,
+    TogglePause,
+    /// Drop the pattern being placed and whatever is being dragged out
+    Cancel,
+    NewWindow,
+    /// Roll the grid `AppSettings::shift_step` cells, wrapping around
+    ShiftLeft,
+    ShiftRight,
+    ShiftUp,
+    ShiftDown,
+    /// Go back to the previous generation kept in the history
+    StepBack,
+    /// Switch to a random life-like rule on a freshly seeded grid
+    RandomRule,
+    /// Turn the pattern being placed a quarter turn clockwise
+    RotateStamp,
+    /// Mirror the pattern being placed left to right
+    FlipStamp,
+}
+
+impl Command {
+    pub const ALL: [Command; 15] = [
+        Command::Paste,
+        Command::Copy,
+        Command::CycleTheme,
+        Command::TogglePanel,
+        Command::TogglePause,
+        Command::Cancel,
+        Command::NewWindow,
+        Command::ShiftLeft,
+        Command::ShiftRight,
+        Command::ShiftUp,
+        Command::ShiftDown,
+        Command::StepBack,
+        Command::RandomRule,
+        Command::RotateStamp,
+        Command::FlipStamp,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Paste => "Paste pattern",
+            Command::Copy => "Copy selection",
+            Command::CycleTheme => "Next theme",
+            Command::TogglePanel => "Hide/show panel",
+            Command::TogglePause => "Pause/resume",
+            Command::Cancel => "Cancel",
+            Command::NewWindow => "New window",
+            Command::ShiftLeft => "Shift grid left",
+            Command::ShiftRight => "Shift grid right",
+            Command::ShiftUp => "Shift grid up",
+            Command::ShiftDown => "Shift grid down",
+            Command::StepBack => "Step back",
+            Command::RandomRule => "Random rule",
+            Command::RotateStamp => "Rotate pattern",
+            Command::FlipStamp => "Flip pattern",
+        }
+    }
+
+    fn default_binding(&self) -> KeyBinding {
+        let (key, ctrl) = match self {
+            Command::Paste => (VirtualKeyCode::V, true),
+            Command::Copy => (VirtualKeyCode::C, true),
+            Command::CycleTheme => (VirtualKeyCode::T, false),
+            Command::TogglePanel => (VirtualKeyCode::H, false),
+            Command::TogglePause => (VirtualKeyCode::Space, false),
+            Command::Cancel => (VirtualKeyCode::Escape, false),
+            Command::NewWindow => (VirtualKeyCode::F2, false),
+            Command::ShiftLeft => (VirtualKeyCode::Left, true),
+            Command::ShiftRight => (VirtualKeyCode::Right, true),
+            Command::ShiftUp => (VirtualKeyCode::Up, true),
+            Command::ShiftDown => (VirtualKeyCode::Down, true),
+            Command::StepBack => (VirtualKeyCode::Back, false),
+            Command::RandomRule => (VirtualKeyCode::R, false),
+            Command::RotateStamp => (VirtualKeyCode::E, false),
+            Command::FlipStamp => (VirtualKeyCode::F, false),
+        };
+        KeyBinding { key, ctrl }
+    }
+}
+
+/// A key, with or without Ctrl (Cmd on macOS) held
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+}
+
+impl KeyBinding {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Self {
+        Self {
+            key,
+            ctrl: modifiers.ctrl() || modifiers.logo(),
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Key of each command, only the ones changed from the defaults are saved so new commands get
+/// their default key in old settings files
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    changed: HashMap<Command, KeyBinding>,
+}
+
+impl KeyBindings {
+    pub fn get(&self, command: Command) -> KeyBinding {
+        self.changed
+            .get(&command)
+            .copied()
+            .unwrap_or_else(|| command.default_binding())
+    }
+
+    pub fn set(&mut self, command: Command, binding: KeyBinding) {
+        if binding == command.default_binding() {
+            self.changed.remove(&command);
+        } else {
+            self.changed.insert(command, binding);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.changed.clear();
+    }
+
+    /// The command bound to a key press, the first in `Command::ALL` if several share the key
+    pub fn command(&self, binding: KeyBinding) -> Option<Command> {
+        Command::ALL
+            .into_iter()
+            .find(|&command| self.get(command) == binding)
+    }
+
+    /// Pairs of commands bound to the same key, only the first of each pair can be used
+    pub fn conflicts(&self) -> Vec<(Command, Command)> {
+        let mut conflicts = vec![];
+        for (i, &first) in Command::ALL.iter().enumerate() {
+            for &second in &Command::ALL[i + 1..] {
+                if self.get(first) == self.get(second) {
+                    conflicts.push((first, second));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// Whether a key only modifies others, it can't be bound on its own
+pub fn is_modifier(key: VirtualKeyCode) -> bool {
+    matches!(
+        key,
+        VirtualKeyCode::LControl
+            | VirtualKeyCode::RControl
+            | VirtualKeyCode::LShift
+            | VirtualKeyCode::RShift
+            | VirtualKeyCode::LAlt
+            | VirtualKeyCode::RAlt
+            | VirtualKeyCode::LWin
+            | VirtualKeyCode::RWin
+    )
+}
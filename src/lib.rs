@@ -0,0 +1,24 @@
+//! Simulation core of Lifetime: the compute pipeline that simulates and colors the grid, the
+//! rules, pattern files and seeding. Nothing here depends on winit or egui, so it runs headless
+//! (see `bench`) or can be embedded in other apps. The `lifetime` binary adds the windows and UI.
+
+pub mod bench;
+pub mod catalog;
+pub mod device_info;
+pub mod game_compute_pipeline;
+pub mod gradient;
+pub mod growth;
+pub mod history;
+pub mod init_error;
+pub mod pacing;
+pub mod palette_animation;
+pub mod pattern;
+pub mod png_sequence;
+pub mod readback;
+pub mod recording;
+pub mod rule;
+pub mod seeding;
+pub mod self_test;
+pub mod soup;
+pub mod sprite_sheet;
+pub mod stats_log;
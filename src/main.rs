@@ -1,32 +1,53 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    fs,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, Instant},
+};
 
-use egui::Context;
-use egui_winit_vulkano::Gui;
 use lazy_static::lazy_static;
 use vulkano::{
     device::{DeviceExtensions, Features},
     format::Format,
-    image::ImageUsage,
     instance::{InstanceCreateInfo, InstanceExtensions},
-    pipeline::graphics::viewport::Viewport,
 };
 use vulkano_util::{
     context::{VulkanoConfig, VulkanoContext},
-    window::{VulkanoWindows, WindowDescriptor},
+    window::{VulkanoWindows, WindowDescriptor, WindowMode},
 };
 use winit::{
     dpi::LogicalSize,
     event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    window::WindowId,
+};
+
+use lifetime::{
+    bench::{self, GridSuggestion},
+    device_info::DeviceLimits,
+    init_error::InitError,
+    recording,
+    seeding::SeedSettings,
+    self_test,
 };
 
 use crate::{
-    final_render_pass::{FinalRenderPass, ViewportTransform},
-    game_compute_pipeline::GameComputePipeline,
+    cli::{CliArgs, GridSide},
+    settings::{StoredSettings, StoredSuggestion},
+    simulation_window::SimulationWindow,
 };
 
+mod cli;
 mod final_render_pass;
-mod game_compute_pipeline;
+mod input_macro;
+mod keybindings;
+mod notifications;
+mod preset;
+mod session;
+mod settings;
+mod simulation_window;
+mod theme;
+mod tutorial;
 
 lazy_static! {
     static ref INSTANCE_EXTENSIONS: InstanceExtensions = InstanceExtensions {
@@ -51,7 +72,16 @@ const WINDOW_TITLE: &str = "Conway's Game of Life";
 
 const GRID_SIZE: u32 = 2000;
 
+// Frame rate the suggested grid size has to keep up with
+const SUGGESTION_FPS: u32 = 60;
+
+// Minimum swapchain image count of the next window created, 0 leaves the surface's minimum. Set
+// here since the swapchain is configured through a plain function pointer.
+static SWAPCHAIN_IMAGES: AtomicU32 = AtomicU32::new(0);
+
 fn main() {
+    let args = CliArgs::parse();
+
     // Create vulkano context
     let vulkano_context = VulkanoContext::new(VulkanoConfig {
         instance_create_info: InstanceCreateInfo {
@@ -64,170 +94,293 @@ fn main() {
         ..Default::default()
     });
 
-    println!("Using device: {}", vulkano_context.device_name());
+    // On stderr, so it stays out of the benchmark tables and run summaries
+    eprintln!("Using device: {}", vulkano_context.device_name());
+
+    if args.device_info {
+        print!("{}", DeviceLimits::query(&vulkano_context));
+        return;
+    }
 
-    // Create window
+    if args.verify_batching {
+        match bench::verify_batching(&vulkano_context) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => exit_with_error(&err),
+        }
+    }
+
+    if args.verify_shift {
+        match bench::verify_shift(&vulkano_context) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => exit_with_error(&err),
+        }
+    }
+
+    if args.test {
+        match self_test::run(&vulkano_context) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => exit_with_error(&err),
+        }
+    }
+
+    if let Some(path) = &args.stats_out {
+        let seed = SeedSettings {
+            seed: args.seed,
+            ..SeedSettings::default()
+        };
+        let summary = bench::summarize_run(
+            &vulkano_context,
+            [GRID_SIZE, GRID_SIZE],
+            &seed,
+            args.rule,
+            args.generations,
+        )
+        .unwrap_or_else(|err| exit_with_error(&err));
+        let json = serde_json::to_string_pretty(&summary).expect("summary serializes");
+        if let Err(err) = fs::write(path, json) {
+            eprintln!("Failed to write {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+        println!(
+            "{:?} after {} generations, summary written to {}",
+            summary.outcome,
+            summary.generations,
+            path.display()
+        );
+        if !args.bench {
+            return;
+        }
+    }
+
+    if args.bench {
+        let sizes = if args.sweep {
+            &bench::SWEEP_SIZES[..]
+        } else {
+            &[GRID_SIZE][..]
+        };
+        bench::run(&vulkano_context, sizes, args.bench_format);
+        return;
+    }
+
+    // New windows show the same suggestion and start with the same grid
+    let grid_suggestion = match args.grid {
+        GridSide::Fixed(_) => None,
+        GridSide::Default | GridSide::Auto => grid_suggestion(&vulkano_context),
+    };
+    let grid_side = match args.grid {
+        GridSide::Fixed(side) => side,
+        // Even the smallest grid was too slow, it's still the closest to keeping up
+        GridSide::Auto => {
+            grid_suggestion.map_or(bench::SUGGEST_SIZES[0], |suggestion| suggestion.side)
+        }
+        GridSide::Default => GRID_SIZE,
+    };
+
+    // Create window, the count can't be checked against a surface that doesn't exist yet but 2
+    // and 3 are supported nearly everywhere
+    SWAPCHAIN_IMAGES.store(
+        args.swapchain_images.count().unwrap_or(0),
+        Ordering::Relaxed,
+    );
     let mut windows = VulkanoWindows::default();
     let event_loop = EventLoop::new();
-    let main_window_id = windows.create_window(
+    let main_window_id = create_window(
+        &mut windows,
         &event_loop,
         &vulkano_context,
-        &WindowDescriptor {
-            width: WINDOW_SIZE.width,
-            height: WINDOW_SIZE.height,
-            title: WINDOW_TITLE.to_string(),
-            ..WindowDescriptor::default()
-        },
-        |swapchain_create_info| {
-            swapchain_create_info.image_format = Some(SWAPCHAIN_FORMAT);
-        },
-    );
-    let window_renderer = windows.get_primary_renderer_mut().unwrap();
-
-    let mut game_compute_pipeline =
-        GameComputePipeline::new(&vulkano_context, [GRID_SIZE, GRID_SIZE]);
-    let final_render_pass = FinalRenderPass::new(&vulkano_context, SWAPCHAIN_FORMAT);
-
-    // Create gui context
-    let mut gui = Gui::new_with_subpass(
-        window_renderer.surface(),
-        vulkano_context.graphics_queue(),
-        final_render_pass.gui_subpass(),
+        WINDOW_TITLE.to_string(),
+        args.kiosk,
     );
 
-    let mut viewport_transform = ViewportTransform::default();
-
-    let mut counter = 0;
-    let mut fps = 60.0;
-    let mut frame_time = Duration::default();
+    // Every window gets its own simulation, they only share the vulkano context
+    let mut simulations = HashMap::new();
+    let mut main_simulation = SimulationWindow::new(
+        &vulkano_context,
+        windows.get_primary_renderer().unwrap(),
+        SWAPCHAIN_FORMAT,
+        [grid_side, grid_side],
+    )
+    .unwrap_or_else(|err| exit_with_error(&err));
+    main_simulation.set_grid_suggestion(grid_suggestion);
+    main_simulation.seed(&args);
+    simulations.insert(main_window_id, main_simulation);
+    let mut windows_opened = 1;
+
+    let ffmpeg_available = recording::ffmpeg_available();
+    let mut frame_start = Instant::now();
 
     // Run the event loop to keep window open
-    event_loop.run(move |event, _, control_flow| {
-        let window_renderer = windows.get_primary_renderer_mut().unwrap();
-
-        match event {
-            Event::WindowEvent { event, window_id } => {
-                if window_id == main_window_id {
-                    let pass_events_to_app = !gui.update(&event);
-                    if pass_events_to_app {}
-
-                    match event {
-                        WindowEvent::Resized(_) => {
-                            window_renderer.resize();
-                        }
-                        WindowEvent::ScaleFactorChanged { .. } => {
-                            window_renderer.resize();
-                        }
-                        WindowEvent::CloseRequested => {
-                            *control_flow = ControlFlow::Exit;
-                        }
-                        _ => (),
+    event_loop.run(move |event, event_loop, control_flow| match event {
+        Event::WindowEvent { event, window_id } => {
+            let new_window_requested = match (
+                windows.get_renderer_mut(window_id),
+                simulations.get_mut(&window_id),
+            ) {
+                (Some(renderer), Some(simulation)) => {
+                    simulation.handle_event(renderer, &event);
+                    // Opens another window with its own simulation, for comparing rules or seeds
+                    // side by side
+                    let requested = simulation.take_new_window_request();
+                    if requested {
+                        SWAPCHAIN_IMAGES
+                            .store(simulation.swapchain_image_count(), Ordering::Relaxed);
+                    }
+                    requested
+                }
+                _ => return,
+            };
+            if new_window_requested {
+                windows_opened += 1;
+                let window_id = create_window(
+                    &mut windows,
+                    event_loop,
+                    &vulkano_context,
+                    format!("{} ({})", WINDOW_TITLE, windows_opened),
+                    false,
+                );
+                match SimulationWindow::new(
+                    &vulkano_context,
+                    windows.get_renderer(window_id).unwrap(),
+                    SWAPCHAIN_FORMAT,
+                    [grid_side, grid_side],
+                ) {
+                    Ok(mut simulation) => {
+                        simulation.set_grid_suggestion(grid_suggestion);
+                        simulations.insert(window_id, simulation);
+                    }
+                    // The other windows keep running
+                    Err(err) => {
+                        eprintln!("Failed to open a new window: {}", err);
+                        windows.remove_renderer(window_id);
                     }
                 }
             }
-            Event::RedrawRequested(window_id) => {
-                if window_id == main_window_id {
-                    let timer = Instant::now();
-
-                    // Create immediate ui
-                    let context = gui.context();
-                    gui.immediate_ui(|_| {
-                        egui::SidePanel::left("left_panel")
-                            .min_width(300.0)
-                            .show(&context, |ui| {
-                                ui.vertical_centered(|ui| {
-                                    ui.heading("Settings");
-                                });
-                                ui.separator();
-                                ui.horizontal(|ui| {
-                                    ui.label("Offset X:");
-                                    ui.add(egui::Slider::new(
-                                        &mut viewport_transform.offset[0],
-                                        -10.0..=10.0,
-                                    ));
-                                });
-
-                                ui.horizontal(|ui| {
-                                    ui.label("Offset Y:");
-                                    ui.add(egui::Slider::new(
-                                        &mut viewport_transform.offset[1],
-                                        -10.0..=10.0,
-                                    ));
-                                });
-
-                                ui.horizontal(|ui| {
-                                    ui.label("Scale:");
-                                    ui.add(egui::Slider::new(
-                                        &mut viewport_transform.scale,
-                                        0.1..=50.0,
-                                    ));
-                                });
-                                if ui.button("Reset").clicked() {
-                                    game_compute_pipeline = GameComputePipeline::new(&vulkano_context, [GRID_SIZE, GRID_SIZE]);
-                                }
-                                ui.separator();
-                                ui.label(format!("FPS: {}", f32::floor(fps)));
-
-                                counter += 1;
-                                if counter == 10 {
-                                    counter = 0;
-                                    fps = 1000.0 / frame_time.as_millis() as f32;
-                                }
-                            });
-                    });
-
-                    // Calculate viwport so as not to render behind egui components
-                    let viewport_bounds = calculate_viewport(
-                        &context,
-                        window_renderer.window().scale_factor() as f32,
-                    );
-
-                    // Update image aspect ratio
-                    viewport_transform.aspect_ratio =
-                        viewport_bounds.dimensions[0] / viewport_bounds.dimensions[1];
-
-                    let before_pipeline_future = window_renderer
-                        .acquire()
-                        .expect("failed to acquire window renderer future");
-
-                    // Render viewport
-                    let after_compute_future = game_compute_pipeline.compute(
-                        before_pipeline_future,
-                        [1.0, 0.0, 0.0, 1.0],
-                        [0.0, 0.0, 0.0, 1.0],
-                    );
-
-                    // Render gui
-                    let after_gui_future = final_render_pass.render(
-                        after_compute_future,
-                        window_renderer.swapchain_image_view(),
-                        &mut gui,
-                        game_compute_pipeline.view(),
-                        viewport_bounds,
-                        viewport_transform,
-                    );
-
-                    // Present to surface
-                    window_renderer.present(after_gui_future, true);
-
-                    frame_time = Instant::now().duration_since(timer);
+
+            match event {
+                WindowEvent::CloseRequested if window_id == main_window_id => {
+                    for simulation in simulations.values_mut() {
+                        simulation.close();
+                    }
+                    // Everything is dropped once the loop exits
+                    wait_for_gpu(&vulkano_context);
+                    *control_flow = ControlFlow::Exit;
                 }
+                WindowEvent::CloseRequested => {
+                    if let Some(mut simulation) = simulations.remove(&window_id) {
+                        simulation.close();
+                        wait_for_gpu(&vulkano_context);
+                    }
+                    windows.remove_renderer(window_id);
+                }
+                _ => (),
             }
-            Event::MainEventsCleared => {
-                window_renderer.surface().window().request_redraw();
+        }
+        Event::RedrawRequested(window_id) => {
+            if let (Some(renderer), Some(simulation)) = (
+                windows.get_renderer_mut(window_id),
+                simulations.get_mut(&window_id),
+            ) {
+                simulation.redraw(&vulkano_context, renderer, ffmpeg_available);
             }
-            _ => (),
         }
+        Event::MainEventsCleared => {
+            for (_, renderer) in windows.iter() {
+                renderer.window().request_redraw();
+            }
+        }
+        Event::RedrawEventsCleared => {
+            // Sleep off the rest of the frame interval when the frame rate is capped (the main
+            // window's setting applies to all windows since they're drawn in the same loop)
+            let max_fps = simulations[&main_window_id].settings().max_fps;
+            if max_fps > 0 {
+                let interval = Duration::from_secs_f64(1.0 / max_fps as f64);
+                if let Some(remaining) = interval.checked_sub(frame_start.elapsed()) {
+                    std::thread::sleep(remaining);
+                }
+            }
+            frame_start = Instant::now();
+        }
+        _ => (),
     });
 }
 
-fn calculate_viewport(context: &Context, scale_factor: f32) -> Viewport {
-    let origin = context.available_rect().left_top();
-    let dimensions = context.available_rect().right_bottom() - origin;
+/// Print why the GPU resources couldn't be created and exit, rather than panicking
+/// The grid suggestion kept in the settings file for this device, measured with
+/// `bench::suggest_grid` and kept there if there's none yet
+fn grid_suggestion(vulkano_context: &VulkanoContext) -> Option<GridSuggestion> {
+    let mut stored = StoredSettings::load();
+    let device = vulkano_context.device_name();
+    match &stored.grid_suggestion {
+        Some(cached) if cached.device == device && cached.target_fps == SUGGESTION_FPS => {
+            return cached.suggestion;
+        }
+        _ => (),
+    }
 
-    Viewport {
-        origin: [origin.x * scale_factor, origin.y * scale_factor],
-        dimensions: [dimensions.x * scale_factor, dimensions.y * scale_factor],
-        depth_range: 0.0..1.0,
+    let suggestion = match bench::suggest_grid(vulkano_context, SUGGESTION_FPS) {
+        Ok(suggestion) => suggestion,
+        // Not kept, so the next start measures again
+        Err(err) => {
+            eprintln!("Failed to measure a grid size to suggest: {}", err);
+            return None;
+        }
+    };
+    stored.grid_suggestion = Some(StoredSuggestion {
+        device: device.to_string(),
+        target_fps: SUGGESTION_FPS,
+        suggestion,
+    });
+    if let Err(err) = stored.save() {
+        eprintln!("Failed to save the suggested grid size: {}", err);
     }
+    suggestion
+}
+
+fn exit_with_error(err: &InitError) -> ! {
+    eprintln!("Failed to start: {}", err);
+    std::process::exit(1);
+}
+
+/// Block until the GPU is done with every submitted frame, so nothing still in use gets dropped
+fn wait_for_gpu(vulkano_context: &VulkanoContext) {
+    // Safe since all submissions happen on this thread, so none can happen while waiting
+    if let Err(err) = unsafe { vulkano_context.device().wait() } {
+        eprintln!("Failed to wait for the GPU to finish: {}", err);
+    }
+}
+
+fn create_window(
+    windows: &mut VulkanoWindows,
+    event_loop: &EventLoopWindowTarget<()>,
+    vulkano_context: &VulkanoContext,
+    title: String,
+    // Borderless fullscreen without a cursor
+    kiosk: bool,
+) -> WindowId {
+    windows.create_window(
+        event_loop,
+        vulkano_context,
+        &WindowDescriptor {
+            width: WINDOW_SIZE.width,
+            height: WINDOW_SIZE.height,
+            title,
+            mode: if kiosk {
+                WindowMode::BorderlessFullscreen
+            } else {
+                WindowMode::Windowed
+            },
+            cursor_visible: !kiosk,
+            ..WindowDescriptor::default()
+        },
+        |swapchain_create_info| {
+            swapchain_create_info.image_format = Some(SWAPCHAIN_FORMAT);
+            // Starts out at the surface's minimum, never go below it
+            swapchain_create_info.min_image_count = swapchain_create_info
+                .min_image_count
+                .max(SWAPCHAIN_IMAGES.load(Ordering::Relaxed));
+        },
+    )
 }
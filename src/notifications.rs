@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+use egui::{Color32, Context};
+
+// How long a notification stays up, errors stay longer so there's time to read them
+const INFO_DURATION: Duration = Duration::from_secs(3);
+const ERROR_DURATION: Duration = Duration::from_secs(8);
+
+// Older notifications are dropped past this many
+const MAX_NOTIFICATIONS: usize = 5;
+
+// Notifications fade out over this long before they're dismissed
+const FADE_DURATION: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn color(&self) -> Color32 {
+        match self {
+            Level::Info => Color32::LIGHT_GRAY,
+            Level::Warning => Color32::YELLOW,
+            Level::Error => Color32::RED,
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        match self {
+            Level::Info => INFO_DURATION,
+            Level::Warning | Level::Error => ERROR_DURATION,
+        }
+    }
+}
+
+struct Notification {
+    message: String,
+    level: Level,
+    shown: Instant,
+}
+
+/// Short lived messages stacked in the bottom right corner of the window, the newest at the
+/// bottom. Each dismisses itself after a while depending on its level.
+#[derive(Default)]
+pub struct Notifications {
+    queue: Vec<Notification>,
+}
+
+impl Notifications {
+    pub fn notify(&mut self, message: impl Into<String>, level: Level) {
+        self.queue.push(Notification {
+            message: message.into(),
+            level,
+            shown: Instant::now(),
+        });
+        if self.queue.len() > MAX_NOTIFICATIONS {
+            self.queue.remove(0);
+        }
+    }
+
+    /// Drop the expired notifications and draw the rest
+    pub fn show(&mut self, context: &Context) {
+        self.queue
+            .retain(|notification| notification.shown.elapsed() < notification.level.duration());
+        if self.queue.is_empty() {
+            return;
+        }
+
+        egui::Area::new("notifications")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-16.0, -16.0])
+            .show(context, |ui| {
+                for notification in &self.queue {
+                    let remaining = notification
+                        .level
+                        .duration()
+                        .saturating_sub(notification.shown.elapsed());
+                    let opacity = (remaining.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0);
+                    let color = notification.level.color().linear_multiply(opacity);
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(color, &notification.message);
+                    });
+                }
+            });
+    }
+}
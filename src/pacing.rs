@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+/// Longest frame counted in full. A stall (dragging the window, a blocking dialog, the OS
+/// suspending the app) would otherwise be paid back all at once, and if that catch-up frame is
+/// slow in turn the debt only grows, the "spiral of death".
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Shortest frame counted. A frame measured as zero long (a coarse clock) or negative (a clock
+/// that went backwards) still moves the simulation on a little rather than not at all.
+pub const MIN_FRAME_TIME: Duration = Duration::from_micros(1);
+
+/// Measures the time between frames
+#[derive(Default)]
+pub struct FrameClock {
+    last: Option<Instant>,
+}
+
+impl FrameClock {
+    /// Time since the last tick, at least `MIN_FRAME_TIME`. `None` on the first tick, there's
+    /// nothing to measure from yet.
+    pub fn tick(&mut self, now: Instant) -> Option<Duration> {
+        let last = self.last.replace(now)?;
+        let elapsed = now.checked_duration_since(last).unwrap_or_default();
+        Some(elapsed.max(MIN_FRAME_TIME))
+    }
+}
+
+/// Turns elapsed time into a whole number of generations at a fixed rate, so the simulation
+/// speed doesn't depend on how fast frames are rendered.
+///
+/// Invariants:
+/// - after `advance` the carried remainder is always less than one generation's worth of time,
+///   it's only ever spent by later calls, never dropped
+/// - at a constant rate the generations returned add up to `floor(total elapsed time * rate)`,
+///   however the time was split between calls, as long as neither cap was hit
+/// - time past the caps (`MAX_FRAME_TIME` and the generation limit) is dropped, the simulation
+///   falls behind the clock instead of freezing the app to catch up
+/// - changing the rate keeps the remainder, the next generation just comes sooner or later
+pub struct StepAccumulator {
+    interval: Duration,
+    remainder: Duration,
+}
+
+impl StepAccumulator {
+    pub fn new(generations_per_second: f32) -> Self {
+        Self {
+            interval: interval(generations_per_second),
+            remainder: Duration::ZERO,
+        }
+    }
+
+    pub fn set_rate(&mut self, generations_per_second: f32) {
+        self.interval = interval(generations_per_second);
+    }
+
+    /// Add `elapsed` time (clamped to `MIN_FRAME_TIME..=MAX_FRAME_TIME`) and return how many
+    /// whole generations it completes, at most `max_generations`
+    pub fn advance(&mut self, elapsed: Duration, max_generations: u32) -> u32 {
+        self.remainder += elapsed.clamp(MIN_FRAME_TIME, MAX_FRAME_TIME);
+        let generations = self.remainder.as_nanos() / self.interval.as_nanos();
+        if generations > max_generations as u128 {
+            self.remainder = Duration::ZERO;
+            return max_generations;
+        }
+
+        self.remainder -= self.interval * generations as u32;
+        generations as u32
+    }
+}
+
+fn interval(generations_per_second: f32) -> Duration {
+    // Clamped so the interval is neither infinite nor zero
+    Duration::from_secs_f64(1.0 / generations_per_second.max(1e-3) as f64)
+        .max(Duration::from_nanos(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_tick_has_no_frame_time() {
+        let mut clock = FrameClock::default();
+        let start = Instant::now();
+        assert_eq!(clock.tick(start), None);
+        assert_eq!(
+            clock.tick(start + Duration::from_millis(16)),
+            Some(Duration::from_millis(16))
+        );
+    }
+
+    #[test]
+    fn zero_and_backwards_frames_count_as_min_frame_time() {
+        let mut clock = FrameClock::default();
+        let start = Instant::now() + Duration::from_secs(1);
+        clock.tick(start);
+        assert_eq!(clock.tick(start), Some(MIN_FRAME_TIME));
+        let earlier = start - Duration::from_millis(500);
+        assert_eq!(clock.tick(earlier), Some(MIN_FRAME_TIME));
+    }
+
+    #[test]
+    fn zero_elapsed_eventually_steps() {
+        // One generation every 10 microseconds, each zero length frame adds MIN_FRAME_TIME
+        let mut accumulator = StepAccumulator::new(100_000.0);
+        let total: u32 = (0..20)
+            .map(|_| accumulator.advance(Duration::ZERO, 100))
+            .sum();
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn huge_elapsed_is_capped() {
+        let mut accumulator = StepAccumulator::new(10.0);
+        // MAX_FRAME_TIME is 2.5 generations at 10 per second
+        assert_eq!(accumulator.advance(Duration::MAX, 100), 2);
+        // The generation limit drops the rest instead of carrying it
+        let mut accumulator = StepAccumulator::new(1_000.0);
+        assert_eq!(accumulator.advance(Duration::from_secs(3600), 10), 10);
+        assert_eq!(accumulator.advance(Duration::ZERO, 10), 0);
+    }
+
+    #[test]
+    fn pathological_rates_stay_finite() {
+        for rate in [0.0, -5.0, f32::NAN, f32::INFINITY, f32::MIN_POSITIVE] {
+            let mut accumulator = StepAccumulator::new(rate);
+            for elapsed in [Duration::ZERO, Duration::from_millis(16), Duration::MAX] {
+                assert!(accumulator.advance(elapsed, 1_000) <= 1_000);
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_compute_pipeline::RenderSettings;
+
+/// How the live and dead colors change over time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteAnimation {
+    Static,
+    /// Rotate the hue all the way around once per period
+    HueCycle,
+    /// Dim and brighten again once per period
+    Pulse,
+}
+
+impl PaletteAnimation {
+    pub const ALL: [PaletteAnimation; 3] = [
+        PaletteAnimation::Static,
+        PaletteAnimation::HueCycle,
+        PaletteAnimation::Pulse,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaletteAnimation::Static => "Static",
+            PaletteAnimation::HueCycle => "Hue cycle",
+            PaletteAnimation::Pulse => "Pulse",
+        }
+    }
+}
+
+/// What the animation period is counted in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationClock {
+    /// Seconds, keeps going while the simulation is paused
+    Time,
+    /// Generations, the colors follow the simulation speed
+    Generation,
+}
+
+impl AnimationClock {
+    pub const ALL: [AnimationClock; 2] = [AnimationClock::Time, AnimationClock::Generation];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimationClock::Time => "Seconds",
+            AnimationClock::Generation => "Generations",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaletteAnimationSettings {
+    pub mode: PaletteAnimation,
+    pub clock: AnimationClock,
+    /// Length of one cycle, in seconds or generations
+    pub period: f32,
+    /// Fraction of the brightness lost at the bottom of a pulse
+    pub pulse_depth: f32,
+    pub animate_life: bool,
+    pub animate_dead: bool,
+}
+
+impl Default for PaletteAnimationSettings {
+    fn default() -> Self {
+        Self {
+            mode: PaletteAnimation::Static,
+            clock: AnimationClock::Time,
+            period: 10.0,
+            pulse_depth: 0.6,
+            animate_life: true,
+            animate_dead: false,
+        }
+    }
+}
+
+impl PaletteAnimationSettings {
+    /// Position in the current cycle, `0.0..1.0`
+    pub fn phase(&self, seconds: f64, generation: u64) -> f32 {
+        let elapsed = match self.clock {
+            AnimationClock::Time => seconds,
+            AnimationClock::Generation => generation as f64,
+        };
+        (elapsed / self.period.max(1e-3) as f64).fract() as f32
+    }
+
+    /// `color` at `phase` through the cycle, alpha is left as is
+    pub fn animate(&self, color: [f32; 4], phase: f32) -> [f32; 4] {
+        let [r, g, b, a] = color;
+        let [r, g, b] = match self.mode {
+            PaletteAnimation::Static => [r, g, b],
+            PaletteAnimation::HueCycle => rotate_hue([r, g, b], phase),
+            PaletteAnimation::Pulse => {
+                let dip = 0.5 - 0.5 * (phase * std::f32::consts::TAU).cos();
+                [r, g, b].map(|c| c * (1.0 - self.pulse_depth.clamp(0.0, 1.0) * dip))
+            }
+        };
+        [r, g, b, a]
+    }
+
+    /// `settings` with the colors set to animate at `phase`, borrowed as is while static
+    pub fn apply<'a>(&self, settings: &'a RenderSettings, phase: f32) -> Cow<'a, RenderSettings> {
+        if self.mode == PaletteAnimation::Static || !(self.animate_life || self.animate_dead) {
+            return Cow::Borrowed(settings);
+        }
+
+        let mut animated = settings.clone();
+        if self.animate_life {
+            animated.life_color = self.animate(settings.life_color, phase);
+        }
+        if self.animate_dead {
+            animated.dead_color = self.animate(settings.dead_color, phase);
+        }
+        Cow::Owned(animated)
+    }
+}
+
+/// Shift the hue by `turns` of the color wheel keeping saturation and value, grays don't change
+fn rotate_hue(rgb: [f32; 3], turns: f32) -> [f32; 3] {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let min = rgb[0].min(rgb[1]).min(rgb[2]);
+    let chroma = max - min;
+    if chroma <= 0.0 {
+        return rgb;
+    }
+
+    // Hue in sixths of the wheel
+    let [r, g, b] = rgb;
+    let hue = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let hue = (hue + turns * 6.0).rem_euclid(6.0);
+
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let [r, g, b] = match hue as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    [r + min, g + min, b + min]
+}
@@ -0,0 +1,485 @@
+use std::{fmt, fs, io, path::Path};
+
+use image::{imageops::FilterType, GenericImageView, ImageError};
+use serde::{Deserialize, Serialize};
+
+use crate::{init_error::InitError, rule::Rule};
+
+// Images larger than this on either side are scaled down before importing
+const MAX_IMAGE_SIDE: u32 = 4096;
+
+/// A rectangular block of cells, stored row by row with 1 for alive and 0 for dead
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pattern {
+    size: [u32; 2],
+    cells: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    Parse(String),
+    Image(ImageError),
+    /// The grid couldn't be resized to fit
+    Grid(InitError),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(err) => write!(f, "failed to read pattern: {}", err),
+            PatternError::Parse(msg) => write!(f, "invalid pattern: {}", msg),
+            PatternError::Image(err) => write!(f, "failed to import image: {}", err),
+            PatternError::Grid(err) => write!(f, "failed to resize the grid: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<io::Error> for PatternError {
+    fn from(err: io::Error) -> Self {
+        PatternError::Io(err)
+    }
+}
+
+impl From<InitError> for PatternError {
+    fn from(err: InitError) -> Self {
+        PatternError::Grid(err)
+    }
+}
+
+impl From<ImageError> for PatternError {
+    fn from(err: ImageError) -> Self {
+        PatternError::Image(err)
+    }
+}
+
+impl Pattern {
+    pub fn new(size: [u32; 2], cells: Vec<u8>) -> Self {
+        assert_eq!(
+            cells.len(),
+            size[0] as usize * size[1] as usize,
+            "pattern cell count doesn't match its size"
+        );
+        Self { size, cells }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.cells[(y * self.size[0] + x) as usize] != 0
+    }
+
+    /// Load a pattern file, picking the format from the extension (falls back to sniffing the contents).
+    /// Patterns wider or taller than `max_side` are rejected, see `parse`.
+    pub fn from_file(path: &Path, max_side: u32) -> Result<Self, PatternError> {
+        let text = fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("rle") => parse_rle(&text, max_side),
+            Some("cells") => parse_plaintext(&text, max_side),
+            _ => parse(&text, max_side),
+        }
+    }
+
+    /// Load an image with one cell per pixel, alive where the brightness (`0.0..=1.0`) is over
+    /// `threshold`. Transparent pixels count as dark and large images are scaled down.
+    pub fn from_image(path: &Path, threshold: f32) -> Result<Self, PatternError> {
+        let mut image = image::open(path)?;
+        if image.width() > MAX_IMAGE_SIDE || image.height() > MAX_IMAGE_SIDE {
+            image = image.resize(MAX_IMAGE_SIDE, MAX_IMAGE_SIDE, FilterType::Triangle);
+        }
+
+        let image = image.to_luma_alpha8();
+        let cells = image
+            .pixels()
+            .map(|pixel| {
+                let [luma, alpha] = pixel.0;
+                let brightness = (luma as f32 / 255.0) * (alpha as f32 / 255.0);
+                (brightness > threshold) as u8
+            })
+            .collect();
+        Ok(Pattern::new([image.width(), image.height()], cells))
+    }
+
+    /// Minimum and maximum (inclusive) coordinates of the live cells, `None` if there are none
+    pub fn bounding_box(&self) -> Option<([u32; 2], [u32; 2])> {
+        let mut bounds: Option<([u32; 2], [u32; 2])> = None;
+        for y in 0..self.size[1] {
+            for x in 0..self.size[0] {
+                if self.get(x, y) {
+                    let (min, max) = bounds.get_or_insert(([x, y], [x, y]));
+                    *min = [min[0].min(x), min[1].min(y)];
+                    *max = [max[0].max(x), max[1].max(y)];
+                }
+            }
+        }
+        bounds
+    }
+
+    /// The `size` cells starting at `min`, clipped to the pattern
+    pub fn cropped(&self, min: [u32; 2], size: [u32; 2]) -> Pattern {
+        let max = [0, 1].map(|i| (min[i] + size[i]).min(self.size[i]));
+        let size = [0, 1].map(|i| max[i].saturating_sub(min[i]));
+        let cells = (min[1]..max[1])
+            .flat_map(|y| (min[0]..max[0]).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y) as u8)
+            .collect();
+        Pattern::new(size, cells)
+    }
+
+    /// Turned a quarter turn clockwise
+    pub fn rotated(&self) -> Pattern {
+        let [width, height] = self.size;
+        let cells = (0..width)
+            .flat_map(|y| (0..height).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(y, height - 1 - x) as u8)
+            .collect();
+        Pattern::new([height, width], cells)
+    }
+
+    /// Mirrored left to right
+    pub fn flipped(&self) -> Pattern {
+        let [width, height] = self.size;
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(width - 1 - x, y) as u8)
+            .collect();
+        Pattern::new(self.size, cells)
+    }
+
+    /// Crop away the dead border around the live cells
+    pub fn trimmed(&self) -> Pattern {
+        match self.bounding_box() {
+            Some((min, max)) => {
+                let size = [max[0] - min[0] + 1, max[1] - min[1] + 1];
+                let cells = (min[1]..=max[1])
+                    .flat_map(|y| (min[0]..=max[0]).map(move |x| (x, y)))
+                    .map(|(x, y)| self.get(x, y) as u8)
+                    .collect();
+                Pattern::new(size, cells)
+            }
+            None => Pattern::new([0, 0], vec![]),
+        }
+    }
+}
+
+/// Parse either RLE or plaintext, guessing the format from the contents
+pub fn parse(text: &str, max_side: u32) -> Result<Pattern, PatternError> {
+    let is_rle = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_some_and(|line| line.starts_with('x'));
+
+    if is_rle {
+        parse_rle(text, max_side)
+    } else {
+        parse_plaintext(text, max_side)
+    }
+}
+
+/// Parse the run length encoded format used by most pattern collections
+/// (https://conwaylife.com/wiki/Run_Length_Encoded). Patterns (or headers) wider or taller than
+/// `max_side` are rejected before anything that large is allocated.
+pub fn parse_rle(text: &str, max_side: u32) -> Result<Pattern, PatternError> {
+    let mut header_size = None;
+    let mut rows: Vec<Vec<u8>> = vec![vec![]];
+    let mut run: Option<u32> = None;
+
+    'lines: for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if header_size.is_none() && line.starts_with('x') {
+            let size = parse_rle_header(line)?;
+            check_size(size, max_side)?;
+            header_size = Some(size);
+            continue;
+        }
+
+        for c in line.chars() {
+            match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap();
+                    run = Some(
+                        run.unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|run| run.checked_add(digit))
+                            .ok_or_else(|| {
+                                PatternError::Parse(
+                                    "run count in RLE data is too large".to_string(),
+                                )
+                            })?,
+                    );
+                }
+                '$' => {
+                    let count = run.take().unwrap_or(1);
+                    check_size([0, (rows.len() as u32).saturating_add(count)], max_side)?;
+                    rows.resize(rows.len() + count as usize, vec![]);
+                }
+                '!' => break 'lines,
+                c if c.is_whitespace() => {}
+                c if c.is_ascii_alphabetic() => {
+                    // Any state other than `b` is treated as alive
+                    let state = (c != 'b') as u8;
+                    let row = rows.last_mut().unwrap();
+                    let count = run.take().unwrap_or(1);
+                    check_size([(row.len() as u32).saturating_add(count), 0], max_side)?;
+                    row.resize(row.len() + count as usize, state);
+                }
+                c => {
+                    return Err(PatternError::Parse(format!(
+                        "unexpected character '{}' in RLE data",
+                        c
+                    )))
+                }
+            }
+        }
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let height = rows.len() as u32;
+    let size = match header_size {
+        Some([x, y]) => [x.max(width), y.max(height)],
+        None => [width, height],
+    };
+
+    Ok(from_rows(size, &rows))
+}
+
+/// Encode a pattern as RLE, the inverse of `parse_rle`. Runs of dead cells at the end of a row
+/// and of empty rows at the end are left out.
+pub fn encode_rle(pattern: &Pattern, rule: &Rule) -> String {
+    // Keeps lines under the 70 characters the format asks for
+    const MAX_LINE: usize = 70;
+
+    let [width, height] = pattern.size();
+    let mut items = vec![];
+    let mut pending_rows = 0;
+    for y in 0..height {
+        let mut runs: Vec<(u32, char)> = vec![];
+        for x in 0..width {
+            let tag = if pattern.get(x, y) { 'o' } else { 'b' };
+            match runs.last_mut() {
+                Some((count, last)) if *last == tag => *count += 1,
+                _ => runs.push((1, tag)),
+            }
+        }
+        if runs.last().is_some_and(|&(_, tag)| tag == 'b') {
+            runs.pop();
+        }
+        if runs.is_empty() {
+            pending_rows += 1;
+            continue;
+        }
+
+        // Row ends are only written once something follows them
+        if !items.is_empty() || pending_rows > 0 {
+            items.push(run_item(pending_rows + !items.is_empty() as u32, '$'));
+        }
+        pending_rows = 0;
+        items.extend(runs.into_iter().map(|(count, tag)| run_item(count, tag)));
+    }
+    items.push("!".to_string());
+
+    let mut text = format!("x = {}, y = {}, rule = {}\n", width, height, rule);
+    let mut line_length = 0;
+    for item in items {
+        if line_length + item.len() > MAX_LINE {
+            text.push('\n');
+            line_length = 0;
+        }
+        line_length += item.len();
+        text.push_str(&item);
+    }
+    text.push('\n');
+    text
+}
+
+fn run_item(count: u32, tag: char) -> String {
+    if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", count, tag)
+    }
+}
+
+fn parse_rle_header(line: &str) -> Result<[u32; 2], PatternError> {
+    let mut size = [None, None];
+    for field in line.split(',') {
+        let (key, value) = match field.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        let index = match key {
+            "x" => 0,
+            "y" => 1,
+            _ => continue,
+        };
+        size[index] = Some(value.parse::<u32>().map_err(|_| {
+            PatternError::Parse(format!(
+                "invalid {} dimension '{}' in RLE header",
+                key, value
+            ))
+        })?);
+    }
+
+    match size {
+        [Some(x), Some(y)] => Ok([x, y]),
+        _ => Err(PatternError::Parse(format!(
+            "RLE header '{}' is missing dimensions",
+            line
+        ))),
+    }
+}
+
+/// Parse the plaintext format, `.` for dead and `O` for alive cells with `!` comments
+/// (https://conwaylife.com/wiki/Plaintext). Patterns wider or taller than `max_side` are rejected.
+pub fn parse_plaintext(text: &str, max_side: u32) -> Result<Pattern, PatternError> {
+    let mut rows = vec![];
+    for line in text.lines() {
+        if line.starts_with('!') {
+            continue;
+        }
+
+        let row = line
+            .trim_end()
+            .chars()
+            .map(|c| match c {
+                '.' => Ok(0),
+                'O' | 'o' | '*' => Ok(1),
+                c => Err(PatternError::Parse(format!(
+                    "unexpected character '{}' in plaintext pattern",
+                    c
+                ))),
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        rows.push(row);
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let size = [width, rows.len()].map(|side| side.min(u32::MAX as usize) as u32);
+    check_size(size, max_side)?;
+    Ok(from_rows(size, &rows))
+}
+
+/// `PatternError::Parse` if either side of a pattern is over `max_side`
+fn check_size(size: [u32; 2], max_side: u32) -> Result<(), PatternError> {
+    if size[0] > max_side || size[1] > max_side {
+        return Err(PatternError::Parse(format!(
+            "pattern is larger than the largest grid the device supports ({}x{})",
+            max_side, max_side
+        )));
+    }
+    Ok(())
+}
+
+/// Build a pattern from ragged rows, padding them with dead cells
+fn from_rows(size: [u32; 2], rows: &[Vec<u8>]) -> Pattern {
+    let mut cells = vec![0; size[0] as usize * size[1] as usize];
+    for (y, row) in rows.iter().enumerate().take(size[1] as usize) {
+        let start = y * size[0] as usize;
+        let len = row.len().min(size[0] as usize);
+        cells[start..start + len].copy_from_slice(&row[..len]);
+    }
+    Pattern::new(size, cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIDE: u32 = 1024;
+
+    fn is_parse_error(result: Result<Pattern, PatternError>) -> bool {
+        matches!(result, Err(PatternError::Parse(_)))
+    }
+
+    #[test]
+    fn long_run_counts_are_rejected() {
+        assert!(is_parse_error(parse_rle(
+            "99999999999999999999o!",
+            MAX_SIDE
+        )));
+        assert!(is_parse_error(parse_rle("4294967295o!", MAX_SIDE)));
+        assert!(is_parse_error(parse_rle("4294967295$o!", MAX_SIDE)));
+    }
+
+    #[test]
+    fn oversized_headers_are_rejected() {
+        assert!(is_parse_error(parse_rle(
+            "x = 100000, y = 100000\no!",
+            MAX_SIDE
+        )));
+        assert!(is_parse_error(parse_rle(
+            "x = 4294967295, y = 4294967295\no!",
+            MAX_SIDE
+        )));
+    }
+
+    #[test]
+    fn patterns_up_to_the_max_side_parse() {
+        let pattern = parse_rle(&format!("{}o${}$o!", MAX_SIDE, MAX_SIDE - 2), MAX_SIDE).unwrap();
+        assert_eq!(pattern.size(), [MAX_SIDE, MAX_SIDE]);
+        assert!(is_parse_error(parse_plaintext(
+            &"O".repeat(MAX_SIDE as usize + 1),
+            MAX_SIDE
+        )));
+    }
+    fn assert_round_trips(pattern: Pattern) {
+        let text = encode_rle(&pattern, &Rule::CONWAY);
+        assert_eq!(parse_rle(&text, MAX_SIDE).unwrap(), pattern, "{}", text);
+    }
+
+    #[test]
+    fn trailing_dead_runs_round_trip() {
+        // The live cells end before the right and bottom edges
+        #[rustfmt::skip]
+        let cells = vec![
+            1, 1, 0, 0, 0,
+            0, 1, 0, 0, 0,
+            0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0,
+        ];
+        assert_round_trips(Pattern::new([5, 4], cells));
+    }
+
+    #[test]
+    fn blank_rows_round_trip() {
+        #[rustfmt::skip]
+        let cells = vec![
+            0, 1, 1,
+            0, 0, 0,
+            0, 0, 0,
+            1, 0, 1,
+            0, 0, 0,
+            0, 1, 0,
+        ];
+        assert_round_trips(Pattern::new([3, 6], cells));
+    }
+
+    #[test]
+    fn wrapped_lines_round_trip() {
+        // Alternating cells take a run item each, far more than fit on one line
+        let size = [200, 3];
+        let cells = (0..size[0] * size[1]).map(|i| (i % 2) as u8).collect();
+        let pattern = Pattern::new(size, cells);
+        let text = encode_rle(&pattern, &Rule::CONWAY);
+        assert!(text.lines().count() > 2);
+        assert!(text.lines().all(|line| line.len() <= 70));
+        assert_round_trips(pattern);
+    }
+}
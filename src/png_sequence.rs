@@ -0,0 +1,147 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, SyncSender},
+    thread::{self, JoinHandle},
+};
+
+use image::{
+    imageops::{self, FilterType},
+    RgbaImage,
+};
+use serde::{Deserialize, Serialize};
+
+// Frames waiting for the worker at most, queueing more waits for it to catch up
+const MAX_QUEUED_FRAMES: usize = 4;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PngSequenceSettings {
+    /// Directory the frames are written to, created if missing
+    pub directory: String,
+    /// Generations run, the first frame is the generation the export starts from
+    pub generations: u32,
+    /// Generations between frames
+    pub stride: u32,
+    /// Write the image at its own size, one pixel per cell, instead of `resolution`
+    pub native_resolution: bool,
+    pub resolution: [u32; 2],
+}
+
+impl Default for PngSequenceSettings {
+    fn default() -> Self {
+        Self {
+            directory: "frames".to_string(),
+            generations: 300,
+            stride: 1,
+            native_resolution: false,
+            resolution: [1080, 1080],
+        }
+    }
+}
+
+impl PngSequenceSettings {
+    /// Frames written, the starting generation and every `stride`th one after it
+    pub fn frame_count(&self) -> u32 {
+        self.generations / self.stride.max(1) + 1
+    }
+}
+
+/// Writes frames to `frame_00001.png`, `frame_00002.png` and so on. Frames are scaled and
+/// encoded by a worker thread so the simulation can run ahead while they're written.
+pub struct PngSequence {
+    sender: SyncSender<Vec<u8>>,
+    worker: JoinHandle<io::Result<()>>,
+    stride: u32,
+    frames: u32,
+    queued: u32,
+}
+
+impl PngSequence {
+    /// Start writing frames of `image_size` RGBA pixels to the settings' directory
+    pub fn start(settings: &PngSequenceSettings, image_size: [u32; 2]) -> io::Result<Self> {
+        let directory = PathBuf::from(&settings.directory);
+        fs::create_dir_all(&directory)?;
+        let size = if settings.native_resolution {
+            image_size
+        } else {
+            settings.resolution.map(|side| side.max(1))
+        };
+        // Cells stay sharp when scaled up, scaling down averages them
+        let filter = if size[0] >= image_size[0] && size[1] >= image_size[1] {
+            FilterType::Nearest
+        } else {
+            FilterType::Triangle
+        };
+
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(MAX_QUEUED_FRAMES);
+        let worker = thread::spawn(move || {
+            for (index, frame) in receiver.into_iter().enumerate() {
+                let image = RgbaImage::from_raw(image_size[0], image_size[1], frame)
+                    .ok_or_else(|| io::Error::other("captured frame doesn't match the image"))?;
+                let image = if size == image_size {
+                    image
+                } else {
+                    imageops::resize(&image, size[0], size[1], filter)
+                };
+                image
+                    .save(frame_path(&directory, index as u32 + 1))
+                    .map_err(io::Error::other)?;
+            }
+            Ok(())
+        });
+
+        Ok(Self {
+            sender,
+            worker,
+            stride: settings.stride.max(1),
+            frames: settings.frame_count(),
+            queued: 0,
+        })
+    }
+
+    /// Generations to run before capturing the next frame, none for the first
+    pub fn next_generations(&self) -> u32 {
+        if self.queued == 0 {
+            0
+        } else {
+            self.stride
+        }
+    }
+
+    /// Frames queued so far and in total
+    pub fn progress(&self) -> (u32, u32) {
+        (self.queued, self.frames)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.queued >= self.frames
+    }
+
+    /// Generations run between the first frame and the last queued one
+    pub fn generations(&self) -> u32 {
+        self.queued.saturating_sub(1) * self.stride
+    }
+
+    /// Queue the next frame, 8 bit RGBA pixels row by row. Waits while the worker is behind,
+    /// fails if it stopped because of a write error, `finish` returns the error.
+    pub fn add(&mut self, frame: Vec<u8>) -> io::Result<()> {
+        self.sender.send(frame).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "PNG sequence export stopped")
+        })?;
+        self.queued += 1;
+        Ok(())
+    }
+
+    /// Write the queued frames, also used to cancel with the frames queued so far
+    pub fn finish(self) -> io::Result<()> {
+        drop(self.sender);
+        self.worker
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("PNG sequence thread panicked")))
+    }
+}
+
+fn frame_path(directory: &Path, number: u32) -> PathBuf {
+    directory.join(format!("frame_{:05}.png", number))
+}
@@ -0,0 +1,212 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use lifetime::{
+    game_compute_pipeline::{CellShape, RenderMode, Topology},
+    gradient::Gradient,
+    rule::{BlockRule, KernelRule, LeniaRule, Rule, MAX_KERNEL_RADIUS, MAX_LENIA_RADIUS},
+};
+
+use crate::settings::AppSettings;
+
+/// Presets shipped with the app, the same format as exported ones
+const BUILTIN: [&str; 4] = [
+    include_str!("../presets/conway.json"),
+    include_str!("../presets/highlife.json"),
+    include_str!("../presets/day_and_night.json"),
+    include_str!("../presets/hex_life.json"),
+];
+
+/// A rule and palette to share, without the grid or anything else a session keeps
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub rule: Rule,
+    /// At most one of these is set, replacing `rule` like the checkboxes in the panel do
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_rule: Option<KernelRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_rule: Option<BlockRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lenia: Option<LeniaRule>,
+    pub topology: Topology,
+    pub wrap_edges: bool,
+    pub mode: RenderMode,
+    pub life_color: [f32; 4],
+    pub dead_color: [f32; 4],
+    pub grid_color: [f32; 4],
+    pub density_palette: Gradient,
+    pub cell_shape: CellShape,
+}
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// Parsed, but with values the app can't use
+    Invalid(String),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Io(err) => write!(f, "failed to access preset file: {}", err),
+            PresetError::Parse(err) => write!(f, "not a preset file: {}", err),
+            PresetError::Invalid(msg) => write!(f, "invalid preset: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl From<io::Error> for PresetError {
+    fn from(err: io::Error) -> Self {
+        PresetError::Io(err)
+    }
+}
+
+impl Preset {
+    /// The rule and palette currently in `settings`
+    pub fn capture(name: &str, settings: &AppSettings) -> Self {
+        // Same precedence as the pipeline, Lenia over the block rule over the kernel rule
+        let lenia = settings.use_lenia.then(|| settings.lenia.clone());
+        let block_rule =
+            (lenia.is_none() && settings.use_block_rule).then_some(settings.block_rule);
+        let kernel_rule = (lenia.is_none() && block_rule.is_none() && settings.use_kernel_rule)
+            .then(|| settings.kernel_rule.clone());
+        Self {
+            name: name.to_string(),
+            rule: settings.rule,
+            kernel_rule,
+            block_rule,
+            lenia,
+            topology: settings.topology,
+            wrap_edges: settings.wrap_edges,
+            mode: settings.render.mode,
+            life_color: settings.render.life_color,
+            dead_color: settings.render.dead_color,
+            grid_color: settings.render.grid_color,
+            density_palette: settings.render.density_palette.clone(),
+            cell_shape: settings.render.cell_shape,
+        }
+    }
+
+    /// Replace the rule and palette in `settings`, the pipeline picks them up on the next frame
+    pub fn apply(&self, settings: &mut AppSettings) {
+        settings.rule = self.rule;
+        settings.use_kernel_rule = self.kernel_rule.is_some();
+        if let Some(kernel_rule) = &self.kernel_rule {
+            settings.kernel_rule = kernel_rule.clone();
+        }
+        settings.use_block_rule = self.block_rule.is_some();
+        if let Some(block_rule) = self.block_rule {
+            settings.block_rule = block_rule;
+        }
+        settings.use_lenia = self.lenia.is_some();
+        if let Some(lenia) = &self.lenia {
+            settings.lenia = lenia.clone();
+        }
+        settings.topology = self.topology;
+        settings.wrap_edges = self.wrap_edges;
+        settings.render.mode = self.mode;
+        settings.render.life_color = self.life_color;
+        settings.render.dead_color = self.dead_color;
+        settings.render.grid_color = self.grid_color;
+        settings.render.density_palette = self.density_palette.clone();
+        settings.render.cell_shape = self.cell_shape;
+    }
+
+    /// Checks what parsing alone doesn't, the rule string and gradient already are
+    pub fn validate(&self) -> Result<(), PresetError> {
+        let invalid = |msg: String| Err(PresetError::Invalid(msg));
+
+        let rules = [
+            self.kernel_rule.is_some(),
+            self.block_rule.is_some(),
+            self.lenia.is_some(),
+        ];
+        if rules.iter().filter(|&&set| set).count() > 1 {
+            return invalid("more than one of kernel_rule, block_rule and lenia set".to_string());
+        }
+        if let Some(kernel_rule) = &self.kernel_rule {
+            if !(1..=MAX_KERNEL_RADIUS).contains(&kernel_rule.radius) {
+                return invalid(format!(
+                    "kernel radius {} outside 1 to {}",
+                    kernel_rule.radius, MAX_KERNEL_RADIUS
+                ));
+            }
+            let side = kernel_rule.side() as usize;
+            if kernel_rule.weights.len() != side * side {
+                return invalid(format!(
+                    "{} kernel weights for a {}x{} kernel",
+                    kernel_rule.weights.len(),
+                    side,
+                    side
+                ));
+            }
+            let values = kernel_rule.weights.iter().chain(&kernel_rule.birth);
+            if !values
+                .chain(&kernel_rule.survival)
+                .all(|value| value.is_finite())
+            {
+                return invalid("kernel rule values must be finite".to_string());
+            }
+        }
+        if let Some(lenia) = &self.lenia {
+            if !(1..=MAX_LENIA_RADIUS).contains(&lenia.radius) {
+                return invalid(format!(
+                    "Lenia radius {} outside 1 to {}",
+                    lenia.radius, MAX_LENIA_RADIUS
+                ));
+            }
+            if !(lenia.growth_center.is_finite() && lenia.growth_width > 0.0) {
+                return invalid("Lenia growth needs a center and a positive width".to_string());
+            }
+            if !(lenia.time_step > 0.0 && lenia.time_step <= 1.0) {
+                return invalid(format!(
+                    "Lenia time step {} outside 0 to 1",
+                    lenia.time_step
+                ));
+            }
+        }
+
+        let colors = [self.life_color, self.dead_color, self.grid_color];
+        let stops = self.density_palette.stops().iter();
+        if !colors
+            .iter()
+            .flatten()
+            .chain(stops.flat_map(|stop| &stop.color))
+            .all(|channel| (0.0..=1.0).contains(channel))
+        {
+            return invalid("color channels must be between 0 and 1".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn parse(json: &str) -> Result<Self, PresetError> {
+        let preset: Preset = serde_json::from_str(json).map_err(PresetError::Parse)?;
+        preset.validate()?;
+        Ok(preset)
+    }
+}
+
+/// Pretty printed JSON, meant to be read and tweaked by hand
+pub fn save(path: &Path, preset: &Preset) -> Result<(), PresetError> {
+    let json = serde_json::to_string_pretty(preset).map_err(PresetError::Parse)?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Preset, PresetError> {
+    Preset::parse(&fs::read_to_string(path)?)
+}
+
+/// The presets shipped with the app
+pub fn builtin() -> Vec<Preset> {
+    BUILTIN
+        .iter()
+        .map(|json| Preset::parse(json).expect("built-in preset is valid"))
+        .collect()
+}
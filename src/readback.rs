@@ -0,0 +1,109 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use bytemuck::Pod;
+
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, CpuAccessibleBuffer},
+    device::Device,
+    sync::{FenceSignalFuture, GpuFuture},
+};
+
+use crate::init_error::InitError;
+
+/// Fence signalled once a submitted frame has finished on the GPU
+pub type FrameFence = Arc<FenceSignalFuture<Box<dyn GpuFuture>>>;
+
+/// Host buffers a GPU result is copied into and read a frame or two later, once the frame that
+/// copied it has finished, so reading never waits on the GPU. Each copy is tagged (usually with
+/// its generation) and handed out in the order they were submitted. While every buffer is still
+/// in flight there's nowhere to copy to and the frame's result is skipped.
+pub struct Readback<T: BufferContents + ?Sized> {
+    buffers: Vec<Arc<CpuAccessibleBuffer<T>>>,
+    free: Vec<usize>,
+    // Taken by `reserve` for a copy that hasn't been submitted yet
+    reserved: Option<usize>,
+    in_flight: VecDeque<(usize, u64, FrameFence)>,
+}
+
+impl<T: BufferContents + Clone> Readback<T> {
+    /// `slots` buffers, filled with `initial` until something is copied into them
+    pub fn new(device: Arc<Device>, slots: usize, initial: T) -> Result<Self, InitError> {
+        let buffers: Vec<_> = (0..slots)
+            .map(|_| {
+                CpuAccessibleBuffer::from_data(
+                    device.clone(),
+                    BufferUsage::transfer_dst(),
+                    true,
+                    initial.clone(),
+                )
+                .map_err(|err| InitError::allocation("a readback buffer", err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::from_buffers(buffers))
+    }
+}
+
+impl<T: Pod + Send + Sync> Readback<[T]> {
+    /// `slots` buffers of `len` elements, filled with `initial` until something is copied into
+    /// them
+    pub fn array(
+        device: Arc<Device>,
+        slots: usize,
+        len: usize,
+        initial: T,
+    ) -> Result<Self, InitError> {
+        let buffers: Vec<_> = (0..slots)
+            .map(|_| {
+                CpuAccessibleBuffer::from_iter(
+                    device.clone(),
+                    BufferUsage::transfer_dst(),
+                    true,
+                    (0..len).map(|_| initial),
+                )
+                .map_err(|err| InitError::allocation("a readback buffer", err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::from_buffers(buffers))
+    }
+}
+
+impl<T: BufferContents + ?Sized> Readback<T> {
+    fn from_buffers(buffers: Vec<Arc<CpuAccessibleBuffer<T>>>) -> Self {
+        Self {
+            free: (0..buffers.len()).rev().collect(),
+            buffers,
+            reserved: None,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    /// A free buffer to copy into this frame, `None` if all of them are in flight
+    pub fn reserve(&mut self) -> Option<Arc<CpuAccessibleBuffer<T>>> {
+        if self.reserved.is_none() {
+            self.reserved = self.free.pop();
+        }
+        self.reserved.map(|slot| self.buffers[slot].clone())
+    }
+
+    /// The copy into the reserved buffer was submitted, it can be read once `fence` signals
+    pub fn submit(&mut self, tag: u64, fence: FrameFence) {
+        if let Some(slot) = self.reserved.take() {
+            self.in_flight.push_back((slot, tag, fence));
+        }
+    }
+
+    /// Hand the results that have arrived since the last poll to `callback` with their tags,
+    /// without blocking
+    pub fn poll(&mut self, mut callback: impl FnMut(u64, &T)) {
+        while let Some((slot, tag, fence)) = self.in_flight.front() {
+            if !fence.is_signaled().unwrap_or(false) {
+                break;
+            }
+            // Returns right away, it only releases the buffers locked by the frame
+            fence.wait(None).unwrap();
+            callback(*tag, &self.buffers[*slot].read().unwrap());
+            self.free.push(*slot);
+            self.in_flight.pop_front();
+        }
+    }
+}
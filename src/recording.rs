@@ -0,0 +1,89 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingSettings {
+    pub path: String,
+    pub framerate: u32,
+    /// Record every `stride`th generation
+    pub stride: u32,
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        Self {
+            path: "recording.mp4".to_string(),
+            framerate: 30,
+            stride: 1,
+        }
+    }
+}
+
+/// Whether `ffmpeg` can be launched from PATH
+pub fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Encodes raw RGBA frames to an H.264 video by piping them into an `ffmpeg` child process
+pub struct FfmpegRecorder {
+    child: Child,
+    stdin: ChildStdin,
+    size: [u32; 2],
+}
+
+impl FfmpegRecorder {
+    pub fn start(path: &Path, size: [u32; 2], framerate: u32) -> io::Result<Self> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            // Input is tightly packed RGBA frames on stdin
+            .args(["-f", "rawvideo", "-pixel_format", "rgba"])
+            .args(["-video_size", &format!("{}x{}", size[0], size[1])])
+            .args(["-framerate", &framerate.to_string()])
+            .args(["-i", "-"])
+            // yuv420p is the only pixel format most players support
+            .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+
+        Ok(Self { child, stdin, size })
+    }
+
+    pub fn write_frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        if rgba.len() != (self.size[0] * self.size[1] * 4) as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame size changed during recording",
+            ));
+        }
+        self.stdin.write_all(rgba)
+    }
+
+    /// Close the pipe and wait for ffmpeg to finish writing the file
+    pub fn finish(self) -> io::Result<()> {
+        let Self {
+            mut child, stdin, ..
+        } = self;
+        drop(stdin);
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("ffmpeg exited with {}", status)))
+        }
+    }
+}
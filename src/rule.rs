@@ -0,0 +1,442 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Life-like rule in birth/survival notation, bit `n` of a mask is set if `n` live neighbors
+/// cause a dead cell to be born (or a live cell to survive)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    pub birth: u16,
+    pub survival: u16,
+}
+
+impl Rule {
+    /// B3/S23
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+    };
+
+    /// Random birth and survival masks over counts up to `max_neighbors` (8 on the square grid, 6
+    /// on the hex one). With `interesting` there's always some of both, and births on 0 or 1
+    /// neighbors, which flash or fill the grid whatever the seed, are left out.
+    pub fn random(rng: &mut impl Rng, max_neighbors: u32, interesting: bool) -> Rule {
+        let counts = (1u16 << (max_neighbors + 1)) - 1;
+        loop {
+            let mut rule = Rule {
+                birth: rng.gen::<u16>() & counts,
+                survival: rng.gen::<u16>() & counts,
+            };
+            if !interesting {
+                return rule;
+            }
+            rule.birth &= !0b11;
+            if rule.birth != 0 && rule.survival != 0 {
+                return rule;
+            }
+        }
+    }
+}
+
+/// Rules that behave well on the hexagonal grid
+pub const HEX_PRESETS: [(&str, Rule); 2] = [
+    // Carter Bays' hexagonal analogue of Life, with a small glider
+    (
+        "Hex life",
+        Rule {
+            birth: 1 << 2,
+            survival: 1 << 3 | 1 << 4,
+        },
+    ),
+    // Conway's rule unchanged, with fewer neighbors most soups die out quickly
+    ("Conway on hex", Rule::CONWAY),
+];
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |mask: u16| {
+            (0..=8)
+                .filter(|n| mask & (1 << n) != 0)
+                .map(|n| n.to_string())
+                .collect::<String>()
+        };
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Parses `B3/S23` style rules (case insensitive, either part may come first)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut birth = None;
+        let mut survival = None;
+
+        for part in s.trim().split('/') {
+            let mut chars = part.chars();
+            let target = match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => &mut birth,
+                Some('S') => &mut survival,
+                _ => return Err(format!("expected rule like B3/S23, found '{}'", s)),
+            };
+
+            let mut mask = 0u16;
+            for c in chars {
+                match c.to_digit(10) {
+                    Some(n) if n <= 8 => mask |= 1 << n,
+                    _ => return Err(format!("invalid neighbor count '{}' in rule '{}'", c, s)),
+                }
+            }
+            *target = Some(mask);
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Rule { birth, survival }),
+            _ => Err(format!("expected rule like B3/S23, found '{}'", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> Self {
+        rule.to_string()
+    }
+}
+
+/// Largest radius of a `KernelRule`, the shader keeps room for this many weights
+pub const MAX_KERNEL_RADIUS: u32 = 7;
+
+/// Larger than Life style rule (https://conwaylife.com/wiki/Larger_than_Life): the weighted sum
+/// of the live cells within `radius` causes a birth (or survival) if it's within a range
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KernelRule {
+    pub radius: u32,
+    /// `side() * side()` weights row by row, the one in the middle is the cell itself
+    pub weights: Vec<f32>,
+    /// Inclusive range of sums for a birth
+    pub birth: [f32; 2],
+    /// Inclusive range of sums for a live cell to survive
+    pub survival: [f32; 2],
+}
+
+impl KernelRule {
+    pub fn side(&self) -> u32 {
+        2 * self.radius + 1
+    }
+
+    /// Weight 1 for every cell within `radius` except the center, counting live neighbors
+    pub fn unit_weights(radius: u32) -> Vec<f32> {
+        let side = 2 * radius + 1;
+        let center = side * side / 2;
+        (0..side * side)
+            .map(|i| if i == center { 0.0 } else { 1.0 })
+            .collect()
+    }
+
+    /// Change the radius, resetting to unit weights
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = radius.clamp(1, MAX_KERNEL_RADIUS);
+        self.weights = Self::unit_weights(self.radius);
+    }
+}
+
+/// Shapes of neighborhood for the weights of a `KernelRule`, each neighbor in it counts 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// The whole square, like Conway's rule at radius 1
+    Moore,
+    /// The diamond of cells at most `radius` steps away orthogonally
+    VonNeumann,
+    /// Only the diagonals through the cell
+    Diagonal,
+    /// A hexagon, the square without two opposite corners like a hex grid stored in rows
+    Hexagonal,
+    /// The cells within `radius` in a straight line
+    Circular,
+}
+
+impl Neighborhood {
+    pub const ALL: [Neighborhood; 5] = [
+        Neighborhood::Moore,
+        Neighborhood::VonNeumann,
+        Neighborhood::Diagonal,
+        Neighborhood::Hexagonal,
+        Neighborhood::Circular,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "Moore",
+            Neighborhood::VonNeumann => "Von Neumann",
+            Neighborhood::Diagonal => "Diagonal",
+            Neighborhood::Hexagonal => "Hexagonal",
+            Neighborhood::Circular => "Circular",
+        }
+    }
+
+    /// Kernel weights row by row, 1 for the neighbors within `radius` and 0 elsewhere (and for
+    /// the cell itself)
+    pub fn weights(&self, radius: u32) -> Vec<f32> {
+        let r = radius as i32;
+        (-r..=r)
+            .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| {
+                let inside = match self {
+                    Neighborhood::Moore => true,
+                    Neighborhood::VonNeumann => dx.abs() + dy.abs() <= r,
+                    Neighborhood::Diagonal => dx.abs() == dy.abs(),
+                    Neighborhood::Hexagonal => (dx - dy).abs() <= r,
+                    Neighborhood::Circular => dx * dx + dy * dy <= r * r + r,
+                };
+                (inside && (dx, dy) != (0, 0)) as u32 as f32
+            })
+            .collect()
+    }
+}
+
+impl Default for KernelRule {
+    /// Conway's rule, B3/S23 as ranges over the unit weighted 3x3 neighborhood
+    fn default() -> Self {
+        Self {
+            radius: 1,
+            weights: Self::unit_weights(1),
+            birth: [3.0, 3.0],
+            survival: [2.0, 3.0],
+        }
+    }
+}
+
+/// Block cellular automaton on the Margolus neighborhood
+/// (https://en.wikipedia.org/wiki/Block_cellular_automaton): the grid is split into 2x2 blocks,
+/// shifted by one cell diagonally every other generation, and each block is replaced as a whole
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockRule {
+    /// HPP lattice gas, particles move diagonally and scatter sideways in head on collisions
+    Hpp,
+    /// Reversible rule with gliders, flipping crowded and empty blocks
+    Critters,
+    /// Flips blocks that are all alive or all dead
+    Tron,
+}
+
+impl BlockRule {
+    pub const ALL: [BlockRule; 3] = [BlockRule::Hpp, BlockRule::Critters, BlockRule::Tron];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockRule::Hpp => "HPP gas",
+            BlockRule::Critters => "Critters",
+            BlockRule::Tron => "Tron",
+        }
+    }
+
+    /// Next state of every block. A block is 4 bits, the top left cell in bit 0, top right in
+    /// bit 1, bottom left in bit 2 and bottom right in bit 3.
+    pub fn table(&self) -> [u32; 16] {
+        let mut table = [0; 16];
+        for (block, next) in table.iter_mut().enumerate() {
+            let block = block as u32;
+            *next = match self {
+                // Head on collisions (two particles on a diagonal) turn a quarter, everything
+                // else passes through to the opposite corner
+                BlockRule::Hpp if block == 0b1001 || block == 0b0110 => block ^ 0b1111,
+                BlockRule::Hpp => rotate_half(block),
+                BlockRule::Critters => match block.count_ones() {
+                    2 => block,
+                    3 => rotate_half(block ^ 0b1111),
+                    _ => block ^ 0b1111,
+                },
+                BlockRule::Tron if block == 0 || block == 0b1111 => block ^ 0b1111,
+                BlockRule::Tron => block,
+            };
+        }
+        table
+    }
+}
+
+/// Block turned 180 degrees, swapping the cells in opposite corners
+fn rotate_half(block: u32) -> u32 {
+    (0..4).fold(0, |rotated, bit| {
+        rotated | ((block >> bit) & 1) << (3 - bit)
+    })
+}
+
+/// Largest kernel radius of a `LeniaRule`, the shader keeps room for this many weights
+pub const MAX_LENIA_RADIUS: u32 = 13;
+
+/// Lenia (https://en.wikipedia.org/wiki/Lenia), a continuous cellular automaton: cells hold
+/// values in `0.0..=1.0`, the neighborhood is averaged with a smooth ring shaped kernel and a
+/// bell shaped growth function of the average is added to each cell every step
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeniaRule {
+    pub radius: u32,
+    /// Neighborhood average that grows a cell the most
+    pub growth_center: f32,
+    /// Width of the growth bell, averages further from the center shrink the cell
+    pub growth_width: f32,
+    /// Fraction of the growth applied each step
+    pub time_step: f32,
+}
+
+impl LeniaRule {
+    /// Parameters of the orbium, a glider (see `ORBIUM`)
+    pub const ORBIUM: LeniaRule = LeniaRule {
+        radius: 13,
+        growth_center: 0.15,
+        growth_width: 0.015,
+        time_step: 0.1,
+    };
+
+    pub fn side(&self) -> u32 {
+        2 * self.radius + 1
+    }
+
+    /// `side() * side()` weights row by row, a smooth ring of radius `radius` summing to 1
+    pub fn weights(&self) -> Vec<f32> {
+        let radius = self.radius.clamp(1, MAX_LENIA_RADIUS) as i32;
+        let mut weights: Vec<f32> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| {
+                let r = ((dx * dx + dy * dy) as f32).sqrt() / radius as f32;
+                if r > 0.0 && r < 1.0 {
+                    (4.0 - 1.0 / (r * (1.0 - r))).exp()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        for weight in &mut weights {
+            *weight /= total;
+        }
+        weights
+    }
+}
+
+impl Default for LeniaRule {
+    fn default() -> Self {
+        Self::ORBIUM
+    }
+}
+
+/// Cell values of the orbium, a glider of `LeniaRule::ORBIUM`, row by row from Bert Chan's
+/// Lenia (https://github.com/Chakazul/Lenia)
+#[rustfmt::skip]
+pub const ORBIUM: [[f32; 20]; 20] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1, 0.14, 0.1, 0.0, 0.0, 0.03, 0.03, 0.0, 0.0, 0.3, 0.0, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.08, 0.24, 0.3, 0.3, 0.18, 0.14, 0.15, 0.16, 0.15, 0.09, 0.2, 0.0, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.15, 0.34, 0.44, 0.46, 0.38, 0.18, 0.14, 0.11, 0.13, 0.19, 0.18, 0.45, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.06, 0.13, 0.39, 0.5, 0.5, 0.37, 0.06, 0.0, 0.0, 0.0, 0.02, 0.16, 0.68, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.11, 0.17, 0.17, 0.33, 0.4, 0.38, 0.28, 0.14, 0.0, 0.0, 0.0, 0.0, 0.0, 0.18, 0.42, 0.0, 0.0],
+    [0.0, 0.0, 0.09, 0.18, 0.13, 0.06, 0.08, 0.26, 0.32, 0.32, 0.27, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.82, 0.0, 0.0],
+    [0.27, 0.0, 0.16, 0.12, 0.0, 0.0, 0.0, 0.25, 0.38, 0.44, 0.45, 0.34, 0.0, 0.0, 0.0, 0.0, 0.0, 0.22, 0.17, 0.0],
+    [0.0, 0.07, 0.2, 0.02, 0.0, 0.0, 0.0, 0.31, 0.48, 0.57, 0.6, 0.57, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.49, 0.0],
+    [0.0, 0.59, 0.19, 0.0, 0.0, 0.0, 0.0, 0.2, 0.57, 0.69, 0.76, 0.76, 0.49, 0.0, 0.0, 0.0, 0.0, 0.0, 0.36, 0.0],
+    [0.0, 0.58, 0.19, 0.0, 0.0, 0.0, 0.0, 0.0, 0.67, 0.83, 0.9, 0.92, 0.87, 0.12, 0.0, 0.0, 0.0, 0.0, 0.22, 0.07],
+    [0.0, 0.0, 0.46, 0.0, 0.0, 0.0, 0.0, 0.0, 0.7, 0.93, 1.0, 1.0, 1.0, 0.61, 0.0, 0.0, 0.0, 0.0, 0.18, 0.11],
+    [0.0, 0.0, 0.82, 0.0, 0.0, 0.0, 0.0, 0.0, 0.47, 1.0, 1.0, 0.98, 1.0, 0.96, 0.27, 0.0, 0.0, 0.0, 0.19, 0.1],
+    [0.0, 0.0, 0.46, 0.0, 0.0, 0.0, 0.0, 0.0, 0.25, 1.0, 1.0, 0.84, 0.92, 0.97, 0.54, 0.14, 0.04, 0.1, 0.21, 0.05],
+    [0.0, 0.0, 0.0, 0.4, 0.0, 0.0, 0.0, 0.0, 0.09, 0.8, 1.0, 0.82, 0.8, 0.85, 0.63, 0.31, 0.18, 0.19, 0.2, 0.01],
+    [0.0, 0.0, 0.0, 0.36, 0.1, 0.0, 0.0, 0.0, 0.05, 0.54, 0.86, 0.79, 0.74, 0.72, 0.6, 0.39, 0.28, 0.24, 0.13, 0.0],
+    [0.0, 0.0, 0.0, 0.01, 0.3, 0.07, 0.0, 0.0, 0.08, 0.36, 0.64, 0.7, 0.64, 0.6, 0.51, 0.39, 0.29, 0.19, 0.04, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.1, 0.24, 0.14, 0.1, 0.15, 0.29, 0.45, 0.53, 0.52, 0.46, 0.4, 0.31, 0.21, 0.08, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.08, 0.21, 0.21, 0.22, 0.29, 0.36, 0.39, 0.37, 0.33, 0.26, 0.18, 0.09, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.03, 0.13, 0.19, 0.22, 0.24, 0.24, 0.23, 0.18, 0.13, 0.05, 0.0, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.02, 0.06, 0.08, 0.09, 0.07, 0.05, 0.01, 0.0, 0.0, 0.0, 0.0, 0.0],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight(weights: &[f32], radius: u32, [dx, dy]: [i32; 2]) -> f32 {
+        let side = 2 * radius as i32 + 1;
+        let r = radius as i32;
+        weights[((dy + r) * side + dx + r) as usize]
+    }
+
+    #[test]
+    fn neighbor_counts() {
+        let expected = [
+            (Neighborhood::Moore, [8, 24]),
+            (Neighborhood::VonNeumann, [4, 12]),
+            (Neighborhood::Diagonal, [4, 8]),
+            (Neighborhood::Hexagonal, [6, 18]),
+            (Neighborhood::Circular, [8, 20]),
+        ];
+        for (neighborhood, counts) in expected {
+            for (radius, count) in [1, 2].into_iter().zip(counts) {
+                let weights = neighborhood.weights(radius);
+                assert_eq!(weights.len(), (2 * radius as usize + 1).pow(2));
+                assert_eq!(
+                    weights.iter().sum::<f32>(),
+                    count as f32,
+                    "{} at radius {}",
+                    neighborhood.name(),
+                    radius
+                );
+                assert_eq!(weight(&weights, radius, [0, 0]), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn neighborhoods_are_symmetric() {
+        for neighborhood in Neighborhood::ALL {
+            let radius = 3;
+            let weights = neighborhood.weights(radius);
+            let r = radius as i32;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let at = weight(&weights, radius, [dx, dy]);
+                    assert_eq!(at, weight(&weights, radius, [-dx, -dy]));
+                    assert_eq!(at, weight(&weights, radius, [dy, dx]));
+                    // A hex grid stored in rows leans one way
+                    if neighborhood != Neighborhood::Hexagonal {
+                        assert_eq!(at, weight(&weights, radius, [-dx, dy]));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn block_tables_are_permutations() {
+        // Every block rule here is reversible, each block has exactly one block turning into it
+        for rule in BlockRule::ALL {
+            let mut next = rule.table();
+            next.sort_unstable();
+            assert_eq!(
+                next,
+                std::array::from_fn(|block| block as u32),
+                "{}",
+                rule.name()
+            );
+        }
+    }
+
+    #[test]
+    fn hpp_keeps_the_particles() {
+        for (block, next) in BlockRule::Hpp.table().into_iter().enumerate() {
+            assert_eq!(next.count_ones(), block.count_ones());
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{catalog, pattern::Pattern};
+
+/// How a randomized grid is filled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeedMode {
+    /// Every cell is alive with the same chance
+    Uniform,
+    /// Cells are only alive inside the blobs where Perlin noise is over a threshold
+    Noise,
+}
+
+impl SeedMode {
+    pub const ALL: [SeedMode; 2] = [SeedMode::Uniform, SeedMode::Noise];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeedMode::Uniform => "Uniform",
+            SeedMode::Noise => "Noise",
+        }
+    }
+}
+
+/// Where the seed comes from each time the grid is filled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeedSource {
+    /// The seed setting as it is
+    Fixed,
+    /// The current day (UTC), the same grid all day and a new one the next
+    Date,
+    /// The current time, a different grid every time
+    Time,
+}
+
+impl SeedSource {
+    pub const ALL: [SeedSource; 3] = [SeedSource::Fixed, SeedSource::Date, SeedSource::Time];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeedSource::Fixed => "Fixed",
+            SeedSource::Date => "Date",
+            SeedSource::Time => "Time",
+        }
+    }
+
+    /// Seed to fill the grid with at `now`, `None` to keep the current one
+    pub fn seed(&self, now: SystemTime) -> Option<u64> {
+        // A clock set before 1970 counts as 1970
+        let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        match self {
+            SeedSource::Fixed => None,
+            SeedSource::Date => Some(since_epoch.as_secs() / (24 * 60 * 60)),
+            SeedSource::Time => Some(since_epoch.as_nanos() as u64),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeedSettings {
+    pub mode: SeedMode,
+    /// Chance of a cell starting alive (inside the noise blobs for `SeedMode::Noise`)
+    pub density: f32,
+    /// Rough size of the noise blobs in cells
+    pub noise_scale: f32,
+    /// Noise value (`-1.0..=1.0`) a cell has to exceed to be in a blob
+    pub noise_threshold: f32,
+    /// The same seed always fills the grid the same way
+    pub seed: u64,
+    /// Replace `seed` from the clock before each fill
+    pub source: SeedSource,
+}
+
+impl Default for SeedSettings {
+    fn default() -> Self {
+        Self {
+            mode: SeedMode::Uniform,
+            density: 0.5,
+            noise_scale: 48.0,
+            noise_threshold: 0.1,
+            seed: 0,
+            source: SeedSource::Fixed,
+        }
+    }
+}
+
+/// Randomized grid of `size` cells
+pub fn seeded_grid(size: [u32; 2], settings: &SeedSettings) -> Pattern {
+    seeded_region([0, 0], size, settings)
+}
+
+/// Randomized block of `size` cells meant to go at `min` in the grid, the noise blobs line up
+/// with the ones `seeded_grid` would put there
+pub fn seeded_region(min: [u32; 2], size: [u32; 2], settings: &SeedSettings) -> Pattern {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let noise = Perlin::new(&mut rng);
+    let scale = settings.noise_scale.max(1.0);
+
+    let cells = (min[1]..min[1] + size[1])
+        .flat_map(|y| (min[0]..min[0] + size[0]).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let in_blob = match settings.mode {
+                SeedMode::Uniform => true,
+                SeedMode::Noise => {
+                    noise.sample(x as f32 / scale, y as f32 / scale) > settings.noise_threshold
+                }
+            };
+            // Always drawn so a cell gets the same chance whichever mode is picked
+            let roll = rng.gen::<f32>();
+            (in_blob && roll < settings.density) as u8
+        })
+        .collect();
+    Pattern::new(size, cells)
+}
+
+/// How drawn lines are widened, and roughened into random cells with the soft brush
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrushSettings {
+    /// Cells around each point of a line that are drawn too, 0 draws single cells
+    pub radius: u32,
+    /// Set the cells at random, more likely near the center of the brush
+    pub soft: bool,
+    /// Fraction of the radius the soft brush keeps its full density out to before falling off,
+    /// 1 is a solid disc
+    pub hardness: f32,
+    /// Chance of a cell being set where the soft brush is at full strength
+    pub density: f32,
+    /// The soft brush draws the same cells for the same strokes after (re)seeding
+    pub seed: u64,
+}
+
+impl Default for BrushSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0,
+            soft: false,
+            hardness: 0.3,
+            density: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+impl BrushSettings {
+    /// Cells the brush covers along `line`, each with the chance of being set (1 unless soft),
+    /// ordered by where they are first reached
+    pub fn footprint(&self, line: &[[i32; 2]]) -> Vec<([i32; 2], f32)> {
+        let radius = self.radius as i32;
+        let mut order = vec![];
+        // Closest any point of the line comes to each cell
+        let mut distances = HashMap::new();
+        for &[x, y] in line {
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                    if distance > self.radius as f32 + 0.5 {
+                        continue;
+                    }
+                    let cell = [x + dx, y + dy];
+                    let closest = distances.entry(cell).or_insert_with(|| {
+                        order.push(cell);
+                        distance
+                    });
+                    *closest = closest.min(distance);
+                }
+            }
+        }
+
+        order
+            .into_iter()
+            .map(|cell| (cell, self.chance(distances[&cell])))
+            .collect()
+    }
+
+    /// Chance of a cell `distance` from the center being set
+    fn chance(&self, distance: f32) -> f32 {
+        if !self.soft {
+            return 1.0;
+        }
+        let t = distance / (self.radius as f32 + 0.5);
+        let hardness = self.hardness.clamp(0.0, 1.0);
+        let fall = ((t - hardness) / (1.0 - hardness).max(1e-3)).clamp(0.0, 1.0);
+        // Smoothstep from full strength down to nothing at the rim
+        self.density * (1.0 - fall * fall * (3.0 - 2.0 * fall))
+    }
+
+    /// Roll the footprint of `line`, keeping the cells to set
+    pub fn paint(&self, line: &[[i32; 2]], rng: &mut StdRng) -> Vec<[i32; 2]> {
+        self.footprint(line)
+            .into_iter()
+            .filter(|&(_, chance)| chance >= 1.0 || rng.gen::<f32>() < chance)
+            .map(|(cell, _)| cell)
+            .collect()
+    }
+}
+
+/// What clicking in the viewport paints: lines with the brush, or a stamp of a shape or pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    /// Drag to draw lines of `BrushSettings::radius`
+    Paint,
+    /// Filled disc or square of `BrushSettings::radius`, a single cell at 0
+    Disc,
+    Square,
+    /// One of the `catalog` patterns
+    Named(&'static str),
+    /// The pattern last loaded or pasted
+    Loaded,
+}
+
+impl Brush {
+    pub const ALL: [Brush; 10] = [
+        Brush::Paint,
+        Brush::Disc,
+        Brush::Square,
+        Brush::Named("Block"),
+        Brush::Named("Blinker"),
+        Brush::Named("Toad"),
+        Brush::Named("Beacon"),
+        Brush::Named("Pulsar"),
+        Brush::Named("Glider"),
+        Brush::Loaded,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Brush::Paint => "Paint",
+            Brush::Disc => "Disc",
+            Brush::Square => "Square",
+            Brush::Named(name) => name,
+            Brush::Loaded => "Loaded",
+        }
+    }
+
+    /// Whether the stamp depends on the brush radius
+    pub fn is_shape(&self) -> bool {
+        matches!(self, Brush::Disc | Brush::Square)
+    }
+
+    /// The cells stamped, `None` for painting and the loaded pattern (kept by the caller)
+    pub fn stamp(&self, radius: u32) -> Option<Pattern> {
+        let side = 2 * radius + 1;
+        match self {
+            Brush::Paint | Brush::Loaded => None,
+            Brush::Disc => {
+                let solid = BrushSettings {
+                    radius,
+                    soft: false,
+                    ..BrushSettings::default()
+                };
+                let mut cells = vec![0; (side * side) as usize];
+                for ([x, y], _) in solid.footprint(&[[radius as i32; 2]]) {
+                    cells[(y as u32 * side + x as u32) as usize] = 1;
+                }
+                Some(Pattern::new([side, side], cells))
+            }
+            Brush::Square => Some(Pattern::new([side, side], vec![1; (side * side) as usize])),
+            Brush::Named(name) => catalog::named(name).map(|entry| entry.pattern()),
+        }
+    }
+}
+
+/// 2D gradient noise (https://en.wikipedia.org/wiki/Perlin_noise)
+struct Perlin {
+    // Shuffled 0..256, doubled so lookups of a lookup never need wrapping
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    fn new(rng: &mut StdRng) -> Self {
+        let mut values: Vec<u8> = (0..=255).collect();
+        values.shuffle(rng);
+
+        let mut permutation = [0; 512];
+        for (i, value) in permutation.iter_mut().enumerate() {
+            *value = values[i % 256];
+        }
+        Self { permutation }
+    }
+
+    fn hash(&self, x: i32, y: i32) -> u8 {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        self.permutation[self.permutation[x] as usize + y]
+    }
+
+    /// Dot product of the corner's pseudorandom gradient with the offset from the corner
+    fn gradient(&self, x: i32, y: i32, dx: f32, dy: f32) -> f32 {
+        match self.hash(x, y) & 7 {
+            0 => dx + dy,
+            1 => dx - dy,
+            2 => -dx + dy,
+            3 => -dx - dy,
+            4 => dx,
+            5 => -dx,
+            6 => dy,
+            _ => -dy,
+        }
+    }
+
+    /// Noise at a point, roughly in `-1.0..=1.0`
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        // Quintic fade so the noise is smooth across the corners
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let (u, v) = (fade(dx), fade(dy));
+
+        let bottom = lerp(
+            self.gradient(x0, y0, dx, dy),
+            self.gradient(x0 + 1, y0, dx - 1.0, dy),
+            u,
+        );
+        let top = lerp(
+            self.gradient(x0, y0 + 1, dx, dy - 1.0),
+            self.gradient(x0 + 1, y0 + 1, dx - 1.0, dy - 1.0),
+            u,
+        );
+        lerp(bottom, top, v)
+    }
+}
@@ -0,0 +1,242 @@
+use vulkano::sync::{self, GpuFuture};
+use vulkano_util::context::VulkanoContext;
+
+use crate::{
+    bench,
+    game_compute_pipeline::{GameComputePipeline, RenderSettings, LDR_FORMAT},
+    init_error::InitError,
+    pattern::Pattern,
+    rule::Rule,
+    soup,
+};
+
+// Grid side of the pattern checks, small enough that the edges are in reach
+const TEST_SIZE: u32 = 32;
+
+// Side, generations and seed of the random soup compared against the CPU
+const SOUP_SIZE: u32 = 64;
+const SOUP_GENERATIONS: u32 = 50;
+const SOUP_SEED: u64 = 1;
+
+/// One known pattern run on the GPU, compared against the expected grid
+struct Check {
+    name: &'static str,
+    wrap: bool,
+    /// Cells alive at the start, as a pattern and where its top left corner goes
+    start: (Pattern, [i32; 2]),
+    generations: u32,
+    /// Expected grid, `None` to compare against `reference_run`
+    expected: Option<(Pattern, [i32; 2])>,
+}
+
+/// Run the known pattern checks against the GPU simulation, then the glider counting checks and
+/// the batching and shift checks of `bench`. Prints a line per check and returns whether they all
+/// passed.
+pub fn run(context: &VulkanoContext) -> Result<bool, InitError> {
+    let glider = Pattern::new([3, 3], vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+    let blinker = Pattern::new([3, 1], vec![1, 1, 1]);
+    let block = Pattern::new([2, 2], vec![1; 4]);
+    let checks = [
+        Check {
+            name: "glider moves one cell diagonally every 4 generations",
+            wrap: true,
+            start: (glider.clone(), [10, 10]),
+            generations: 8,
+            expected: Some((glider.clone(), [12, 12])),
+        },
+        Check {
+            name: "blinker turns vertical",
+            wrap: true,
+            start: (blinker.clone(), [10, 10]),
+            generations: 1,
+            expected: Some((Pattern::new([1, 3], vec![1, 1, 1]), [11, 9])),
+        },
+        Check {
+            name: "blinker has period 2",
+            wrap: true,
+            start: (blinker.clone(), [10, 10]),
+            generations: 2,
+            expected: Some((blinker, [10, 10])),
+        },
+        Check {
+            name: "block is a still life",
+            wrap: true,
+            start: (block.clone(), [10, 10]),
+            generations: 10,
+            expected: Some((block.clone(), [10, 10])),
+        },
+        Check {
+            name: "lone cell dies",
+            wrap: true,
+            start: (Pattern::new([1, 1], vec![1]), [10, 10]),
+            generations: 1,
+            expected: Some((Pattern::new([1, 1], vec![0]), [10, 10])),
+        },
+        Check {
+            name: "three neighbors give birth",
+            wrap: true,
+            start: (Pattern::new([2, 2], vec![1, 1, 1, 0]), [10, 10]),
+            generations: 1,
+            expected: Some((block, [10, 10])),
+        },
+        Check {
+            name: "four neighbors kill",
+            wrap: true,
+            start: (
+                Pattern::new([3, 3], vec![0, 1, 0, 1, 1, 1, 0, 1, 0]),
+                [10, 10],
+            ),
+            generations: 1,
+            expected: None,
+        },
+        Check {
+            name: "glider wraps across the corner",
+            wrap: true,
+            start: (glider.clone(), [TEST_SIZE as i32 - 3, TEST_SIZE as i32 - 3]),
+            generations: 12,
+            expected: Some((glider.clone(), [0, 0])),
+        },
+        Check {
+            name: "glider stops at the edge without wrapping",
+            wrap: false,
+            start: (glider.clone(), [TEST_SIZE as i32 - 3, TEST_SIZE as i32 - 3]),
+            generations: 12,
+            expected: None,
+        },
+        Check {
+            name: "random soup matches the CPU",
+            wrap: true,
+            start: (soup::soup(SOUP_SEED, SOUP_SIZE, 0.5), [0, 0]),
+            generations: SOUP_GENERATIONS,
+            expected: None,
+        },
+    ];
+
+    let mut all_passed = true;
+    for check in &checks {
+        let passed = run_check(context, check)?;
+        all_passed &= passed;
+        println!("{}: {}", check.name, if passed { "ok" } else { "FAILED" });
+    }
+    for (name, wrap, offset, expected) in [
+        ("a lone glider counts as one", true, [10, 10], 1),
+        (
+            "a glider across the edge counts as one when wrapping",
+            true,
+            [-1, 10],
+            1,
+        ),
+        (
+            "a glider split by the edge isn't counted without wrapping",
+            false,
+            [-1, 10],
+            0,
+        ),
+    ] {
+        let passed = count_gliders(context, wrap, (glider.clone(), offset))? == Some(expected);
+        all_passed &= passed;
+        println!("{}: {}", name, if passed { "ok" } else { "FAILED" });
+    }
+    all_passed &= bench::verify_batching(context)?;
+    all_passed &= bench::verify_shift(context)?;
+    Ok(all_passed)
+}
+
+fn run_check(context: &VulkanoContext, check: &Check) -> Result<bool, InitError> {
+    let size = [TEST_SIZE.max(check.start.0.size()[0]); 2];
+    let start = place(size, &check.start);
+    let expected = match &check.expected {
+        Some(expected) => place(size, expected),
+        None => reference_run(&start, size, &Rule::CONWAY, check.wrap, check.generations),
+    };
+
+    let mut pipeline = GameComputePipeline::new(context, size, LDR_FORMAT)?;
+    pipeline.set_color(false);
+    pipeline.set_wrap(check.wrap);
+    pipeline.clear();
+    pipeline.load_pattern(&Pattern::new(size, start), [0, 0]);
+    let before = sync::now(context.device()).boxed();
+    let _ = pipeline.compute_n(
+        before,
+        check.generations,
+        &Rule::CONWAY,
+        &RenderSettings::default(),
+    );
+    pipeline.wait();
+    Ok(pipeline.snapshot() == expected)
+}
+
+/// Gliders the glider counting pass finds on a grid with only the given pattern
+fn count_gliders(
+    context: &VulkanoContext,
+    wrap: bool,
+    start: (Pattern, [i32; 2]),
+) -> Result<Option<u32>, InitError> {
+    let size = [TEST_SIZE; 2];
+    let mut pipeline = GameComputePipeline::new(context, size, LDR_FORMAT)?;
+    pipeline.set_color(false);
+    pipeline.set_wrap(wrap);
+    pipeline.set_count_gliders(true);
+    pipeline.clear();
+    pipeline.load_pattern(&Pattern::new(size, place(size, &start)), [0, 0]);
+    // No generations, only the passes over the current state
+    let before = sync::now(context.device()).boxed();
+    let _ = pipeline.compute_n(before, 0, &Rule::CONWAY, &RenderSettings::default());
+    Ok(pipeline.glider_count())
+}
+
+/// A grid of `size` with the pattern at its offset, wrapping around the edges
+fn place(size: [u32; 2], (pattern, offset): &(Pattern, [i32; 2])) -> Vec<u8> {
+    let mut cells = vec![0; (size[0] * size[1]) as usize];
+    let [width, height] = pattern.size();
+    for y in 0..height {
+        for x in 0..width {
+            let gx = (offset[0] + x as i32).rem_euclid(size[0] as i32) as u32;
+            let gy = (offset[1] + y as i32).rem_euclid(size[1] as i32) as u32;
+            cells[(gy * size[0] + gx) as usize] = pattern.get(x, y) as u8;
+        }
+    }
+    cells
+}
+
+/// `generations` steps of a life-like rule on the CPU, on the square grid. Without `wrap` cells
+/// past the edges count as dead.
+pub fn reference_run(
+    cells: &[u8],
+    size: [u32; 2],
+    rule: &Rule,
+    wrap: bool,
+    generations: u32,
+) -> Vec<u8> {
+    let [width, height] = size.map(|side| side as i32);
+    let mut cells = cells.to_vec();
+    for _ in 0..generations {
+        let alive = |x: i32, y: i32| {
+            let (x, y) = if wrap {
+                (x.rem_euclid(width), y.rem_euclid(height))
+            } else if (0..width).contains(&x) && (0..height).contains(&y) {
+                (x, y)
+            } else {
+                return 0;
+            };
+            (cells[(y * width + x) as usize] != 0) as u32
+        };
+        cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let neighbors: u32 = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(|&offset| offset != (0, 0))
+                    .map(|(dx, dy)| alive(x + dx, y + dy))
+                    .sum();
+                let mask = if alive(x, y) != 0 {
+                    rule.survival
+                } else {
+                    rule.birth
+                };
+                (mask & (1 << neighbors) != 0) as u8
+            })
+            .collect();
+    }
+    cells
+}
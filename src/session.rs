@@ -0,0 +1,136 @@
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec};
+
+use lifetime::{init_error::InitError, pattern::Pattern};
+
+use crate::settings::AppSettings;
+
+const MAGIC: &[u8; 8] = b"LIFETIME";
+
+/// Bump when the layout after the header changes, older versions stay loadable
+const VERSION: u32 = 1;
+
+/// A saved session, the settings and the full grid at some generation
+pub struct Session {
+    pub settings: AppSettings,
+    pub generation: u64,
+    pub grid: Pattern,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    NotASession,
+    UnsupportedVersion(u32),
+    Corrupt(String),
+    /// The grid couldn't be resized to the session's
+    Grid(InitError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(err) => write!(f, "failed to access session file: {}", err),
+            SessionError::NotASession => write!(f, "not a session file"),
+            SessionError::UnsupportedVersion(version) => write!(
+                f,
+                "session file version {} is newer than supported version {}",
+                version, VERSION
+            ),
+            SessionError::Corrupt(msg) => write!(f, "corrupt session file: {}", msg),
+            SessionError::Grid(err) => write!(f, "failed to restore the grid: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<io::Error> for SessionError {
+    fn from(err: io::Error) -> Self {
+        SessionError::Io(err)
+    }
+}
+
+/// Layout: magic, version, length prefixed JSON settings, generation, grid size, then the grid
+/// packed 8 cells per byte and deflated
+pub fn save(path: &Path, session: &Session) -> Result<(), SessionError> {
+    let settings = serde_json::to_vec(&session.settings)
+        .map_err(|err| SessionError::Corrupt(err.to_string()))?;
+
+    let mut packed = vec![0u8; session.grid.cells().len().div_ceil(8)];
+    for (i, &cell) in session.grid.cells().iter().enumerate() {
+        packed[i / 8] |= (cell & 1) << (i % 8);
+    }
+    let grid = compress_to_vec(&packed, 6);
+
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&(settings.len() as u32).to_le_bytes())?;
+    file.write_all(&settings)?;
+    file.write_all(&session.generation.to_le_bytes())?;
+    file.write_all(&session.grid.size()[0].to_le_bytes())?;
+    file.write_all(&session.grid.size()[1].to_le_bytes())?;
+    file.write_all(&grid)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Session, SessionError> {
+    let mut file = io::BufReader::new(fs::File::open(path)?);
+
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)
+        .map_err(|_| SessionError::NotASession)?;
+    if &magic != MAGIC {
+        return Err(SessionError::NotASession);
+    }
+
+    let version = read_u32(&mut file)?;
+    if version > VERSION {
+        return Err(SessionError::UnsupportedVersion(version));
+    }
+
+    let mut settings = vec![0u8; read_u32(&mut file)? as usize];
+    file.read_exact(&mut settings)?;
+    // Unknown fields are ignored and missing ones take their defaults
+    let settings = serde_json::from_slice(&settings)
+        .map_err(|err| SessionError::Corrupt(format!("invalid settings: {}", err)))?;
+
+    let mut generation = [0u8; 8];
+    file.read_exact(&mut generation)?;
+    let generation = u64::from_le_bytes(generation);
+
+    let size = [read_u32(&mut file)?, read_u32(&mut file)?];
+    let mut grid = vec![];
+    file.read_to_end(&mut grid)?;
+    let packed = decompress_to_vec(&grid)
+        .map_err(|err| SessionError::Corrupt(format!("invalid grid data: {:?}", err)))?;
+
+    let len = size[0] as usize * size[1] as usize;
+    if packed.len() != len.div_ceil(8) {
+        return Err(SessionError::Corrupt(format!(
+            "grid data doesn't match its {}x{} size",
+            size[0], size[1]
+        )));
+    }
+    let cells = (0..len).map(|i| (packed[i / 8] >> (i % 8)) & 1).collect();
+
+    Ok(Session {
+        settings,
+        generation,
+        grid: Pattern::new(size, cells),
+    })
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, SessionError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
@@ -0,0 +1,368 @@
+use std::{env, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use lifetime::{
+    bench::GridSuggestion,
+    game_compute_pipeline::{RenderSettings, Topology},
+    history::HistorySettings,
+    palette_animation::PaletteAnimationSettings,
+    png_sequence::PngSequenceSettings,
+    recording::RecordingSettings,
+    rule::{BlockRule, KernelRule, LeniaRule, Rule},
+    seeding::{BrushSettings, SeedSettings},
+    soup::SoupSettings,
+    sprite_sheet::SpriteSheetSettings,
+};
+
+use crate::{final_render_pass::ViewportTransform, keybindings::KeyBindings};
+
+/// Narrowest the settings panel can be resized to, in points
+pub const MIN_PANEL_WIDTH: f32 = 300.0;
+
+/// Window edge the settings panel is docked to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelSide {
+    Left,
+    Right,
+}
+
+impl PanelSide {
+    pub fn opposite(&self) -> Self {
+        match self {
+            PanelSide::Left => PanelSide::Right,
+            PanelSide::Right => PanelSide::Left,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PanelSide::Left => "left",
+            PanelSide::Right => "right",
+        }
+    }
+}
+
+/// Where the old cells go when the grid is resized keeping them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeAnchor {
+    /// Centered, the grid grows or shrinks evenly on every side
+    Center,
+    /// At the top left corner, the grid grows or shrinks on the right and bottom
+    Origin,
+}
+
+impl ResizeAnchor {
+    pub const ALL: [ResizeAnchor; 2] = [ResizeAnchor::Center, ResizeAnchor::Origin];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResizeAnchor::Center => "Center",
+            ResizeAnchor::Origin => "Top left",
+        }
+    }
+
+    /// Where the top left corner of a grid of `old` cells goes in one of `new` cells
+    pub fn offset(&self, old: [u32; 2], new: [u32; 2]) -> [i32; 2] {
+        match self {
+            ResizeAnchor::Center => [0, 1].map(|i| (new[i] as i32 - old[i] as i32) / 2),
+            ResizeAnchor::Origin => [0, 0],
+        }
+    }
+}
+
+/// How many images the swapchain of a window cycles through, more smooth out uneven frame times
+/// at the cost of latency
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwapchainImages {
+    /// The surface's minimum
+    Default,
+    Double,
+    Triple,
+}
+
+impl SwapchainImages {
+    pub const ALL: [SwapchainImages; 3] = [
+        SwapchainImages::Default,
+        SwapchainImages::Double,
+        SwapchainImages::Triple,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwapchainImages::Default => "Default",
+            SwapchainImages::Double => "Double buffering",
+            SwapchainImages::Triple => "Triple buffering",
+        }
+    }
+
+    /// Minimum image count requested, `None` for the surface's minimum
+    pub fn count(&self) -> Option<u32> {
+        match self {
+            SwapchainImages::Default => None,
+            SwapchainImages::Double => Some(2),
+            SwapchainImages::Triple => Some(3),
+        }
+    }
+}
+
+/// Collapsible group of controls in the settings panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelSection {
+    Simulation,
+    Rendering,
+    Colors,
+    Patterns,
+    Recording,
+    Debug,
+}
+
+impl PanelSection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PanelSection::Simulation => "Simulation",
+            PanelSection::Rendering => "Rendering",
+            PanelSection::Colors => "Colors",
+            PanelSection::Patterns => "Patterns",
+            PanelSection::Recording => "Recording",
+            PanelSection::Debug => "Debug",
+        }
+    }
+}
+
+/// Everything the user can adjust from the settings panel
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub rule: Rule,
+    /// Keep random rules to ones likely to do something, see `Rule::random`
+    pub interesting_random_rules: bool,
+    /// Use `kernel_rule` instead of `rule`
+    pub use_kernel_rule: bool,
+    pub kernel_rule: KernelRule,
+    /// Edit the kernel weights as a grid of on and off toggles rather than numbers
+    pub kernel_toggles: bool,
+    /// Use `block_rule` instead of `rule` and `kernel_rule`
+    pub use_block_rule: bool,
+    pub block_rule: BlockRule,
+    /// Use the continuous `lenia` rule instead of every other rule
+    pub use_lenia: bool,
+    pub lenia: LeniaRule,
+    pub render: RenderSettings,
+    pub viewport_transform: ViewportTransform,
+    /// Keep the view centered on the centroid of the live cells (overrides the offset)
+    pub track_centroid: bool,
+    /// Keep the zoom between fitting the whole grid and `MAX_CELL_SIZE` pixels per cell
+    pub limit_zoom: bool,
+    /// Round the offset so cells line up with screen pixels while the zoom puts a whole number of
+    /// pixels on each cell (or cells on each pixel), see `ViewportTransform::snapped`
+    pub snap_to_cells: bool,
+    pub panel_side: PanelSide,
+    pub key_bindings: KeyBindings,
+    /// Width the settings panel was last resized to, in points
+    pub panel_width: f32,
+    /// Panel sections left expanded
+    pub open_sections: Vec<PanelSection>,
+    /// Generations timed by the grid benchmark
+    pub benchmark_generations: u32,
+    /// Swapchain images of windows opened from this one, the swapchain of an open window can't
+    /// be recreated with a different count
+    pub swapchain_images: SwapchainImages,
+    /// Number the rows and columns along the viewport edges once cells are big enough
+    pub coordinate_labels: bool,
+    pub topology: Topology,
+    /// Wrap the grid edges around like a torus, otherwise everything past them is dead
+    pub wrap_edges: bool,
+    /// Width of the dead band kept along the grid edges, 0 lets patterns wrap around
+    pub quarantine_border: u32,
+    /// Show the number of live cells
+    pub count_population: bool,
+    /// Plot the recent live cell counts in the panel
+    pub population_sparkline: bool,
+    /// Run the glider counting pass each frame
+    pub count_gliders: bool,
+    /// Watch for live cells coming within `escape_margin` cells of an edge
+    pub detect_escape: bool,
+    /// Fill the grid from the seed again once every cell has died
+    pub restart_on_extinction: bool,
+    /// Flip random cells every generation so the grid never settles
+    pub cosmic_rays: bool,
+    /// Cells the cosmic rays flip per generation, on average
+    pub cosmic_ray_rate: f32,
+    pub escape_margin: u32,
+    pub pause_on_escape: bool,
+    /// Sample the population each frame to classify how the pattern grows
+    pub measure_growth: bool,
+    /// Frame rate cap, 0 renders as fast as possible
+    pub max_fps: u32,
+    /// Hold the simulation while a widget is being dragged, it resumes on release
+    pub pause_while_using_ui: bool,
+    /// Run a generation at a time, morphing the image from each to the next over `morph_duration`
+    pub slow_motion: bool,
+    /// Seconds each slow motion morph takes
+    pub morph_duration: f32,
+    /// Pause on reaching this generation, ignored once past it
+    pub stop_at_generation: Option<u64>,
+    /// Also finish the recording on reaching `stop_at_generation`
+    pub stop_recording_at_target: bool,
+    /// Simulation speed independent of the frame rate, 0 steps once per frame
+    pub generations_per_second: u32,
+    /// Most generations simulated in one frame, the speed drops below `generations_per_second`
+    /// rather than letting a slow frame snowball (see `pacing::MAX_FRAME_TIME`)
+    pub max_generations_per_frame: u32,
+    /// Only color every this many generations, simulating them in one go. At one generation
+    /// per frame this many are run each frame instead.
+    pub render_every: u32,
+    /// Most generations in one GPU submission, 0 submits each frame's generations together
+    pub generations_per_submit: u32,
+    pub recording: RecordingSettings,
+    pub history: HistorySettings,
+    pub palette_animation: PaletteAnimationSettings,
+    pub sprite_sheet: SpriteSheetSettings,
+    pub png_sequence: PngSequenceSettings,
+    pub soup: SoupSettings,
+    pub seeding: SeedSettings,
+    /// Cells the grid is shifted by each press of the shift keys
+    pub shift_step: u32,
+    /// Cells the grid is shifted right and down by the shift button
+    pub shift_offset: [i32; 2],
+    /// Distance between the copies when tiling a stamp
+    pub tile_spacing: [u32; 2],
+    /// Keep the cells when the grid is resized instead of filling it randomly
+    pub preserve_on_resize: bool,
+    pub resize_anchor: ResizeAnchor,
+    pub brush: BrushSettings,
+    /// JSON file macros of interactions are saved to and replayed from
+    pub macro_path: String,
+    pub screenshot_path: String,
+    /// Export screenshots of the visible cells at a whole number of pixels per cell
+    pub pixel_perfect: bool,
+    /// CSV file the stats are appended to while logging
+    pub stats_log_path: String,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            rule: Rule::default(),
+            interesting_random_rules: true,
+            use_kernel_rule: false,
+            kernel_rule: KernelRule::default(),
+            kernel_toggles: true,
+            use_block_rule: false,
+            block_rule: BlockRule::Hpp,
+            use_lenia: false,
+            lenia: LeniaRule::default(),
+            render: RenderSettings::default(),
+            viewport_transform: ViewportTransform::default(),
+            track_centroid: false,
+            limit_zoom: true,
+            snap_to_cells: false,
+            panel_side: PanelSide::Left,
+            key_bindings: KeyBindings::default(),
+            panel_width: MIN_PANEL_WIDTH,
+            open_sections: vec![PanelSection::Simulation],
+            benchmark_generations: 256,
+            swapchain_images: SwapchainImages::Default,
+            coordinate_labels: false,
+            topology: Topology::Square,
+            wrap_edges: true,
+            quarantine_border: 0,
+            count_population: false,
+            population_sparkline: true,
+            count_gliders: false,
+            detect_escape: false,
+            restart_on_extinction: false,
+            cosmic_rays: false,
+            cosmic_ray_rate: 1.0,
+            escape_margin: 16,
+            pause_on_escape: true,
+            measure_growth: false,
+            max_fps: 0,
+            pause_while_using_ui: false,
+            slow_motion: false,
+            morph_duration: 1.0,
+            stop_at_generation: None,
+            stop_recording_at_target: true,
+            generations_per_second: 0,
+            max_generations_per_frame: 100,
+            render_every: 1,
+            generations_per_submit: 0,
+            recording: RecordingSettings::default(),
+            history: HistorySettings::default(),
+            palette_animation: PaletteAnimationSettings::default(),
+            sprite_sheet: SpriteSheetSettings::default(),
+            png_sequence: PngSequenceSettings::default(),
+            soup: SoupSettings::default(),
+            seeding: SeedSettings::default(),
+            shift_step: 16,
+            shift_offset: [0, 0],
+            tile_spacing: [16, 16],
+            preserve_on_resize: true,
+            resize_anchor: ResizeAnchor::Center,
+            brush: BrushSettings::default(),
+            macro_path: "macro.json".to_string(),
+            screenshot_path: "screenshot.png".to_string(),
+            pixel_perfect: true,
+            stats_log_path: "stats.csv".to_string(),
+        }
+    }
+}
+
+/// What's kept between runs in the settings file, everything in `AppSettings` starts from the
+/// defaults each run
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoredSettings {
+    /// The first run tutorial was finished or skipped
+    pub tutorial_completed: bool,
+    /// Measured on the first start, so later starts skip the benchmark
+    pub grid_suggestion: Option<StoredSuggestion>,
+}
+
+/// A `bench::suggest_grid` result and what it was measured for, measured again if either changes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredSuggestion {
+    pub device: String,
+    pub target_fps: u32,
+    /// `None` if even the smallest grid couldn't keep up
+    pub suggestion: Option<GridSuggestion>,
+}
+
+impl StoredSettings {
+    /// The defaults if there's no settings file yet or it can't be read
+    pub fn load() -> Self {
+        settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = settings_path().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory to save settings to",
+            )
+        })?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).expect("settings serialize");
+        fs::write(path, json)
+    }
+}
+
+/// `lifetime/settings.json` in the platform's config directory, `None` if the environment
+/// doesn't say where that is
+fn settings_path() -> Option<PathBuf> {
+    let dir = |var| env::var_os(var).map(PathBuf::from);
+    let config = if cfg!(windows) {
+        dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        dir("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        dir("XDG_CONFIG_HOME").or_else(|| dir("HOME").map(|home| home.join(".config")))
+    };
+    Some(config?.join("lifetime").join("settings.json"))
+}
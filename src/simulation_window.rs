@@ -0,0 +1,4920 @@
+use std::{
+    collections::VecDeque,
+    io,
+    ops::RangeInclusive,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use egui::{Color32, Context, LayerId, Pos2, Rect, Stroke};
+use egui_winit_vulkano::Gui;
+use rand::{rngs::StdRng, SeedableRng};
+use vulkano::{
+    format::Format,
+    pipeline::graphics::viewport::Viewport,
+    sync::{self, GpuFuture},
+};
+use vulkano_util::{context::VulkanoContext, renderer::VulkanoWindowRenderer};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, WindowEvent};
+
+use lifetime::{
+    bench::GridSuggestion,
+    catalog,
+    device_info::DeviceLimits,
+    game_compute_pipeline::{
+        CellInfo, CellShape, GameComputePipeline, GridStats, RenderMode, RenderSettings, Topology,
+        HDR_FORMAT, LDR_FORMAT,
+    },
+    gradient::{Gradient, GradientStop, MAX_GRADIENT_STOPS},
+    growth::GrowthMeter,
+    history::{self, GridHistory, HistorySettings, HistoryStorage},
+    init_error::InitError,
+    pacing::{FrameClock, StepAccumulator, MIN_FRAME_TIME},
+    palette_animation::{AnimationClock, PaletteAnimation, PaletteAnimationSettings},
+    pattern::{self, Pattern, PatternError},
+    png_sequence::{PngSequence, PngSequenceSettings},
+    recording::FfmpegRecorder,
+    rule::{self, BlockRule, KernelRule, LeniaRule, Neighborhood, Rule, ORBIUM},
+    seeding::{self, Brush, BrushSettings, SeedMode, SeedSource},
+    soup::{self, SoupSearch},
+    sprite_sheet::{SpriteSheet, SpriteSheetSettings},
+    stats_log::StatsLogger,
+};
+
+use crate::{
+    cli::CliArgs,
+    final_render_pass::{FinalRenderPass, ViewportTransform},
+    input_macro::{Action, InputMacro, MacroPlayer, MacroRecorder},
+    keybindings::{self, Command, KeyBinding, KeyBindings},
+    notifications::{Level, Notifications},
+    preset::{self, Preset},
+    session::{self, Session, SessionError},
+    settings::{
+        AppSettings, PanelSection, PanelSide, ResizeAnchor, StoredSettings, SwapchainImages,
+        MIN_PANEL_WIDTH,
+    },
+    theme::THEMES,
+    tutorial::{Target, Tutorial},
+};
+
+// How quickly the view catches up with the tracked centroid, higher is snappier
+const TRACKING_SPEED: f32 = 5.0;
+
+// Dead cells left on each side of a pattern when the grid is grown to fit it
+const PATTERN_MARGIN: u32 = 32;
+
+// How long the name of a newly picked theme stays on screen
+const THEME_NOTICE_DURATION: Duration = Duration::from_millis(1500);
+
+// Shortest time between readbacks of the hovered cell
+const INSPECT_INTERVAL: Duration = Duration::from_millis(100);
+
+// Past this many cells the stamp preview only shows the outline
+const MAX_PREVIEW_CELLS: u32 = 64 * 64;
+
+// Coordinate labels are shown once a cell is this many points wide, and at least this far apart
+const MIN_LABELED_CELL_SIZE: f32 = 8.0;
+const MIN_LABEL_SPACING: f32 = 40.0;
+
+// Largest on screen size of a cell in pixels while the zoom is limited
+const MAX_CELL_SIZE: f32 = 64.0;
+
+// The temporal filter engages while a texel of the image is smaller than this many pixels
+const TEMPORAL_FILTER_TEXEL_SIZE: f32 = 1.0;
+
+// Zoom range while it isn't limited (or before the viewport is known)
+const DEFAULT_ZOOM_RANGE: RangeInclusive<f32> = 0.1..=50.0;
+
+// Time each frame spends exporting a PNG sequence, the window stays responsive in between
+const PNG_SEQUENCE_BUDGET: Duration = Duration::from_millis(50);
+
+// Population samples the sparkline shows, one per frame the stats arrive for
+const SPARKLINE_SAMPLES: usize = 120;
+const SPARKLINE_HEIGHT: f32 = 24.0;
+
+/// A window with its own simulation, renderer and settings panel
+pub struct SimulationWindow {
+    pipeline: GameComputePipeline,
+    render_pass: FinalRenderPass,
+    gui: Gui,
+    settings: AppSettings,
+    // Bounds the grid sizes patterns can resize to, shown in the device info
+    device_limits: DeviceLimits,
+    // Fewest and most (`None` if unbounded) swapchain images the window surface supports
+    surface_images: (u32, Option<u32>),
+
+    rule_text: String,
+    rule_error: Option<String>,
+
+    pattern_path: String,
+    resize_to_fit: bool,
+    // Size typed in for the grid, applied by the resize button. `None` until edited, showing the
+    // current size.
+    resize_to: Option<[u32; 2]>,
+    // Brightness over which an imported image pixel is alive
+    image_threshold: f32,
+
+    // Pattern following the cursor, placed with a left click
+    stamp: Option<Pattern>,
+    // Drag out a rectangle to fill with copies of the stamp instead, from the cell the drag began
+    tile_stamp: bool,
+    tile_start: Option<[i32; 2]>,
+    // Picked in the brush palette, anything but painting puts its pattern in `stamp`
+    brush: Brush,
+    // Last pattern loaded from a file, stdin or the clipboard, offered in the brush palette
+    loaded_pattern: Option<Pattern>,
+
+    // Dragging out the simulated region, starting from the cell where the drag began
+    drawing_region: bool,
+    region_start: Option<[i32; 2]>,
+    // Dragging out a selection to copy, starting from the cell where the drag began
+    selecting: bool,
+    selection_start: Option<[i32; 2]>,
+    selection: Option<([u32; 2], [u32; 2])>,
+    // Cell where the line being dragged out started, and whether it erases
+    line_start: Option<([i32; 2], bool)>,
+    // Drawn lines place walls instead of live cells
+    painting_walls: bool,
+    // Rolls the cells of the soft brush, restarted from the brush seed
+    brush_rng: StdRng,
+
+    // Read back the hovered cell for the inspector, throttled to `INSPECT_INTERVAL`
+    inspect_cells: bool,
+    inspected: Option<([u32; 2], CellInfo)>,
+    // Live cells of the grid last benchmarked and its average time per generation
+    grid_benchmark: Option<(u64, Duration)>,
+    // Largest grid the startup benchmark found to keep up, shown next to the grid size
+    grid_suggestion: Option<GridSuggestion>,
+    // What the grid was last identified as by the pattern catalog
+    identified: Option<String>,
+    tutorial: Tutorial,
+    last_inspect: Instant,
+
+    // Start of the clock animated palettes run on
+    palette_epoch: Instant,
+
+    session_path: String,
+
+    preset_path: String,
+    // Parsed once, see `preset::builtin`
+    builtin_presets: Vec<Preset>,
+
+    recorder: Option<FfmpegRecorder>,
+
+    stats_log: Option<StatsLogger>,
+
+    // Interactions recorded to, or replayed from, a macro file
+    macro_recorder: Option<MacroRecorder>,
+    macro_player: Option<MacroPlayer>,
+
+    // Capture the next frame and save it as a screenshot
+    screenshot_pending: bool,
+    // Run the generations of a sprite sheet at the start of the next frame
+    sprite_sheet_pending: bool,
+    // The simulation is held while a sequence is exported, a few frames each window frame
+    png_sequence: Option<PngSequence>,
+
+    // Kept after stopping so the leaderboard stays visible
+    soup_search: Option<SoupSearch>,
+    soup_running: bool,
+    soup_seed: u64,
+
+    // Feedback on actions and errors, shown over the viewport
+    notifications: Notifications,
+
+    // Hidden to give the whole window to the viewport
+    panel_visible: bool,
+    // Fullscreen display, the panel stays out of the way even when hidden
+    kiosk: bool,
+    // Command waiting for the next key press to be bound to it
+    rebinding: Option<Command>,
+    new_window_requested: bool,
+
+    // Index into `THEMES` of the theme the hotkey applies next
+    next_theme: usize,
+    // Name of the last applied theme and when it was applied
+    theme_notice: Option<(&'static str, Instant)>,
+
+    // Generations owed at `generations_per_second`, see `StepAccumulator`
+    accumulator: StepAccumulator,
+    // Generations due but held back until there are `render_every` of them
+    held_generations: u32,
+    // Stops the simulation, the grid is still drawn and can be edited
+    paused: bool,
+    // Run a single generation next frame while paused
+    step_requested: bool,
+    // Start of the slow motion morph into the current generation
+    morph_start: Option<Instant>,
+    // First generation live cells were seen within the escape margin, detection stops until cleared
+    escape_generation: Option<u64>,
+    // Population samples while measuring growth
+    growth: GrowthMeter,
+    // Past generations to step back to, and the capacity the budget cut it to when last warned
+    history: GridHistory,
+    history_capped: Option<u32>,
+    // Stats of the latest generation read back from the GPU, a frame or two behind
+    latest_stats: Option<(u64, GridStats)>,
+    // Generation and population of the latest stats for the sparkline, oldest first
+    population_samples: VecDeque<(u64, u64)>,
+
+    counter: u32,
+    fps: f32,
+    // Zero on the first frame, there's no previous one to measure from
+    frame_time: Duration,
+    frame_clock: FrameClock,
+
+    // Input state, the cursor is in physical pixels
+    viewport_bounds: Viewport,
+    cursor_position: Option<[f32; 2]>,
+    modifiers: ModifiersState,
+}
+
+impl SimulationWindow {
+    pub fn new(
+        context: &VulkanoContext,
+        renderer: &VulkanoWindowRenderer,
+        format: Format,
+        grid_size: [u32; 2],
+    ) -> Result<Self, InitError> {
+        let pipeline = GameComputePipeline::new(context, grid_size, LDR_FORMAT)?;
+        let render_pass = FinalRenderPass::new(context, format)?;
+
+        // Create gui context
+        let gui = Gui::new_with_subpass(
+            renderer.surface(),
+            context.graphics_queue(),
+            render_pass.gui_subpass(),
+        );
+
+        let settings = AppSettings::default();
+        let rule_text = settings.rule.to_string();
+        let brush_rng = StdRng::seed_from_u64(settings.brush.seed);
+        let surface_images = context
+            .device()
+            .physical_device()
+            .surface_capabilities(&renderer.surface(), Default::default())
+            .map_or((1, None), |capabilities| {
+                (capabilities.min_image_count, capabilities.max_image_count)
+            });
+
+        Ok(Self {
+            pipeline,
+            render_pass,
+            gui,
+            settings,
+            device_limits: DeviceLimits::query(context),
+            surface_images,
+            rule_text,
+            rule_error: None,
+            pattern_path: String::new(),
+            resize_to_fit: true,
+            resize_to: None,
+            image_threshold: 0.5,
+            stamp: None,
+            tile_stamp: false,
+            tile_start: None,
+            brush: Brush::Paint,
+            loaded_pattern: None,
+            drawing_region: false,
+            selecting: false,
+            selection_start: None,
+            selection: None,
+            region_start: None,
+            line_start: None,
+            painting_walls: false,
+            brush_rng,
+            inspect_cells: false,
+            inspected: None,
+            grid_benchmark: None,
+            grid_suggestion: None,
+            identified: None,
+            tutorial: Tutorial::default(),
+            last_inspect: Instant::now(),
+            palette_epoch: Instant::now(),
+            session_path: String::new(),
+            preset_path: String::new(),
+            builtin_presets: preset::builtin(),
+            recorder: None,
+            stats_log: None,
+            macro_recorder: None,
+            macro_player: None,
+            screenshot_pending: false,
+            sprite_sheet_pending: false,
+            png_sequence: None,
+            soup_search: None,
+            soup_running: false,
+            soup_seed: 0,
+            notifications: Notifications::default(),
+            panel_visible: true,
+            kiosk: false,
+            rebinding: None,
+            new_window_requested: false,
+            next_theme: 0,
+            theme_notice: None,
+            accumulator: StepAccumulator::new(1.0),
+            held_generations: 0,
+            paused: false,
+            step_requested: false,
+            morph_start: None,
+            escape_generation: None,
+            growth: GrowthMeter::default(),
+            history: GridHistory::default(),
+            history_capped: None,
+            latest_stats: None,
+            population_samples: VecDeque::with_capacity(SPARKLINE_SAMPLES),
+            counter: 0,
+            fps: 60.0,
+            frame_time: Duration::default(),
+            frame_clock: FrameClock::default(),
+            viewport_bounds: Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [1.0, 1.0],
+                depth_range: 0.0..1.0,
+            },
+            cursor_position: None,
+            modifiers: ModifiersState::default(),
+        })
+    }
+
+    pub fn settings(&self) -> &AppSettings {
+        &self.settings
+    }
+
+    /// Minimum swapchain image count for windows opened from this one, clamped to what this
+    /// window's surface supports. 0 leaves it at the surface's minimum.
+    pub fn swapchain_image_count(&self) -> u32 {
+        let (min, max) = self.surface_images;
+        self.settings.swapchain_images.count().map_or(0, |count| {
+            count.clamp(min, max.unwrap_or(u32::MAX).max(min))
+        })
+    }
+
+    /// Returns true if the event should also be handled by the app (egui didn't consume it)
+    pub fn handle_event(
+        &mut self,
+        renderer: &mut VulkanoWindowRenderer,
+        event: &WindowEvent,
+    ) -> bool {
+        let pass_events_to_app = !self.gui.update(event);
+
+        match event {
+            // Minimizing reports a zero size on some platforms, there's no swapchain for that
+            WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                renderer.resize();
+            }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                renderer.resize();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some([position.x as f32, position.y as f32]);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } if pass_events_to_app && !keybindings::is_modifier(*key) => {
+                let binding = KeyBinding::new(*key, self.modifiers);
+                if let Some(command) = self.rebinding.take() {
+                    self.rebind(command, binding);
+                } else if let Some(command) = self.settings.key_bindings.command(binding) {
+                    self.run_command(command);
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } if pass_events_to_app => {
+                if let Some(cursor) = self.cursor_position {
+                    self.handle_click(*state, cursor);
+                }
+            }
+            _ => (),
+        }
+
+        pass_events_to_app
+    }
+
+    fn handle_click(&mut self, state: ElementState, cursor: [f32; 2]) {
+        if self.drawing_region {
+            match state {
+                ElementState::Pressed => self.region_start = Some(self.cell_at(cursor)),
+                ElementState::Released => {
+                    if let Some(start) = self.region_start.take() {
+                        let region = cell_rect(start, self.cell_at(cursor));
+                        self.pipeline.set_region(Some(region));
+                        self.drawing_region = false;
+                    }
+                }
+            }
+        } else if self.selecting {
+            match state {
+                ElementState::Pressed => self.selection_start = Some(self.cell_at(cursor)),
+                ElementState::Released => {
+                    if let Some(start) = self.selection_start.take() {
+                        self.selection = Some(cell_rect(start, self.cell_at(cursor)));
+                        self.selecting = false;
+                    }
+                }
+            }
+        } else if let Some(stamp) = self.stamp.clone() {
+            if self.tile_stamp {
+                match state {
+                    ElementState::Pressed => self.tile_start = Some(self.cell_at(cursor)),
+                    ElementState::Released => {
+                        if let Some(start) = self.tile_start.take() {
+                            let (min, max) = cell_rect(start, self.cell_at(cursor));
+                            self.perform(Action::Tile {
+                                min,
+                                max,
+                                spacing: self.settings.tile_spacing,
+                                pattern: stamp,
+                            });
+                        }
+                    }
+                }
+            } else if state == ElementState::Pressed {
+                let offset = self.stamp_offset(&stamp, cursor);
+                self.perform(Action::Stamp {
+                    offset,
+                    pattern: stamp,
+                });
+            }
+        } else {
+            // Dragging draws a line of live cells (or walls), or erases one with shift held
+            match state {
+                ElementState::Pressed => {
+                    let start = self.clamped_cell_at(cursor);
+                    self.line_start = Some((start, self.modifiers.shift()));
+                }
+                ElementState::Released => {
+                    if let Some((start, erase)) = self.line_start.take() {
+                        let end = self.clamped_cell_at(cursor);
+                        let cells = self.brush_cells(start, end, erase || self.painting_walls);
+                        self.perform(if self.painting_walls {
+                            Action::Walls {
+                                cells,
+                                wall: !erase,
+                            }
+                        } else {
+                            Action::Cells {
+                                cells,
+                                alive: !erase,
+                            }
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Paste => self.paste(),
+            Command::Copy => self.copy_selection(),
+            Command::CycleTheme => {
+                let theme = &THEMES[self.next_theme];
+                theme.apply(&mut self.settings.render);
+                self.theme_notice = Some((theme.name, Instant::now()));
+                self.next_theme = (self.next_theme + 1) % THEMES.len();
+            }
+            Command::TogglePanel => self.panel_visible = !self.panel_visible,
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Cancel => {
+                self.stamp = None;
+                self.brush = Brush::Paint;
+                self.tile_start = None;
+                self.drawing_region = false;
+                self.region_start = None;
+                self.selecting = false;
+                self.selection_start = None;
+                self.line_start = None;
+            }
+            Command::NewWindow => self.new_window_requested = true,
+            Command::StepBack => self.step_back(),
+            Command::RandomRule => self.random_rule(),
+            Command::RotateStamp => self.stamp = self.stamp.as_ref().map(Pattern::rotated),
+            Command::FlipStamp => self.stamp = self.stamp.as_ref().map(Pattern::flipped),
+            Command::ShiftLeft | Command::ShiftRight | Command::ShiftUp | Command::ShiftDown => {
+                let step = self.settings.shift_step as i32;
+                let offset = match command {
+                    Command::ShiftLeft => [-step, 0],
+                    Command::ShiftRight => [step, 0],
+                    Command::ShiftUp => [0, -step],
+                    _ => [0, step],
+                };
+                self.perform(Action::Shift(offset));
+            }
+        }
+    }
+
+    /// Bind a key picked in the keybindings section, warning if another command already uses it
+    fn rebind(&mut self, command: Command, binding: KeyBinding) {
+        let bindings = &mut self.settings.key_bindings;
+        if let Some(other) = Command::ALL
+            .into_iter()
+            .find(|&other| other != command && bindings.get(other) == binding)
+        {
+            self.notifications.notify(
+                format!(
+                    "{} is also bound to {}, only one of them will work",
+                    binding,
+                    other.name()
+                ),
+                Level::Warning,
+            );
+        }
+        bindings.set(command, binding);
+    }
+
+    /// Grid size to suggest in the panel, see `bench::suggest_grid`
+    pub fn set_grid_suggestion(&mut self, suggestion: Option<GridSuggestion>) {
+        self.grid_suggestion = suggestion;
+    }
+
+    /// Whether the new window key was pressed since the last call, the app opens the window
+    pub fn take_new_window_request(&mut self) -> bool {
+        std::mem::take(&mut self.new_window_requested)
+    }
+
+    /// Apply an interaction, recording it while a macro is being recorded
+    fn perform(&mut self, action: Action) {
+        match &action {
+            Action::Seed(settings) => {
+                self.pipeline.set_generation(0);
+                self.pipeline.seed(settings);
+            }
+            Action::Rule(rule) => {
+                self.settings.rule = *rule;
+                self.rule_text = rule.to_string();
+                self.rule_error = None;
+            }
+            Action::Cells { cells, alive } => self.pipeline.set_cells(cells, *alive),
+            Action::Walls { cells, wall } => {
+                for &cell in cells {
+                    self.pipeline.set_wall(cell, *wall);
+                }
+            }
+            Action::Stamp { offset, pattern } => self.pipeline.load_pattern(pattern, *offset),
+            Action::Shift([dx, dy]) => self.pipeline.shift(*dx, *dy),
+            Action::Tile {
+                min,
+                max,
+                spacing,
+                pattern,
+            } => self.pipeline.tile_pattern(pattern, *min, *max, *spacing),
+            Action::View { offset, scale } => {
+                self.settings.viewport_transform.offset = *offset;
+                self.settings.viewport_transform.scale = *scale;
+            }
+            Action::CosmicRays(rate) => {
+                self.settings.cosmic_rays = rate.is_some();
+                if let Some(rate) = rate {
+                    self.settings.cosmic_ray_rate = *rate;
+                }
+            }
+            // Simulated by the frame that hands them out
+            Action::Generations(_) => (),
+        }
+        if let Some(recorder) = &mut self.macro_recorder {
+            recorder.push(action);
+        }
+    }
+
+    /// Fill the grid from the seed, taking a new one from the clock first unless it's fixed
+    fn reseed(&mut self) {
+        let seeding = &mut self.settings.seeding;
+        if let Some(seed) = seeding.source.seed(SystemTime::now()) {
+            seeding.seed = seed;
+        }
+        self.escape_generation = None;
+        self.perform(Action::Seed(self.settings.seeding.clone()));
+    }
+
+    /// Start recording a macro from a freshly seeded grid, so replaying it gives the same result
+    fn start_macro_recording(&mut self) {
+        self.macro_player = None;
+        self.macro_recorder = Some(MacroRecorder::start());
+        self.reseed();
+        self.record_changed_settings();
+    }
+
+    /// Record the view, rule and cosmic rays if they changed since they were last recorded
+    fn record_changed_settings(&mut self) {
+        let cosmic_rays = self.cosmic_rays();
+        let recorder = match &mut self.macro_recorder {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        let transform = &self.settings.viewport_transform;
+        let view = (transform.offset, transform.scale);
+        if recorder.last_view != Some(view) {
+            recorder.push(Action::View {
+                offset: view.0,
+                scale: view.1,
+            });
+        }
+        if recorder.last_rule != Some(self.settings.rule) {
+            recorder.push(Action::Rule(self.settings.rule));
+        }
+        if recorder.last_cosmic_rays != Some(cosmic_rays) {
+            recorder.push(Action::CosmicRays(cosmic_rays));
+        }
+    }
+
+    /// Cells the cosmic rays flip per generation, `None` while they're off
+    fn cosmic_rays(&self) -> Option<f32> {
+        Some(self.settings.cosmic_ray_rate).filter(|_| self.settings.cosmic_rays)
+    }
+
+    /// How far the slow motion morph into the current generation is, `0.0..=1.0`
+    fn morph_progress(&self) -> f32 {
+        self.morph_start.map_or(1.0, |start| {
+            (start.elapsed().as_secs_f32() / self.settings.morph_duration.max(1e-3)).min(1.0)
+        })
+    }
+
+    /// In slow motion a generation at a time, once the last morph has finished or on a step while
+    /// paused, starting the morph into it
+    fn slow_motion_generations(&mut self, paused: bool) -> u32 {
+        let due = if paused {
+            std::mem::take(&mut self.step_requested)
+        } else {
+            self.morph_progress() >= 1.0
+        };
+        if due {
+            self.morph_start = Some(Instant::now());
+        }
+        due as u32
+    }
+
+    /// Hold generations back until `render_every` of them are due, then release them all at once
+    fn batch_generations(&mut self, due: u32) -> u32 {
+        let total = self.held_generations + due;
+        if total >= self.settings.render_every.max(1) {
+            self.held_generations = 0;
+            total
+        } else {
+            self.held_generations = total;
+            0
+        }
+    }
+
+    /// Apply the replayed actions due this frame, returns the generations to simulate
+    fn replay_macro(&mut self) -> Option<u32> {
+        let actions = self.macro_player.as_mut()?.due();
+        let mut generations = 0;
+        for action in actions {
+            match action {
+                Action::Generations(count) => generations += count,
+                action => self.perform(action),
+            }
+        }
+        if self
+            .macro_player
+            .as_ref()
+            .is_some_and(|player| player.finished())
+        {
+            self.macro_player = None;
+            self.notifications.notify("Replay finished", Level::Info);
+        }
+        Some(generations)
+    }
+
+    /// Refresh the inspected cell from the one under the cursor
+    fn inspect_hovered_cell(&mut self) {
+        if !self.inspect_cells || self.last_inspect.elapsed() < INSPECT_INTERVAL {
+            return;
+        }
+        self.last_inspect = Instant::now();
+
+        // Cells behind the panel aren't visible, so they can't be hovered
+        let bounds = &self.viewport_bounds;
+        let in_viewport = |cursor: &[f32; 2]| {
+            (0..2).all(|i| {
+                (bounds.origin[i]..bounds.origin[i] + bounds.dimensions[i]).contains(&cursor[i])
+            })
+        };
+        let size = self.pipeline.size();
+        let cell = self
+            .cursor_position
+            .filter(in_viewport)
+            .map(|cursor| self.cell_at(cursor))
+            .filter(|cell| (0..2).all(|i| (0..size[i] as i32).contains(&cell[i])))
+            .map(|cell| cell.map(|v| v as u32));
+        self.inspected = cell.map(|cell| (cell, self.pipeline.inspect_cell(cell)));
+    }
+
+    /// Take the stats that arrived from the GPU since last frame, without waiting for the ones
+    /// still in flight, and log, sample and check them
+    fn poll_stats(&mut self) {
+        let mut arrived = vec![];
+        self.pipeline
+            .poll_stats(|generation, stats| arrived.push((generation, stats)));
+
+        for (generation, stats) in arrived {
+            if let Some(stats_log) = &mut self.stats_log {
+                if stats_log.log(generation, &stats).is_err() {
+                    // The worker hit a write error, finishing reports it
+                    if let Err(err) = self.stats_log.take().unwrap().finish() {
+                        self.notifications.notify(err.to_string(), Level::Error);
+                    }
+                }
+            }
+            if self.settings.measure_growth {
+                self.growth.sample(generation, stats.population);
+            }
+            self.detect_escape(generation, &stats);
+            // Stats still in flight from before a restart are for later generations than the grid
+            let current = generation > 0 && generation <= self.pipeline.generation();
+            if self.settings.population_sparkline && current {
+                self.sample_population(generation, stats.population);
+            }
+            if self.settings.restart_on_extinction && current && stats.population == 0 {
+                self.reseed();
+            }
+            self.latest_stats = Some((generation, stats));
+        }
+    }
+
+    /// Add a sparkline sample, dropping the oldest past `SPARKLINE_SAMPLES`. An earlier
+    /// generation than the last sample (the grid was reset) starts over.
+    fn sample_population(&mut self, generation: u64, population: u64) {
+        match self.population_samples.back() {
+            Some(&(last, _)) if last == generation => return,
+            Some(&(last, _)) if last > generation => self.population_samples.clear(),
+            _ => (),
+        }
+        if self.population_samples.len() == SPARKLINE_SAMPLES {
+            self.population_samples.pop_front();
+        }
+        self.population_samples.push_back((generation, population));
+    }
+
+    /// Allocate the buffers of the effects that are on, turning off the ones that don't fit
+    fn update_effect_buffers(&mut self) {
+        let effects = self.settings.render.mode.has_effects();
+        if let Err(err) = self.pipeline.set_trails(self.settings.render.trails) {
+            self.settings.render.trails = false;
+            self.notifications
+                .notify(format!("Trails turned off, {}", err), Level::Error);
+        }
+        if let Err(err) = self
+            .pipeline
+            .set_bloom(self.settings.render.bloom && effects)
+        {
+            self.settings.render.bloom = false;
+            self.notifications
+                .notify(format!("Bloom turned off, {}", err), Level::Error);
+        }
+        // Only while texels are smaller than a screen pixel, zoomed in the image stays crisp
+        let texel_size = self.settings.viewport_transform.scale
+            * self.viewport_bounds.dimensions[0]
+            / self.pipeline.image_size()[0] as f32;
+        if let Err(err) = self.pipeline.set_temporal_filter(
+            self.settings.render.temporal_filter
+                && texel_size < TEMPORAL_FILTER_TEXEL_SIZE
+                && effects,
+        ) {
+            self.settings.render.temporal_filter = false;
+            self.notifications
+                .notify(format!("Anti-flicker turned off, {}", err), Level::Error);
+        }
+        if let Err(err) = self.pipeline.set_keep_previous(self.settings.slow_motion) {
+            self.settings.slow_motion = false;
+            self.notifications
+                .notify(format!("Slow motion turned off, {}", err), Level::Error);
+        }
+    }
+
+    /// Keep the grids read back since the last frame, within the history's budget
+    fn poll_history(&mut self) {
+        if let Err(err) = self
+            .pipeline
+            .set_record_history(self.settings.history.enabled)
+        {
+            self.settings.history.enabled = false;
+            self.notifications
+                .notify(format!("History turned off, {}", err), Level::Error);
+        }
+        let settings = &self.settings.history;
+        if !settings.enabled {
+            self.history.clear();
+            self.history_capped = None;
+            return;
+        }
+
+        let size = self.pipeline.size();
+        let capacity = settings.capacity(size);
+        if capacity >= settings.generations {
+            self.history_capped = None;
+        } else if self.history_capped != Some(capacity) {
+            self.history_capped = Some(capacity);
+            self.notifications.notify(
+                format!(
+                    "Only {} generations of history fit the {} MiB budget",
+                    capacity, settings.budget_mib
+                ),
+                Level::Warning,
+            );
+        }
+
+        // Grids still in flight from before stepping back are for later generations
+        let current = self.pipeline.generation();
+        let storage = settings.storage;
+        let history = &mut self.history;
+        self.pipeline.poll_history(|generation, cells| {
+            if generation <= current {
+                history.push(generation, size, cells, storage, capacity);
+            }
+        });
+        history.truncate(capacity);
+    }
+
+    /// Switch to a random life-like rule (over the other rules) and run it on a freshly seeded
+    /// grid, the rule shows in the rule field to keep if it turns out interesting
+    fn random_rule(&mut self) {
+        let rule = Rule::random(
+            &mut rand::thread_rng(),
+            self.settings.topology.neighbors(),
+            self.settings.interesting_random_rules,
+        );
+        self.settings.use_kernel_rule = false;
+        self.settings.use_block_rule = false;
+        self.settings.use_lenia = false;
+        self.perform(Action::Rule(rule));
+        self.reseed();
+        self.paused = false;
+        self.notifications
+            .notify(format!("Exploring {}", rule), Level::Info);
+    }
+
+    /// Resize the grid to `resize_to` (rounded up to the workgroup size), keeping the cells or
+    /// filling it randomly. Warns if shrinking cropped any live cells.
+    fn resize_grid(&mut self) {
+        let old_size = self.pipeline.size();
+        let size = self
+            .resize_to
+            .take()
+            .unwrap_or(old_size)
+            .map(|side| side.max(8).next_multiple_of(8));
+        let result = if self.settings.preserve_on_resize {
+            let offset = self.settings.resize_anchor.offset(old_size, size);
+            self.pipeline.resize_preserving(
+                size,
+                offset,
+                &self.settings.rule,
+                &self.settings.render,
+            )
+        } else {
+            self.pipeline.resize(size).map(|()| 0)
+        };
+        match result {
+            Ok(0) => (),
+            Ok(cropped) => self.notifications.notify(
+                format!(
+                    "Shrinking the grid to {}x{} cropped {} live cells",
+                    size[0], size[1], cropped
+                ),
+                Level::Warning,
+            ),
+            Err(err) => self.notifications.notify(err.to_string(), Level::Error),
+        }
+        self.escape_generation = None;
+    }
+
+    /// Clear the grid and place an orbium in the middle under its Lenia rule
+    fn load_orbium(&mut self) {
+        self.settings.use_lenia = true;
+        self.settings.lenia = LeniaRule::ORBIUM;
+        let size = [ORBIUM[0].len() as u32, ORBIUM.len() as u32];
+        let offset = [0, 1].map(|i| (self.pipeline.size()[i] as i32 - size[i] as i32) / 2);
+        self.pipeline.clear();
+        self.pipeline.set_generation(0);
+        self.pipeline.load_values(size, &ORBIUM.concat(), offset);
+    }
+
+    /// Restore the newest generation in the history before the current one and pause there
+    fn step_back(&mut self) {
+        match self.history.step_back(self.pipeline.generation()) {
+            Some((generation, grid)) => {
+                // The stamp covers the whole grid
+                self.perform(Action::Stamp {
+                    offset: [0, 0],
+                    pattern: grid,
+                });
+                self.pipeline.set_generation(generation);
+                self.paused = true;
+            }
+            None => self
+                .notifications
+                .notify("No earlier generation in the history", Level::Info),
+        }
+    }
+
+    /// Note the generation if its live cells reached the escape margin, usually a spaceship
+    /// leaving the pattern
+    fn detect_escape(&mut self, generation: u64, stats: &GridStats) {
+        if !self.settings.detect_escape || self.escape_generation.is_some() {
+            return;
+        }
+        let (min, max) = match stats.bounding_box {
+            Some(bounding_box) => bounding_box,
+            None => return,
+        };
+
+        let size = self.pipeline.size();
+        let margin = self.settings.escape_margin;
+        if (0..2).any(|i| min[i] < margin || max[i] + margin >= size[i]) {
+            self.escape_generation = Some(generation);
+            if self.settings.pause_on_escape {
+                self.paused = true;
+            }
+        }
+    }
+
+    /// Sample the soup simulated last frame and start the next one when it's done
+    fn step_soup_search(&mut self) {
+        let search = match &mut self.soup_search {
+            Some(search) => search,
+            None => return,
+        };
+
+        if let (Some(_), Some(stats)) = (search.current_seed(), self.pipeline.stats()) {
+            let finished = search
+                .sample(
+                    self.pipeline.generation(),
+                    stats.population,
+                    &self.settings.soup,
+                )
+                .is_some();
+            if finished {
+                let path = Path::new(&self.settings.soup.leaderboard_path);
+                if let Err(err) = soup::save_leaderboard(path, search.leaderboard()) {
+                    self.notifications
+                        .notify(format!("failed to save leaderboard: {}", err), Level::Error);
+                }
+            }
+        }
+
+        if let Some(seed) = search.next_soup() {
+            let settings = &self.settings.soup;
+            place_soup(&mut self.pipeline, seed, settings.size, settings.density);
+        }
+    }
+
+    /// Copy the selected cells to the clipboard as RLE
+    fn copy_selection(&mut self) {
+        let (min, max) = match self.selection {
+            Some(selection) => selection,
+            None => return,
+        };
+        let (cells, size) = self.pipeline.snapshot_region(min, max);
+        let rle = pattern::encode_rle(&Pattern::new(size, cells), &self.settings.rule);
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(rle)) {
+            Ok(()) => self
+                .notifications
+                .notify(format!("Copied {}x{} cells", size[0], size[1]), Level::Info),
+            Err(err) => self
+                .notifications
+                .notify(format!("failed to write clipboard: {}", err), Level::Error),
+        }
+    }
+
+    /// Copy the view transform to the clipboard as JSON, to come back to or share the framing
+    fn copy_view(&mut self) {
+        let json = match serde_json::to_string(&self.displayed_transform()) {
+            Ok(json) => json,
+            Err(err) => {
+                self.notifications
+                    .notify(format!("failed to encode view: {}", err), Level::Error);
+                return;
+            }
+        };
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(json)) {
+            Ok(()) => self.notifications.notify("Copied view", Level::Info),
+            Err(err) => self
+                .notifications
+                .notify(format!("failed to write clipboard: {}", err), Level::Error),
+        }
+    }
+
+    /// Move to the view transform on the clipboard, the aspect ratio stays the viewport's
+    fn paste_view(&mut self) {
+        match paste_view() {
+            Ok(transform) => self.perform(Action::View {
+                offset: transform.offset,
+                scale: transform.scale,
+            }),
+            Err(err) => self.notifications.notify(err, Level::Error),
+        }
+    }
+
+    /// Enter stamp mode with the RLE pattern on the clipboard
+    fn paste(&mut self) {
+        match paste_rle(self.device_limits.max_grid_side()) {
+            Ok(pattern) => {
+                self.loaded_pattern = Some(pattern);
+                self.select_brush(Brush::Loaded);
+            }
+            Err(err) => self.notifications.notify(err, Level::Error),
+        }
+    }
+
+    /// Switch to a brush from the palette, stamps start out in their listed orientation
+    fn select_brush(&mut self, brush: Brush) {
+        self.stamp = match brush {
+            Brush::Loaded => self.loaded_pattern.clone(),
+            brush => brush.stamp(self.settings.brush.radius),
+        };
+        self.brush = if self.stamp.is_some() {
+            brush
+        } else {
+            Brush::Paint
+        };
+        self.tile_start = None;
+    }
+
+    /// The view transform the grid is drawn with, fitted to the current viewport (a loaded session
+    /// may carry the aspect ratio of another one) and snapped to the cell grid if enabled
+    fn displayed_transform(&self) -> ViewportTransform {
+        let viewport = self.viewport_bounds.dimensions;
+        let transform = self.settings.viewport_transform.fitted(viewport);
+        let transform = if self.settings.snap_to_cells {
+            transform.snapped(viewport, self.pipeline.image_size())
+        } else {
+            transform
+        };
+        let [width, height] = transform.drawn_size(viewport);
+        debug_assert!(
+            (width - height).abs() <= 1e-3 * width.max(height),
+            "grid drawn {}x{} pixels in a {}x{} viewport, cells aren't square",
+            width,
+            height,
+            viewport[0],
+            viewport[1]
+        );
+        transform
+    }
+
+    /// Take the area egui left free after laying out the panel (see `calculate_viewport`) as the
+    /// viewport, on whichever side the panel is docked or with it hidden. Keeps the last viewport while the panel covers the
+    /// whole window and returns whether there is one to draw in.
+    fn update_viewport(&mut self, viewport_bounds: Viewport) -> bool {
+        if viewport_bounds.dimensions.iter().any(|&side| side < 1.0) {
+            return false;
+        }
+        self.settings.viewport_transform = self
+            .settings
+            .viewport_transform
+            .fitted(viewport_bounds.dimensions);
+        self.viewport_bounds = viewport_bounds;
+        true
+    }
+
+    /// Grid cell under a point in the window (in physical pixels), may be outside the grid
+    fn cell_at(&self, position: [f32; 2]) -> [i32; 2] {
+        let bounds = &self.viewport_bounds;
+        let ndc =
+            [0, 1].map(|i| (position[i] - bounds.origin[i]) / bounds.dimensions[i] * 2.0 - 1.0);
+        let tex_coords = self.displayed_transform().tex_coords(ndc);
+        let size = self.pipeline.displayed_size();
+        [0, 1].map(|i| (tex_coords[i] * size[i] as f32).floor() as i32)
+    }
+
+    /// Cells from `min` (inclusive) to `max` (exclusive) at least partly in the viewport, clamped
+    /// to the grid. `None` if the grid is out of view.
+    fn visible_cells(&self) -> Option<([u32; 2], [u32; 2])> {
+        let bounds = &self.viewport_bounds;
+        let corners = [
+            self.cell_at(bounds.origin),
+            self.cell_at([0, 1].map(|i| bounds.origin[i] + bounds.dimensions[i])),
+        ];
+        let size = self.pipeline.size();
+        let [min, max] = [0, 1].map(|corner| {
+            [0, 1].map(|i| {
+                let low = corners[0][i].min(corners[1][i]);
+                let high = corners[0][i].max(corners[1][i]) + 1;
+                let cell = if corner == 0 { low } else { high };
+                cell.clamp(0, size[i] as i32) as u32
+            })
+        });
+        (min[0] < max[0] && min[1] < max[1]).then_some((min, max))
+    }
+
+    /// Fill the visible cells from the seed settings, leaving the rest of the grid as it is
+    fn randomize_visible(&mut self) {
+        let (min, max) = match self.visible_cells() {
+            Some(visible) => visible,
+            None => {
+                self.notifications
+                    .notify("no cells are visible", Level::Warning);
+                return;
+            }
+        };
+        let size = [0, 1].map(|i| max[i] - min[i]);
+        let pattern = seeding::seeded_region(min, size, &self.settings.seeding);
+        self.perform(Action::Stamp {
+            offset: min.map(|v| v as i32),
+            pattern,
+        });
+    }
+
+    /// Cell under a point like `cell_at`, moved to the nearest cell inside the grid
+    fn clamped_cell_at(&self, position: [f32; 2]) -> [i32; 2] {
+        let cell = self.cell_at(position);
+        let size = self.pipeline.size();
+        [0, 1].map(|i| cell[i].clamp(0, size[i] as i32 - 1))
+    }
+
+    /// Position in egui points of the top left corner of a grid cell
+    fn cell_position(&self, cell: [i32; 2], scale_factor: f32) -> Pos2 {
+        let bounds = &self.viewport_bounds;
+        let size = self.pipeline.displayed_size();
+        let ndc = self
+            .displayed_transform()
+            .ndc([0, 1].map(|i| cell[i] as f32 / size[i] as f32));
+        let [x, y] = [0, 1].map(|i| bounds.origin[i] + (ndc[i] + 1.0) / 2.0 * bounds.dimensions[i]);
+        // egui works in logical points
+        Pos2::new(x / scale_factor, y / scale_factor)
+    }
+
+    /// Screen rectangle covering the cells from `min` (inclusive) to `max` (exclusive)
+    fn cells_rect(&self, min: [i32; 2], max: [i32; 2], scale_factor: f32) -> Rect {
+        Rect::from_two_pos(
+            self.cell_position(min, scale_factor),
+            self.cell_position(max, scale_factor),
+        )
+    }
+
+    /// Outline the simulated region, or the one being dragged out
+    fn draw_region(&self, context: &Context, scale_factor: f32) {
+        let region = match (self.region_start, self.cursor_position) {
+            (Some(start), Some(cursor)) => Some(cell_rect(start, self.cell_at(cursor))),
+            _ => self.pipeline.region(),
+        };
+
+        if let Some((min, max)) = region {
+            context.layer_painter(LayerId::background()).rect_stroke(
+                self.cells_rect(min.map(|v| v as i32), max.map(|v| v as i32), scale_factor),
+                0.0,
+                Stroke::new(1.0, Color32::YELLOW),
+            );
+        }
+    }
+
+    /// Outline the selection, or the one being dragged out
+    fn draw_selection(&self, context: &Context, scale_factor: f32) {
+        let selection = match (self.selection_start, self.cursor_position) {
+            (Some(start), Some(cursor)) => Some(cell_rect(start, self.cell_at(cursor))),
+            _ => self.selection,
+        };
+
+        if let Some((min, max)) = selection {
+            context.layer_painter(LayerId::background()).rect_stroke(
+                self.cells_rect(min.map(|v| v as i32), max.map(|v| v as i32), scale_factor),
+                0.0,
+                Stroke::new(1.0, Color32::LIGHT_BLUE),
+            );
+        }
+    }
+
+    /// Briefly show the name of a theme picked with the hotkey
+    fn draw_theme_notice(&self, context: &Context) {
+        let name = match self.theme_notice {
+            Some((name, applied)) if applied.elapsed() < THEME_NOTICE_DURATION => name,
+            _ => return,
+        };
+
+        egui::Area::new("theme_notice")
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 16.0])
+            .show(context, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(egui::RichText::new(name).heading());
+                });
+            });
+    }
+
+    /// Scale that fits the whole grid in the viewport, the image is drawn square with sides
+    /// `scale` times the viewport width
+    fn fit_scale(&self) -> f32 {
+        let [width, height] = self.viewport_bounds.dimensions;
+        if width < 1.0 || height < 1.0 {
+            return 1.0;
+        }
+        (height / width).min(1.0)
+    }
+
+    /// Scales from fitting the whole grid to `MAX_CELL_SIZE` pixels per cell
+    fn zoom_range(&self) -> RangeInclusive<f32> {
+        let width = self.viewport_bounds.dimensions[0];
+        if !self.settings.limit_zoom || width < 1.0 {
+            return DEFAULT_ZOOM_RANGE;
+        }
+
+        let fit = self.fit_scale();
+        let cells = self.pipeline.size()[0].min(self.pipeline.size()[1]) as f32;
+        let max = MAX_CELL_SIZE * cells / width;
+        fit..=max.max(fit)
+    }
+
+    /// Number the visible columns along the top of the viewport and the rows along its left side,
+    /// every 1, 2 or 5 times a power of ten cells so the labels don't overlap
+    fn draw_coordinate_labels(&self, context: &Context, scale_factor: f32) {
+        if !self.settings.coordinate_labels {
+            return;
+        }
+        let cell_size =
+            self.cell_position([1, 1], scale_factor) - self.cell_position([0, 0], scale_factor);
+        if cell_size.min_elem() < MIN_LABELED_CELL_SIZE {
+            return;
+        }
+        let step = [1, 2, 5]
+            .into_iter()
+            .flat_map(|base| (0..9).map(move |power| base * 10i32.pow(power)))
+            .filter(|step| *step as f32 * cell_size.min_elem() >= MIN_LABEL_SPACING)
+            .min()
+            .unwrap_or(1);
+
+        let bounds = &self.viewport_bounds;
+        let first = self.cell_at(bounds.origin);
+        let last = self.cell_at([0, 1].map(|i| bounds.origin[i] + bounds.dimensions[i] - 1.0));
+        let viewport_min = [0, 1].map(|i| bounds.origin[i] / scale_factor);
+        let size = self.pipeline.displayed_size().map(|side| side as i32);
+
+        let painter = context.layer_painter(LayerId::background());
+        let font = egui::FontId::monospace(10.0);
+        let color = Color32::from_white_alpha(160);
+        for axis in 0..2 {
+            let start = first[axis] + (step - first[axis].rem_euclid(step)) % step;
+            for cell in (start..=last[axis]).step_by(step as usize) {
+                // The view repeats past the edges while wrapping
+                let label = if self.settings.wrap_edges {
+                    cell.rem_euclid(size[axis])
+                } else if (0..size[axis]).contains(&cell) {
+                    cell
+                } else {
+                    continue;
+                };
+
+                let mut center = [0; 2];
+                center[axis] = cell;
+                let corner = self.cell_position(center, scale_factor);
+                let (position, anchor) = if axis == 0 {
+                    (
+                        Pos2::new(corner.x + cell_size.x / 2.0, viewport_min[1] + 2.0),
+                        egui::Align2::CENTER_TOP,
+                    )
+                } else {
+                    (
+                        Pos2::new(viewport_min[0] + 2.0, corner.y + cell_size.y / 2.0),
+                        egui::Align2::LEFT_CENTER,
+                    )
+                };
+                painter.text(position, anchor, label.to_string(), font.clone(), color);
+            }
+        }
+    }
+
+    /// Cells in the grid the brush sets along the line from `start` to `end`, every one it covers
+    /// if `solid` (erasing and walls) or else those the soft brush rolls
+    fn brush_cells(&mut self, start: [i32; 2], end: [i32; 2], solid: bool) -> Vec<[u32; 2]> {
+        let line = bresenham_line(start, end);
+        let brush = &self.settings.brush;
+        let cells = if solid {
+            brush
+                .footprint(&line)
+                .into_iter()
+                .map(|(cell, _)| cell)
+                .collect()
+        } else {
+            brush.paint(&line, &mut self.brush_rng)
+        };
+        let size = self.pipeline.size();
+        cells
+            .into_iter()
+            .filter(|cell| (0..2).all(|i| (0..size[i] as i32).contains(&cell[i])))
+            .map(|cell| cell.map(|v| v as u32))
+            .collect()
+    }
+
+    /// Show the cells the line being dragged out will set
+    fn draw_line_preview(&self, context: &Context, scale_factor: f32) {
+        let (start, erase, cursor) = match (self.line_start, self.cursor_position) {
+            (Some((start, erase)), Some(cursor)) => (start, erase, cursor),
+            _ => return,
+        };
+
+        let color = if erase {
+            Color32::from_rgba_unmultiplied(255, 64, 64, 128)
+        } else if self.painting_walls {
+            let [r, g, b, _] = self.settings.render.wall_color.map(|v| (v * 255.0) as u8);
+            Color32::from_rgba_unmultiplied(r, g, b, 160)
+        } else {
+            Color32::from_white_alpha(128)
+        };
+        let painter = context.layer_painter(LayerId::background());
+        let line = bresenham_line(start, self.clamped_cell_at(cursor));
+        let footprint = self.settings.brush.footprint(&line);
+        for (cell, chance) in footprint.into_iter().take(MAX_PREVIEW_CELLS as usize) {
+            // The soft brush fades out with the chance of setting each cell
+            let chance = if erase || self.painting_walls {
+                1.0
+            } else {
+                chance
+            };
+            painter.rect_filled(
+                self.cells_rect(cell, [cell[0] + 1, cell[1] + 1], scale_factor),
+                0.0,
+                color.linear_multiply(chance),
+            );
+        }
+    }
+
+    /// Offset that centers the stamp on the cell under the cursor
+    fn stamp_offset(&self, stamp: &Pattern, cursor: [f32; 2]) -> [i32; 2] {
+        let cell = self.cell_at(cursor);
+        let size = stamp.size();
+        [0, 1].map(|i| cell[i] - size[i] as i32 / 2)
+    }
+
+    fn draw_stamp_preview(&self, context: &Context, scale_factor: f32) {
+        let (stamp, cursor) = match (&self.stamp, self.cursor_position) {
+            (Some(stamp), Some(cursor)) => (stamp, cursor),
+            _ => return,
+        };
+
+        // Outline the rectangle being tiled and where each copy goes
+        if self.tile_stamp {
+            let (min, max) = match self.tile_start {
+                Some(start) => cell_rect(start, self.cell_at(cursor)),
+                None => return,
+            };
+            let [min, max] = [min, max].map(|cell| cell.map(|v| v as i32));
+            let painter = context.layer_painter(LayerId::background());
+            let spacing = self.settings.tile_spacing.map(|v| v.max(1) as i32);
+            let tiles = (min[1]..max[1]).step_by(spacing[1] as usize).flat_map(|y| {
+                (min[0]..max[0])
+                    .step_by(spacing[0] as usize)
+                    .map(move |x| [x, y])
+            });
+            for tile in tiles.take(MAX_PREVIEW_CELLS as usize) {
+                let end = [0, 1].map(|i| (tile[i] + spacing[i]).min(max[i]));
+                painter.rect_stroke(
+                    self.cells_rect(tile, end, scale_factor),
+                    0.0,
+                    Stroke::new(1.0, Color32::from_white_alpha(64)),
+                );
+            }
+            painter.rect_stroke(
+                self.cells_rect(min, max, scale_factor),
+                0.0,
+                Stroke::new(1.0, Color32::WHITE),
+            );
+            return;
+        }
+
+        let [width, height] = stamp.size();
+        let [x0, y0] = self.stamp_offset(stamp, cursor);
+        let painter = context.layer_painter(LayerId::background());
+        painter.rect_stroke(
+            self.cells_rect(
+                [x0, y0],
+                [x0 + width as i32, y0 + height as i32],
+                scale_factor,
+            ),
+            0.0,
+            Stroke::new(1.0, Color32::WHITE),
+        );
+
+        if width * height <= MAX_PREVIEW_CELLS {
+            for y in 0..height {
+                for x in 0..width {
+                    if stamp.get(x, y) {
+                        let cell = [x0 + x as i32, y0 + y as i32];
+                        painter.rect_filled(
+                            self.cells_rect(cell, [cell[0] + 1, cell[1] + 1], scale_factor),
+                            0.0,
+                            Color32::from_white_alpha(128),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply the seeding options from the command line, problems are printed and shown in the
+    /// panel but don't stop the rest from being applied
+    pub fn seed(&mut self, args: &CliArgs) {
+        if args.kiosk {
+            self.kiosk = true;
+            self.panel_visible = false;
+            self.settings.restart_on_extinction = true;
+            self.settings.seeding.source = SeedSource::Date;
+        } else if !StoredSettings::load().tutorial_completed {
+            self.start_tutorial();
+        }
+        if let Some(source) = args.seed_source {
+            self.settings.seeding.source = source;
+        }
+        self.settings.swapchain_images = args.swapchain_images;
+        // Bad input leaves the grid random
+        let stdin_pattern = args.stdin.as_deref().and_then(|text| {
+            match parse_stdin_pattern(text, self.device_limits.max_grid_side()) {
+                Ok(pattern) => Some(pattern),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    self.notifications.notify(err.to_string(), Level::Error);
+                    None
+                }
+            }
+        });
+        if !args.seeds_grid() && stdin_pattern.is_none() {
+            if self.settings.seeding.source != SeedSource::Fixed {
+                self.reseed();
+            }
+            return;
+        }
+
+        self.pipeline.clear();
+        if let Some(pattern) = &stdin_pattern {
+            // Paused so the pattern can be looked at before it changes
+            self.paused = true;
+            self.loaded_pattern = Some(pattern.clone());
+            match place_pattern(&mut self.pipeline, pattern, true, &self.device_limits) {
+                Ok(Some(status)) => {
+                    eprintln!("{}", status);
+                    self.notifications.notify(status, Level::Warning);
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    self.notifications.notify(err.to_string(), Level::Error);
+                }
+            }
+        }
+        if let Some(path) = &args.pattern {
+            self.pattern_path = path.display().to_string();
+            match load_pattern_file(&mut self.pipeline, path, true, &self.device_limits) {
+                Ok((pattern, status)) => {
+                    self.loaded_pattern = Some(pattern);
+                    if let Some(status) = status {
+                        eprintln!("{}", status);
+                        self.notifications.notify(status, Level::Warning);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    self.notifications.notify(err.to_string(), Level::Error);
+                }
+            }
+        }
+
+        let size = self.pipeline.size();
+        for &[x, y] in &args.cells {
+            if x < 0 || y < 0 || x >= size[0] as i64 || y >= size[1] as i64 {
+                let message = format!(
+                    "Skipping cell {},{} outside the {}x{} grid",
+                    x, y, size[0], size[1]
+                );
+                eprintln!("{}", message);
+                self.notifications.notify(message, Level::Warning);
+                continue;
+            }
+            self.pipeline.set_cell([x as u32, y as u32], true);
+        }
+    }
+
+    /// Show the panel with the controls the tutorial points at
+    fn start_tutorial(&mut self) {
+        self.panel_visible = true;
+        if !self
+            .settings
+            .open_sections
+            .contains(&PanelSection::Simulation)
+        {
+            self.settings.open_sections.push(PanelSection::Simulation);
+        }
+        self.tutorial.start();
+    }
+
+    /// Remember the tutorial was seen so it doesn't show on the next start
+    fn complete_tutorial(&mut self) {
+        let mut stored = StoredSettings::load();
+        stored.tutorial_completed = true;
+        if let Err(err) = stored.save() {
+            self.notifications.notify(
+                format!("Failed to save that the tutorial was seen: {}", err),
+                Level::Warning,
+            );
+        }
+    }
+
+    /// Stop recording (if any), giving ffmpeg a chance to finish the file so it isn't left truncated
+    pub fn close(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            if let Err(err) = recorder.finish() {
+                eprintln!("Failed to finish recording: {}", err);
+            }
+        }
+        if let Some(stats_log) = self.stats_log.take() {
+            if let Err(err) = stats_log.finish() {
+                eprintln!("Failed to finish stats log: {}", err);
+            }
+        }
+    }
+
+    pub fn redraw(
+        &mut self,
+        vulkano_context: &VulkanoContext,
+        renderer: &mut VulkanoWindowRenderer,
+        ffmpeg_available: bool,
+    ) {
+        let measured = self.frame_clock.tick(Instant::now());
+        self.frame_time = measured.unwrap_or_default();
+
+        // Nothing to draw into while minimized, the swapchain is recreated once it has a size
+        let window_size = renderer.window().inner_size();
+        if window_size.width == 0 || window_size.height == 0 {
+            return;
+        }
+
+        self.poll_stats();
+        self.poll_history();
+
+        if std::mem::take(&mut self.sprite_sheet_pending) {
+            match self.export_sprite_sheet(vulkano_context) {
+                Ok(()) => self.notifications.notify(
+                    format!("Sprite sheet saved to {}", self.settings.sprite_sheet.path),
+                    Level::Info,
+                ),
+                Err(err) => self.notifications.notify(err, Level::Error),
+            }
+        }
+        if self.png_sequence.is_some() {
+            self.export_png_frames(vulkano_context);
+        }
+
+        // A replayed macro decides the generations, soups are run a sample interval per frame.
+        // Dragging a widget last frame holds the simulation like a pause, without catching up
+        // the generations missed once released.
+        let using_ui = self.settings.pause_while_using_ui && self.gui.context().is_using_pointer();
+        let mut holding = false;
+        let generations = if self.png_sequence.is_some() {
+            0
+        } else if let Some(generations) = self.replay_macro() {
+            generations
+        } else if self.settings.slow_motion && !self.soup_running {
+            self.slow_motion_generations(self.paused || using_ui)
+        } else if self.paused || using_ui {
+            std::mem::take(&mut self.step_requested) as u32
+        } else if self.soup_running {
+            self.step_soup_search();
+            soup::SAMPLE_INTERVAL
+        } else if self.settings.generations_per_second == 0 {
+            self.settings.render_every.max(1)
+        } else {
+            self.accumulator
+                .set_rate(self.settings.generations_per_second as f32);
+            // While recording every rendered frame is one video frame, so the video plays back at
+            // the chosen speed however long the frames took to render
+            let elapsed = if self.recorder.is_some() {
+                Some(Duration::from_secs_f64(
+                    1.0 / self.settings.recording.framerate as f64,
+                ))
+            } else {
+                measured
+            };
+            // Nothing is owed on the first frame, the time before it was spent starting up
+            let due = elapsed.map_or(0, |elapsed| {
+                self.accumulator
+                    .advance(elapsed, self.settings.max_generations_per_frame.max(1))
+            });
+            let released = self.batch_generations(due);
+            holding = released == 0 && self.held_generations > 0;
+            released
+        };
+        // Never run past the target generation, the frame reaching it pauses
+        let current = self.pipeline.generation();
+        let remaining = self
+            .settings
+            .stop_at_generation
+            .filter(|&target| target > current)
+            .map(|target| target - current);
+        let generations = match remaining {
+            Some(remaining) => generations.min(remaining.min(u32::MAX as u64) as u32),
+            None => generations,
+        };
+        let reached_target = generations > 0 && remaining == Some(generations as u64);
+
+        // Frames that hold generations back have nothing new to color, the image still shows
+        // the latest generation
+        self.pipeline.set_color(!holding);
+
+        self.inspect_hovered_cell();
+
+        // Follow the live cells, an empty grid leaves the view where it is
+        if self.settings.track_centroid {
+            let grid_size = self.pipeline.displayed_size();
+            let centroid = self.latest_stats.and_then(|(_, stats)| stats.centroid);
+            if let Some([x, y]) = centroid {
+                let target = self
+                    .displayed_transform()
+                    .centering_offset([x / grid_size[0] as f32, y / grid_size[1] as f32]);
+                let t = 1.0 - (-TRACKING_SPEED * self.frame_time.as_secs_f32()).exp();
+                let offset = &mut self.settings.viewport_transform.offset;
+                for i in 0..2 {
+                    offset[i] += (target[i] - offset[i]) * t;
+                }
+            }
+        }
+
+        // Create immediate ui
+        let mut step_back_clicked = false;
+        let mut show_tutorial_clicked = false;
+        let mut open_sections = self.settings.open_sections.clone();
+        let context = self.gui.context();
+        self.gui.begin_frame();
+        if self.panel_visible {
+            let side = match self.settings.panel_side {
+                PanelSide::Left => egui::panel::Side::Left,
+                PanelSide::Right => egui::panel::Side::Right,
+            };
+            let panel = egui::SidePanel::new(side, "settings_panel")
+                .resizable(true)
+                .min_width(MIN_PANEL_WIDTH)
+                .default_width(self.settings.panel_width)
+                .show(&context, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading("Settings");
+                    });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button("Hide panel")
+                            .on_hover_text(format!(
+                                "Give the whole window to the grid ({})",
+                                self.settings.key_bindings.get(Command::TogglePanel)
+                            ))
+                            .clicked()
+                        {
+                            self.panel_visible = false;
+                        }
+                        let opposite = self.settings.panel_side.opposite();
+                        if ui
+                            .small_button(format!("Dock {}", opposite.name()))
+                            .on_hover_text(
+                                "Move the panel to the other side of the window, drag its \
+                                 edge to resize it",
+                            )
+                            .clicked()
+                        {
+                            self.settings.panel_side = opposite;
+                        }
+                    });
+                    ui.collapsing("Help", |ui| {
+                        show_tutorial_clicked = ui
+                            .button("Show tutorial")
+                            .on_hover_text("Walk through the main controls again")
+                            .clicked();
+                        help_ui(ui, &self.settings.key_bindings);
+                    });
+                    ui.collapsing("Keybindings", |ui| {
+                        keybindings_ui(ui, &mut self.settings.key_bindings, &mut self.rebinding)
+                    });
+                    ui.horizontal(|ui| {
+                        let pause =
+                            ui.toggle_value(&mut self.paused, "Pause")
+                                .on_hover_text(format!(
+                                    "Stop the simulation, the grid can still be edited ({})",
+                                    self.settings.key_bindings.get(Command::TogglePause)
+                                ));
+                        self.tutorial.mark(Target::Pause, pause.rect);
+                        if ui
+                            .add_enabled(!self.history.is_empty(), egui::Button::new("Back"))
+                            .on_hover_text(format!(
+                                "Go back to the previous generation in the history and pause \
+                                 ({})",
+                                self.settings.key_bindings.get(Command::StepBack)
+                            ))
+                            .on_disabled_hover_text("Keep a history below to step back")
+                            .clicked()
+                        {
+                            step_back_clicked = true;
+                        }
+                        let step = ui
+                            .add_enabled(self.paused, egui::Button::new("Step"))
+                            .on_hover_text("Run one generation");
+                        self.tutorial.mark(Target::Step, step.rect);
+                        if step.clicked() {
+                            self.step_requested = true;
+                        }
+                        ui.checkbox(&mut self.settings.pause_while_using_ui, "While adjusting")
+                            .on_hover_text(
+                                "Also pause while a slider or other control is being dragged, \
+                                 resuming on release",
+                            );
+                    });
+                    ui.label(format!("Generation: {}", self.pipeline.generation()))
+                        .on_hover_text("Number of steps simulated so far");
+                    ui.label(format!("FPS: {}", f32::floor(self.fps)));
+                    if self.settings.population_sparkline {
+                        sparkline_ui(ui, &self.population_samples);
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        panel_section(ui, &mut open_sections, PanelSection::Simulation, |ui| {
+                            self.simulation_ui(ui, vulkano_context)
+                        });
+                        panel_section(ui, &mut open_sections, PanelSection::Rendering, |ui| {
+                            self.rendering_ui(ui)
+                        });
+                        panel_section(ui, &mut open_sections, PanelSection::Colors, |ui| {
+                            self.colors_ui(ui)
+                        });
+                        panel_section(ui, &mut open_sections, PanelSection::Patterns, |ui| {
+                            self.patterns_ui(ui)
+                        });
+                        panel_section(ui, &mut open_sections, PanelSection::Recording, |ui| {
+                            self.recording_ui(ui, ffmpeg_available)
+                        });
+                        panel_section(ui, &mut open_sections, PanelSection::Debug, |ui| {
+                            self.debug_ui(ui)
+                        });
+                    });
+
+                    self.counter += 1;
+                    if self.counter == 10 {
+                        self.counter = 0;
+                        self.fps = 1.0 / self.frame_time.max(MIN_FRAME_TIME).as_secs_f32();
+                    }
+                });
+            self.settings.panel_width = panel.response.rect.width();
+            self.settings.open_sections = open_sections;
+        } else if !self.kiosk {
+            let anchor = match self.settings.panel_side {
+                PanelSide::Left => (egui::Align2::LEFT_TOP, [8.0, 8.0]),
+                PanelSide::Right => (egui::Align2::RIGHT_TOP, [-8.0, 8.0]),
+            };
+            egui::Area::new("show_panel")
+                .anchor(anchor.0, anchor.1)
+                .show(&context, |ui| {
+                    if ui
+                        .button("Show panel")
+                        .on_hover_text(format!(
+                            "Bring back the settings ({})",
+                            self.settings.key_bindings.get(Command::TogglePanel)
+                        ))
+                        .clicked()
+                    {
+                        self.panel_visible = true;
+                    }
+                });
+        }
+
+        if step_back_clicked {
+            self.step_back();
+        }
+        if show_tutorial_clicked {
+            self.start_tutorial();
+        }
+
+        // Calculate viewport so as not to render behind egui components. The panel can cover the
+        // whole window, leaving nothing to simulate for.
+        let scale_factor = context.pixels_per_point();
+        let viewport_bounds = calculate_viewport(&context);
+        let viewport_visible = self.update_viewport(viewport_bounds.clone());
+        if self.settings.limit_zoom {
+            let zoom_range = self.zoom_range();
+            let scale = &mut self.settings.viewport_transform.scale;
+            *scale = scale.clamp(*zoom_range.start(), *zoom_range.end());
+        }
+
+        self.draw_region(&context, scale_factor);
+        self.draw_selection(&context, scale_factor);
+        self.draw_stamp_preview(&context, scale_factor);
+        self.draw_line_preview(&context, scale_factor);
+        self.draw_theme_notice(&context);
+        self.notifications.show(&context);
+        if self.tutorial.show(&context, &self.settings.key_bindings) {
+            self.complete_tutorial();
+        }
+        if viewport_visible {
+            self.draw_coordinate_labels(&context, scale_factor);
+        }
+
+        let before_pipeline_future = renderer
+            .acquire()
+            .expect("failed to acquire window renderer future");
+
+        let format = if self.settings.render.hdr && self.pipeline.supports_hdr() {
+            HDR_FORMAT
+        } else {
+            LDR_FORMAT
+        };
+        if self.pipeline.image_format() != format {
+            if let Err(err) = self.pipeline.set_image_format(format) {
+                self.settings.render.hdr = false;
+                self.notifications
+                    .notify(format!("HDR turned off, {}", err), Level::Error);
+            }
+        }
+        if self.pipeline.decimation() != self.settings.render.decimation {
+            if let Err(err) = self
+                .pipeline
+                .set_decimation(self.settings.render.decimation)
+            {
+                self.settings.render.decimation = self.pipeline.decimation();
+                self.notifications
+                    .notify(format!("Decimation left unchanged, {}", err), Level::Error);
+            }
+        }
+
+        // Render viewport
+        self.pipeline.set_collect_stats(
+            self.settings.track_centroid
+                || self.soup_running
+                || self.stats_log.is_some()
+                || self.settings.detect_escape
+                || self.settings.restart_on_extinction
+                || self.settings.measure_growth
+                || self.settings.count_population
+                || self.settings.population_sparkline
+                || self.settings.render.bounding_box,
+        );
+        self.pipeline.set_count_gliders(self.settings.count_gliders);
+        self.pipeline.set_border(self.settings.quarantine_border);
+        self.pipeline.set_wrap(self.settings.wrap_edges);
+        self.pipeline.set_topology(self.settings.topology);
+        self.pipeline.set_kernel_rule(
+            Some(&self.settings.kernel_rule).filter(|_| self.settings.use_kernel_rule),
+        );
+        self.pipeline
+            .set_generations_per_submit(self.settings.generations_per_submit);
+        self.update_effect_buffers();
+        self.pipeline.set_block_rule(
+            Some(self.settings.block_rule).filter(|_| self.settings.use_block_rule),
+        );
+        self.pipeline
+            .set_lenia_rule(Some(&self.settings.lenia).filter(|_| self.settings.use_lenia));
+        // Soups are kept as they were rolled
+        self.pipeline
+            .set_cosmic_rays(self.cosmic_rays().filter(|_| !self.soup_running));
+        // Record whenever this frame's generations cross a multiple of the stride
+        let stride = self.settings.recording.stride as u64;
+        let generation = self.pipeline.generation();
+        let record_frame = viewport_visible
+            && self.recorder.is_some()
+            && (generation + generations as u64) / stride > generation / stride;
+        let take_screenshot = viewport_visible && self.screenshot_pending;
+        self.pipeline
+            .set_capture_frame(record_frame || take_screenshot);
+        self.record_changed_settings();
+        if let (true, Some(recorder)) = (viewport_visible, &mut self.macro_recorder) {
+            if generations > 0 {
+                recorder.push(Action::Generations(generations));
+            }
+        }
+        // Worked out every frame, so time animated colors keep changing while paused
+        let animation = &self.settings.palette_animation;
+        let phase = animation.phase(
+            self.palette_epoch.elapsed().as_secs_f64(),
+            self.pipeline.generation(),
+        );
+        let render_settings = animation.apply(&self.settings.render, phase);
+        let after_compute_future = if viewport_visible {
+            self.pipeline.compute_n(
+                before_pipeline_future,
+                generations,
+                &self.settings.rule,
+                &render_settings,
+            )
+        } else {
+            before_pipeline_future
+        };
+
+        // Render gui
+        let transform = self.displayed_transform();
+        // Slow motion keeps the previous frame, there's none before the first step
+        let morph = self
+            .pipeline
+            .previous_view()
+            .map(|view| (view, self.morph_progress()));
+        let after_gui_future = self.render_pass.render(
+            after_compute_future,
+            renderer.swapchain_image_view(),
+            &mut self.gui,
+            self.pipeline.view(),
+            self.pipeline.bloom_view(),
+            viewport_bounds,
+            transform,
+            &render_settings,
+            self.settings.wrap_edges,
+            // Decimated pixels cover blocks of rows so the row parity is lost
+            self.settings.topology == Topology::Hex && self.settings.render.decimation == 1,
+            self.pipeline.raw_states(),
+            self.latest_stats
+                .and_then(|(_, stats)| stats.bounding_box)
+                .filter(|_| self.settings.render.bounding_box),
+            morph,
+        );
+
+        // Present to surface
+        renderer.present(after_gui_future, true);
+
+        let frame = if record_frame || take_screenshot {
+            self.pipeline.captured_frame()
+        } else {
+            None
+        };
+        if let (true, Some(active), Some(frame)) = (record_frame, &mut self.recorder, &frame) {
+            if let Err(err) = active.write_frame(frame) {
+                self.notifications.notify(err.to_string(), Level::Error);
+                // Still try to close the file so what was recorded stays playable
+                let _ = self.recorder.take().unwrap().finish();
+            }
+        }
+        if let (true, Some(frame)) = (take_screenshot, &frame) {
+            self.screenshot_pending = false;
+            match self.save_screenshot(frame) {
+                Ok(()) => self.notifications.notify(
+                    format!("Screenshot saved to {}", self.settings.screenshot_path),
+                    Level::Info,
+                ),
+                Err(err) => self.notifications.notify(err, Level::Error),
+            }
+        }
+
+        if reached_target {
+            self.reach_target();
+        }
+    }
+
+    /// The rule, speed, grid and seeding controls of the settings panel
+    fn simulation_ui(&mut self, ui: &mut egui::Ui, vulkano_context: &VulkanoContext) {
+        let mut random_rule_clicked = false;
+        let mut orbium_clicked = false;
+        let mut shift_clicked = false;
+        ui.horizontal(|ui| {
+            ui.label("Rule:");
+            let response = ui.text_edit_singleline(&mut self.rule_text).on_hover_text(
+                "Birth/survival rule, B3/S23 means a dead cell with 3 live neighbors \
+                 is born and a live cell with 2 or 3 survives (applied when the field \
+                 loses focus)",
+            );
+            if response.lost_focus() {
+                match self.rule_text.parse::<Rule>() {
+                    Ok(rule) => {
+                        self.settings.rule = rule;
+                        self.rule_error = None;
+                    }
+                    Err(err) => self.rule_error = Some(err),
+                }
+            }
+        });
+        if let Some(err) = &self.rule_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        ui.horizontal(|ui| {
+            random_rule_clicked = ui
+                .button("Random rule")
+                .on_hover_text(format!(
+                    "Switch to a random rule and run it on a freshly seeded \
+                     grid ({})",
+                    self.settings.key_bindings.get(Command::RandomRule)
+                ))
+                .clicked();
+            ui.checkbox(
+                &mut self.settings.interesting_random_rules,
+                "Likely interesting",
+            )
+            .on_hover_text(
+                "Always some births and survivals, and no births on 0 or 1 \
+                 neighbors (they flash or fill the grid)",
+            );
+        });
+        egui::ComboBox::from_label("Topology")
+            .selected_text(self.settings.topology.name())
+            .show_ui(ui, |ui| {
+                for topology in Topology::ALL {
+                    ui.selectable_value(&mut self.settings.topology, topology, topology.name());
+                }
+            })
+            .response
+            .on_hover_text(
+                "Square cells have 8 neighbors, hexagonal cells 6 (the rule \
+                 counts only go up to 6)",
+            );
+        if self.settings.topology == Topology::Hex {
+            ui.horizontal(|ui| {
+                for (name, preset) in rule::HEX_PRESETS {
+                    if ui.button(name).on_hover_text(preset.to_string()).clicked() {
+                        self.settings.rule = preset;
+                        self.rule_text = preset.to_string();
+                        self.rule_error = None;
+                    }
+                }
+            });
+        }
+        ui.checkbox(&mut self.settings.use_kernel_rule, "Weighted kernel")
+            .on_hover_text(
+                "Replace the rule with a weighted sum over a larger square \
+                 neighborhood (Larger than Life), the topology is ignored",
+            );
+        if self.settings.use_kernel_rule {
+            kernel_rule_ui(
+                ui,
+                &mut self.settings.kernel_rule,
+                &mut self.settings.kernel_toggles,
+            );
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.use_block_rule, "Block rule")
+                .on_hover_text(
+                    "Replace the rule with a block rule on the Margolus \
+                     neighborhood: 2x2 blocks shifted diagonally every other \
+                     generation, each replaced as a whole (needs even grid sides \
+                     to wrap cleanly)",
+                );
+            ui.add_enabled_ui(self.settings.use_block_rule, |ui| {
+                egui::ComboBox::from_id_source("block_rule")
+                    .selected_text(self.settings.block_rule.name())
+                    .show_ui(ui, |ui| {
+                        for rule in BlockRule::ALL {
+                            ui.selectable_value(&mut self.settings.block_rule, rule, rule.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "HPP is a lattice gas of particles bouncing around, \
+                         try it on a dense blob in an empty grid",
+                    );
+            });
+        });
+        ui.checkbox(&mut self.settings.use_lenia, "Lenia")
+            .on_hover_text(
+                "Experimental: replace every other rule with Lenia, a continuous \
+                 automaton where cells hold values between 0 and 1. Slow on \
+                 large grids, each cell averages hundreds of neighbors",
+            );
+        if self.settings.use_lenia {
+            orbium_clicked = lenia_rule_ui(ui, &mut self.settings.lenia);
+        }
+        ui.checkbox(&mut self.settings.wrap_edges, "Wrap edges")
+            .on_hover_text(
+                "Connect opposite edges like a torus, the view repeats to match. \
+                 Off treats everything past the edges as dead",
+            );
+        ui.horizontal(|ui| {
+            ui.label("Quarantine border:");
+            ui.add(egui::Slider::new(
+                &mut self.settings.quarantine_border,
+                0..=64,
+            ));
+        })
+        .response
+        .on_hover_text(
+            "Keep a band of cells along the grid edges dead so gliders can't \
+             wrap around and fill the grid with noise, 0 to wrap",
+        );
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Max FPS:");
+            ui.add(egui::Slider::new(&mut self.settings.max_fps, 0..=240));
+        })
+        .response
+        .on_hover_text("0 for unlimited");
+        ui.horizontal(|ui| {
+            ui.label("Speed:");
+            ui.add(
+                egui::Slider::new(&mut self.settings.generations_per_second, 0..=1000)
+                    .logarithmic(true)
+                    .suffix(" gen/s"),
+            );
+        })
+        .response
+        .on_hover_text("Generations per second, 0 for one per frame");
+        ui.add_enabled_ui(self.settings.generations_per_second > 0, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Max catch-up:");
+                ui.add(
+                    egui::Slider::new(&mut self.settings.max_generations_per_frame, 1..=10_000)
+                        .logarithmic(true)
+                        .suffix(" gen/frame"),
+                );
+            })
+            .response
+            .on_hover_text(
+                "Most generations run in one frame, so a stall or a slow frame \
+                 makes the simulation fall behind instead of freezing the app \
+                 while it catches up",
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Render every:");
+            ui.add(
+                egui::Slider::new(&mut self.settings.render_every, 1..=1000)
+                    .logarithmic(true)
+                    .suffix(" gen"),
+            );
+        })
+        .response
+        .on_hover_text(
+            "Simulate this many generations before coloring the grid, saving \
+             the coloring of the ones in between. At one generation per frame \
+             this many run each frame",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Per submit:");
+            ui.add(
+                egui::Slider::new(&mut self.settings.generations_per_submit, 0..=1000)
+                    .logarithmic(true)
+                    .suffix(" gen"),
+            );
+        })
+        .response
+        .on_hover_text(
+            "Most generations recorded into one GPU submission, 0 submits all \
+             of a frame's generations at once. Lower it if big batches make \
+             the desktop stutter",
+        );
+        stop_at_ui(
+            ui,
+            &mut self.settings.stop_at_generation,
+            &mut self.settings.stop_recording_at_target,
+            self.pipeline.generation(),
+        );
+        if slow_motion_ui(
+            ui,
+            &mut self.settings.slow_motion,
+            &mut self.settings.morph_duration,
+        ) {
+            self.paused = true;
+            self.step_requested = true;
+        }
+        history_ui(
+            ui,
+            &mut self.settings.history,
+            &self.history,
+            self.pipeline.size(),
+        );
+        ui.separator();
+        if ui
+            .button("Reset")
+            .on_hover_text("Fill the grid randomly and start over from generation 0")
+            .clicked()
+        {
+            match GameComputePipeline::new(
+                vulkano_context,
+                self.pipeline.size(),
+                self.pipeline.image_format(),
+            ) {
+                Ok(pipeline) => {
+                    self.pipeline = pipeline;
+                    self.escape_generation = None;
+                }
+                Err(err) => self.notifications.notify(err.to_string(), Level::Error),
+            }
+        }
+        let mut resize_clicked = resize_ui(
+            ui,
+            &mut self.resize_to,
+            self.pipeline.size(),
+            &mut self.settings,
+        );
+        if let Some(suggestion) = self.grid_suggestion {
+            if grid_suggestion_ui(ui, suggestion, self.pipeline.size()) {
+                self.resize_to = Some([suggestion.side; 2]);
+                resize_clicked = true;
+            }
+        }
+        let seeding = ui.scope(|ui| seeding_ui(ui, &mut self.settings.seeding));
+        self.tutorial.mark(Target::Randomize, seeding.response.rect);
+        let randomize_clicked = seeding.inner;
+        let randomize_visible_clicked = ui
+            .button("Randomize visible")
+            .on_hover_text(
+                "Fill only the cells in view from the seed, the rest of the grid \
+                 and the generation stay as they are",
+            )
+            .clicked();
+        ui.checkbox(
+            &mut self.settings.restart_on_extinction,
+            "Restart when extinct",
+        )
+        .on_hover_text(
+            "Fill the grid from the seed again once every cell has died, with \
+             a new seed unless it's fixed",
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.cosmic_rays, "Cosmic rays")
+                .on_hover_text(
+                    "Flip random cells every generation, so the grid keeps \
+                     changing instead of settling into still lifes and oscillators",
+                );
+            ui.add_enabled(
+                self.settings.cosmic_rays,
+                egui::Slider::new(&mut self.settings.cosmic_ray_rate, 0.01..=100.0)
+                    .logarithmic(true)
+                    .text("per generation"),
+            )
+            .on_hover_text(
+                "Cells flipped each generation on average, picked from the grid \
+                 seed and generation so the same seed gets the same rays",
+            );
+        });
+        ui.horizontal(|ui| {
+            shift_clicked = ui
+                .button("Shift grid")
+                .on_hover_text(format!(
+                    "Roll every cell this many cells right and down, wrapping \
+                     around the edges. The shift keys ({} and the others) roll \
+                     it by the step",
+                    self.settings.key_bindings.get(Command::ShiftRight)
+                ))
+                .clicked();
+            ui.add(egui::DragValue::new(&mut self.settings.shift_offset[0]))
+                .on_hover_text("Cells right, negative for left");
+            ui.add(egui::DragValue::new(&mut self.settings.shift_offset[1]))
+                .on_hover_text("Cells down, negative for up");
+            ui.label("Step:");
+            ui.add(egui::DragValue::new(&mut self.settings.shift_step).clamp_range(1..=1024))
+                .on_hover_text("Cells each press of a shift key rolls the grid");
+        });
+        if randomize_clicked {
+            self.reseed();
+        }
+        if randomize_visible_clicked {
+            self.randomize_visible();
+        }
+        if random_rule_clicked {
+            self.random_rule();
+        }
+        if resize_clicked {
+            self.resize_grid();
+        }
+        if orbium_clicked {
+            self.load_orbium();
+        }
+        if shift_clicked {
+            self.perform(Action::Shift(self.settings.shift_offset));
+        }
+    }
+
+    /// The view, render mode and effect controls of the settings panel
+    fn rendering_ui(&mut self, ui: &mut egui::Ui) {
+        let mut copy_view_clicked = false;
+        let mut paste_view_clicked = false;
+        let zoom_range = self.zoom_range();
+        let fit_scale = self.fit_scale();
+        let aspect_ratio = self.displayed_transform().aspect_ratio;
+        ui.horizontal(|ui| {
+            ui.label("Offset X:");
+            ui.add_enabled(
+                !self.settings.track_centroid,
+                egui::Slider::new(
+                    &mut self.settings.viewport_transform.offset[0],
+                    -10.0..=10.0,
+                ),
+            );
+        })
+        .response
+        .on_hover_text("Horizontal position of the grid in the viewport");
+
+        ui.horizontal(|ui| {
+            ui.label("Offset Y:");
+            ui.add_enabled(
+                !self.settings.track_centroid,
+                egui::Slider::new(
+                    &mut self.settings.viewport_transform.offset[1],
+                    -10.0..=10.0,
+                ),
+            );
+        })
+        .response
+        .on_hover_text("Vertical position of the grid in the viewport");
+
+        ui.horizontal(|ui| {
+            ui.label("Scale:");
+            ui.add(
+                egui::Slider::new(
+                    &mut self.settings.viewport_transform.scale,
+                    zoom_range.clone(),
+                )
+                .logarithmic(true),
+            );
+            if ui
+                .button("Fit")
+                .on_hover_text("Zoom out until the whole grid is in view")
+                .clicked()
+            {
+                self.settings.viewport_transform.scale = fit_scale;
+                self.settings.viewport_transform.offset = [0.0, 0.0];
+            }
+        })
+        .response
+        .on_hover_text("Zoom, at 1.0 the grid fills the width of the viewport");
+        ui.checkbox(&mut self.settings.limit_zoom, "Limit zoom")
+            .on_hover_text(
+                "Keep the zoom between fitting the whole grid in view and cells \
+                 64 pixels wide",
+            );
+        ui.collapsing("Exact view", |ui| {
+            let transform = &mut self.settings.viewport_transform;
+            ui.horizontal(|ui| {
+                ui.label("Offset:");
+                for axis in 0..2 {
+                    ui.add_enabled(
+                        !self.settings.track_centroid,
+                        egui::DragValue::new(&mut transform.offset[axis])
+                            .speed(0.001)
+                            .max_decimals(6),
+                    );
+                }
+            })
+            .response
+            .on_hover_text("Offset of the grid in the viewport, X and Y");
+            ui.horizontal(|ui| {
+                ui.label("Scale:");
+                ui.add(
+                    egui::DragValue::new(&mut transform.scale)
+                        .clamp_range(zoom_range.clone())
+                        .speed(0.001)
+                        .max_decimals(6),
+                );
+                ui.label(format!("Aspect ratio: {:.4}", aspect_ratio));
+            })
+            .response
+            .on_hover_text(
+                "Exact zoom. The aspect ratio follows the viewport and isn't \
+                 set by hand",
+            );
+            ui.horizontal(|ui| {
+                copy_view_clicked = ui
+                    .button("Copy view")
+                    .on_hover_text(
+                        "Copy the offset, scale and aspect ratio to the \
+                         clipboard as JSON",
+                    )
+                    .clicked();
+                paste_view_clicked = ui
+                    .button("Paste view")
+                    .on_hover_text(
+                        "Move to the view copied to the clipboard, keeping \
+                         this viewport's aspect ratio",
+                    )
+                    .clicked();
+            });
+        });
+        ui.checkbox(&mut self.settings.snap_to_cells, "Snap to cell grid")
+            .on_hover_text(
+                "Line cells up with screen pixels and pan in whole cells, \
+                 while the zoom is a whole number of pixels per cell",
+            );
+        ui.checkbox(&mut self.settings.track_centroid, "Track")
+            .on_hover_text("Keep the view centered on the live cells");
+        ui.checkbox(&mut self.settings.coordinate_labels, "Coordinates")
+            .on_hover_text(
+                "Number the rows and columns along the edges of the view when \
+                 zoomed in far enough to see the cells",
+            );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.render.crosshair, "Center mark")
+                .on_hover_text(format!(
+                    "Cross at the grid center, the corner of cell {}, {}",
+                    self.pipeline.size()[0] / 2,
+                    self.pipeline.size()[1] / 2
+                ));
+            ui.checkbox(&mut self.settings.render.axes, "Axes")
+                .on_hover_text("Lines through the grid center across the grid");
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.crosshair_color)
+                .on_hover_text("Marker color, its alpha sets the opacity");
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.render.bounding_box, "Bounding box")
+                .on_hover_text(
+                    "Outline the live cells, a generation or two behind since \
+                     it's read back without waiting",
+                );
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.bounding_box_color)
+                .on_hover_text("Outline color, its alpha sets the opacity");
+            let bounding_box = self
+                .latest_stats
+                .filter(|_| self.settings.render.bounding_box)
+                .map(|(_, stats)| stats.bounding_box);
+            match bounding_box {
+                Some(Some((min, max))) => {
+                    ui.label(format!("{}x{}", max[0] - min[0] + 1, max[1] - min[1] + 1))
+                        .on_hover_text(format!(
+                            "From {}, {} to {}, {}",
+                            min[0], min[1], max[0], max[1]
+                        ));
+                }
+                Some(None) => {
+                    ui.label("Empty");
+                }
+                None => {}
+            }
+        });
+        ui.separator();
+        egui::ComboBox::from_label("Render mode")
+            .selected_text(self.settings.render.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in RenderMode::ALL {
+                    ui.selectable_value(&mut self.settings.render.mode, mode, mode.name());
+                }
+            })
+            .response
+            .on_hover_text(
+                "Cells colors each cell by its state, density map colors it by how \
+                 crowded its neighborhood is, diff by how it changed in the last step \
+                 (pause and step through generations to compare them), raw buffer \
+                 shows the cell values as they are in black and white",
+            );
+
+        if self.settings.render.mode == RenderMode::Diff {
+            diff_legend_ui(ui, &mut self.settings.render);
+        }
+        let mode = self.settings.render.mode;
+        if mode == RenderMode::Cells || mode == RenderMode::Raw {
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut self.settings.render.highlight_changes,
+                    "Highlight changes",
+                )
+                .on_hover_text("Tint cells that were born or died, fading out");
+                ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.highlight_color)
+                    .on_hover_text("Highlight color");
+            });
+        }
+        if mode == RenderMode::Cells {
+            ui.checkbox(
+                &mut self.settings.render.fragment_coloring,
+                "Color in final pass",
+            )
+            .on_hover_text(
+                "Color the cells while drawing them instead of in the compute \
+                 pass, so color changes show without recomputing. Not used with \
+                 trails, bloom or anti-flicker",
+            );
+        }
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Cell shape")
+                .selected_text(self.settings.render.cell_shape.name())
+                .show_ui(ui, |ui| {
+                    for shape in CellShape::ALL {
+                        ui.selectable_value(
+                            &mut self.settings.render.cell_shape,
+                            shape,
+                            shape.name(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("Shape of each cell when zoomed in");
+            ui.add_enabled(
+                self.settings.render.cell_shape != CellShape::Pixel,
+                egui::Checkbox::new(&mut self.settings.render.antialias_cells, "Antialias"),
+            )
+            .on_hover_text("Smooth the edges of shaped cells");
+        });
+        ui.add_enabled_ui(self.settings.render.cell_shape != CellShape::Pixel, |ui| {
+            bevel_ui(ui, &mut self.settings.render)
+        });
+
+        ui.add_enabled(
+            self.pipeline.supports_hdr(),
+            egui::Checkbox::new(&mut self.settings.render.hdr, "HDR output"),
+        )
+        .on_hover_text("Color in 16 bit float, tone mapped for display")
+        .on_disabled_hover_text("This GPU can't write 16 bit float images from a compute shader");
+        channel_mask_ui(ui, &mut self.settings.render);
+        swapchain_images_ui(ui, &mut self.settings.swapchain_images, self.surface_images);
+        ui.checkbox(&mut self.settings.render.bloom, "Bloom")
+            .on_hover_text("Make bright cells glow, looks best with HDR output");
+        if self.settings.render.bloom {
+            ui.horizontal(|ui| {
+                ui.label("Threshold:");
+                ui.add(egui::Slider::new(
+                    &mut self.settings.render.bloom_threshold,
+                    0.0..=1.0,
+                ));
+            })
+            .response
+            .on_hover_text("Brightness a color must exceed to glow");
+            ui.horizontal(|ui| {
+                ui.label("Intensity:");
+                ui.add(egui::Slider::new(
+                    &mut self.settings.render.bloom_intensity,
+                    0.0..=4.0,
+                ));
+            })
+            .response
+            .on_hover_text("Strength of the glow added on top");
+        }
+        vignette_ui(ui, &mut self.settings.render);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.render.trails, "Trails")
+                .on_hover_text(
+                    "Leave a fading trail where cells have been alive, static \
+                     patterns glow steadily",
+                );
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.trail_color)
+                .on_hover_text("Trail color, its alpha scales the trail brightness");
+            if ui
+                .add_enabled(
+                    self.settings.render.trails,
+                    egui::Button::new("Clear trails"),
+                )
+                .on_hover_text("Forget the trails drawn so far")
+                .clicked()
+            {
+                self.pipeline.clear_trails();
+            }
+        });
+        if self.settings.render.trails {
+            ui.horizontal(|ui| {
+                ui.label("Fade:");
+                ui.add(
+                    egui::Slider::new(&mut self.settings.render.trail_fade, 0.001..=0.5)
+                        .logarithmic(true),
+                );
+            })
+            .response
+            .on_hover_text("Fraction of a trail lost each generation");
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.render.temporal_filter, "Anti-flicker")
+                .on_hover_text(
+                    "Blend each frame with the previous ones while zoomed out far \
+                     enough that cells are smaller than a pixel, off again once \
+                     zoomed in",
+                );
+            ui.add_enabled(
+                self.settings.render.temporal_filter,
+                egui::Slider::new(&mut self.settings.render.temporal_blend, 0.0..=0.95),
+            )
+            .on_hover_text(
+                "Weight of the previous frames, higher is steadier but smears \
+                 moving patterns",
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Decimation:");
+            ui.add(egui::Slider::new(
+                &mut self.settings.render.decimation,
+                1..=32,
+            ));
+        })
+        .response
+        .on_hover_text(
+            "Show one pixel per NxN block of cells (alive if any cell is) to preview \
+             huge grids, the simulation still runs on every cell",
+        );
+
+        if self.settings.render.mode == RenderMode::Density {
+            ui.horizontal(|ui| {
+                ui.label("Window:");
+                ui.add(egui::Slider::new(
+                    &mut self.settings.render.density_window,
+                    2..=64,
+                ));
+            })
+            .response
+            .on_hover_text("Side length of the square averaged around each cell");
+            ui.label("Palette:")
+                .on_hover_text("Colors from empty (left) to full (right)");
+            gradient_ui(ui, &mut self.settings.render.density_palette);
+        }
+
+        if copy_view_clicked {
+            self.copy_view();
+        }
+        if paste_view_clicked {
+            self.paste_view();
+        }
+    }
+
+    /// The color controls of the settings panel
+    fn colors_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Colors:");
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.life_color)
+                .on_hover_text("Live cells");
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.dead_color)
+                .on_hover_text("Dead cells");
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.grid_color)
+                .on_hover_text("Gaps between shaped cells");
+            egui::ComboBox::from_id_source("theme")
+                .selected_text("Theme")
+                .show_ui(ui, |ui| {
+                    for theme in &THEMES {
+                        if ui.selectable_label(false, theme.name).clicked() {
+                            theme.apply(&mut self.settings.render);
+                        }
+                    }
+                })
+                .response
+                .on_hover_text(format!(
+                    "Apply a preset color theme ({} cycles them)",
+                    self.settings.key_bindings.get(Command::CycleTheme)
+                ));
+        });
+        palette_animation_ui(ui, &mut self.settings.palette_animation);
+    }
+
+    /// The pattern, brush and selection controls of the settings panel
+    fn patterns_ui(&mut self, ui: &mut egui::Ui) {
+        let mut brush_clicked = None;
+        let mut stamp_command = None;
+        let mut copy_clicked = false;
+        ui.horizontal(|ui| {
+            ui.label("Pattern file:");
+            ui.text_edit_singleline(&mut self.pattern_path);
+        })
+        .response
+        .on_hover_text("Path to an .rle or .cells (plaintext) pattern");
+        ui.checkbox(&mut self.resize_to_fit, "Resize grid to fit pattern")
+            .on_hover_text("Grow the grid if the pattern is too big, otherwise clip it");
+        if ui
+            .button("Load pattern")
+            .on_hover_text("Clear the grid and place the pattern in the center")
+            .clicked()
+        {
+            match load_pattern_file(
+                &mut self.pipeline,
+                Path::new(&self.pattern_path),
+                self.resize_to_fit,
+                &self.device_limits,
+            ) {
+                Ok((pattern, status)) => {
+                    self.loaded_pattern = Some(pattern);
+                    self.notifications.notify(
+                        status.unwrap_or_else(|| "Pattern loaded".to_string()),
+                        Level::Info,
+                    );
+                }
+                Err(err) => self.notifications.notify(err.to_string(), Level::Error),
+            }
+        }
+        let conway = self.settings.rule == Rule::CONWAY
+            && self.settings.topology == Topology::Square
+            && !self.settings.use_kernel_rule
+            && !self.settings.use_block_rule
+            && !self.settings.use_lenia;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(conway, egui::Button::new("Identify pattern"))
+                .on_hover_text(
+                    "Look up the live cells in a small catalog of named \
+                     patterns (block, blinker, toad, beacon, pulsar, \
+                     glider) in any phase, position and orientation",
+                )
+                .on_disabled_hover_text(
+                    "The catalog is for Conway's rule (B3/S23) on the \
+                     square grid",
+                )
+                .clicked()
+            {
+                let [width, height] = self.pipeline.size();
+                let grid = Pattern::new([width, height], self.pipeline.snapshot());
+                self.identified = Some(match catalog::recognize(&grid) {
+                    Some(found) if found.period == 1 => {
+                        format!("{} ({})", found.name, found.kind.name())
+                    }
+                    Some(found) => format!(
+                        "{} ({}, period {})",
+                        found.name,
+                        found.kind.name(),
+                        found.period
+                    ),
+                    None => "Unknown pattern".to_string(),
+                });
+            }
+            if let Some(identified) = &self.identified {
+                ui.label(identified);
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button("Import image")
+                .on_hover_text(
+                    "Resize the grid to a PNG or JPEG image and make its bright \
+                     pixels alive",
+                )
+                .clicked()
+            {
+                if let Err(err) = import_image(
+                    &mut self.pipeline,
+                    Path::new(&self.pattern_path),
+                    self.image_threshold,
+                ) {
+                    self.notifications.notify(err.to_string(), Level::Error);
+                }
+            }
+            ui.add(egui::Slider::new(&mut self.image_threshold, 0.0..=1.0).text("Threshold"))
+                .on_hover_text("Pixels brighter than this start alive");
+        });
+        let paste_clicked = ui
+            .button("Paste RLE")
+            .on_hover_text(format!(
+                "Paste a pattern from the clipboard ({})",
+                self.settings.key_bindings.get(Command::Paste)
+            ))
+            .clicked();
+        if self.stamp.is_some() {
+            ui.checkbox(&mut self.tile_stamp, "Tile").on_hover_text(
+                "Drag a rectangle to fill with copies of the pattern, each \
+                 clipped to its tile and the rectangle",
+            );
+            ui.add_enabled_ui(self.tile_stamp, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Spacing:");
+                    for spacing in &mut self.settings.tile_spacing {
+                        ui.add(egui::DragValue::new(spacing).clamp_range(1..=1024));
+                    }
+                })
+                .response
+                .on_hover_text("Cells from one copy to the next, in X and Y");
+            });
+            ui.label(format!(
+                "{} the pattern, {} to cancel",
+                if self.tile_stamp {
+                    "Drag to tile"
+                } else {
+                    "Click to place"
+                },
+                self.settings.key_bindings.get(Command::Cancel)
+            ));
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.drawing_region, "Draw sim region")
+                .on_hover_text("Drag a rectangle, cells outside it stay frozen");
+            let has_region = self.pipeline.region().is_some();
+            if ui
+                .add_enabled(has_region, egui::Button::new("Clear region"))
+                .clicked()
+            {
+                self.pipeline.set_region(None);
+            }
+        });
+        if let Some((min, max)) = self.pipeline.region() {
+            ui.label(format!(
+                "Region: ({}, {}) to ({}, {})",
+                min[0], min[1], max[0], max[1]
+            ));
+        }
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.painting_walls, "Paint walls")
+                .on_hover_text(
+                    "Drawn lines make walls instead of live cells (shift erases \
+                     them). Walls keep their state: live ones are obstacles, \
+                     dead ones barriers",
+                );
+            ui.color_edit_button_rgba_unmultiplied(&mut self.settings.render.wall_color)
+                .on_hover_text("Wall color");
+            if ui
+                .add_enabled(self.pipeline.has_walls(), egui::Button::new("Clear walls"))
+                .on_hover_text("Turn every wall back into a normal cell")
+                .clicked()
+            {
+                self.pipeline.clear_walls();
+            }
+        });
+        let radius = self.settings.brush.radius;
+        if brush_ui(ui, &mut self.settings.brush) {
+            self.brush_rng = StdRng::seed_from_u64(self.settings.brush.seed);
+        }
+        // Shapes are rebuilt at the new size
+        if self.brush.is_shape() && self.settings.brush.radius != radius {
+            brush_clicked = Some(self.brush);
+        }
+        brush_clicked =
+            brush_palette_ui(ui, self.brush, self.loaded_pattern.is_some()).or(brush_clicked);
+        if self.stamp.is_some() {
+            stamp_command = stamp_orientation_ui(ui, &self.settings.key_bindings);
+        }
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.selecting, "Select")
+                .on_hover_text("Drag a rectangle of cells to copy");
+            if ui
+                .add_enabled(self.selection.is_some(), egui::Button::new("Copy RLE"))
+                .on_hover_text(format!(
+                    "Copy the selected cells to the clipboard as RLE ({}), \
+                     paste them back with {}",
+                    self.settings.key_bindings.get(Command::Copy),
+                    self.settings.key_bindings.get(Command::Paste)
+                ))
+                .clicked()
+            {
+                copy_clicked = true;
+            }
+            if ui
+                .add_enabled(self.selection.is_some(), egui::Button::new("Deselect"))
+                .clicked()
+            {
+                self.selection = None;
+            }
+        });
+
+        ui.separator();
+        ui.collapsing("Soup search", |ui| {
+            soup_search_ui(
+                ui,
+                &mut self.settings.soup,
+                &mut self.soup_search,
+                &mut self.soup_running,
+                &mut self.soup_seed,
+                &mut self.notifications,
+                &mut self.pipeline,
+            );
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Session file:");
+            ui.text_edit_singleline(&mut self.session_path);
+        })
+        .response
+        .on_hover_text("A session stores the settings, generation and grid");
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save session")
+                .on_hover_text("Write the current session to the file")
+                .clicked()
+            {
+                match save_session(
+                    Path::new(&self.session_path),
+                    &self.settings,
+                    &self.pipeline,
+                ) {
+                    Ok(()) => self.notifications.notify("Session saved", Level::Info),
+                    Err(err) => self.notifications.notify(err.to_string(), Level::Error),
+                }
+            }
+            if ui
+                .button("Load session")
+                .on_hover_text("Replace the settings and grid with the saved ones")
+                .clicked()
+            {
+                match load_session(
+                    Path::new(&self.session_path),
+                    &mut self.settings,
+                    &mut self.pipeline,
+                ) {
+                    Ok(()) => {
+                        self.rule_text = self.settings.rule.to_string();
+                        self.rule_error = None;
+                        self.notifications.notify("Session loaded", Level::Info);
+                    }
+                    Err(err) => self.notifications.notify(err.to_string(), Level::Error),
+                }
+            }
+        });
+
+        ui.separator();
+        if preset_ui(
+            ui,
+            &mut self.preset_path,
+            &self.builtin_presets,
+            &mut self.settings,
+            &mut self.notifications,
+        ) {
+            self.rule_text = self.settings.rule.to_string();
+            self.rule_error = None;
+        }
+
+        if paste_clicked {
+            self.paste();
+        }
+        if let Some(brush) = brush_clicked {
+            self.select_brush(brush);
+        }
+        if let Some(command) = stamp_command {
+            self.run_command(command);
+        }
+        if copy_clicked {
+            self.copy_selection();
+        }
+    }
+
+    /// The video, image sequence and macro controls of the settings panel
+    fn recording_ui(&mut self, ui: &mut egui::Ui, ffmpeg_available: bool) {
+        ui.add_enabled_ui(ffmpeg_available && self.recorder.is_none(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Video file:");
+                ui.text_edit_singleline(&mut self.settings.recording.path);
+            })
+            .response
+            .on_hover_text("Where the MP4 is written, overwritten if it exists");
+            ui.horizontal(|ui| {
+                ui.label("Framerate:");
+                ui.add(egui::Slider::new(
+                    &mut self.settings.recording.framerate,
+                    1..=60,
+                ));
+            })
+            .response
+            .on_hover_text("Playback frames per second of the video");
+            ui.horizontal(|ui| {
+                ui.label("Stride:");
+                ui.add(egui::Slider::new(
+                    &mut self.settings.recording.stride,
+                    1..=100,
+                ));
+            })
+            .response
+            .on_hover_text("Record every Nth generation");
+        });
+        let record_label = if self.recorder.is_some() {
+            "Stop recording"
+        } else {
+            "Record MP4"
+        };
+        let record_button = ui
+            .add_enabled(ffmpeg_available, egui::Button::new(record_label))
+            .on_hover_text("Record the colored grid (not the UI) to a video")
+            .on_disabled_hover_text("Recording needs ffmpeg, which wasn't found on PATH");
+        if record_button.clicked() {
+            let result = match self.recorder.take() {
+                Some(recorder) => recorder.finish().map(|()| "Recording saved"),
+                None => FfmpegRecorder::start(
+                    Path::new(&self.settings.recording.path),
+                    self.pipeline.image_size(),
+                    self.settings.recording.framerate,
+                )
+                .map(|started| {
+                    self.recorder = Some(started);
+                    "Recording started"
+                }),
+            };
+            match result {
+                Ok(message) => self.notifications.notify(message, Level::Info),
+                Err(err) => self.notifications.notify(err.to_string(), Level::Error),
+            }
+        }
+
+        ui.separator();
+        sprite_sheet_ui(
+            ui,
+            &mut self.settings.sprite_sheet,
+            &mut self.sprite_sheet_pending,
+        );
+        ui.separator();
+        let png_sequence_clicked = png_sequence_ui(
+            ui,
+            &mut self.settings.png_sequence,
+            self.png_sequence.as_ref().map(PngSequence::progress),
+        );
+
+        ui.separator();
+        let start_macro_recording = macro_ui(
+            ui,
+            &mut self.settings.macro_path,
+            &mut self.macro_recorder,
+            &mut self.macro_player,
+            &mut self.notifications,
+        );
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Screenshot:");
+            ui.text_edit_singleline(&mut self.settings.screenshot_path);
+        })
+        .response
+        .on_hover_text("PNG file screenshots are saved to");
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save screenshot")
+                .on_hover_text("Save the next frame of the grid, without the panel")
+                .clicked()
+            {
+                self.screenshot_pending = true;
+            }
+            ui.checkbox(&mut self.settings.pixel_perfect, "Pixel perfect")
+                .on_hover_text(
+                    "Only save the visible cells, each as a whole number of \
+                     pixels (the zoom closest to the view) so they stay crisp. \
+                     Off saves the whole grid at one pixel per cell",
+                );
+        });
+
+        ui.separator();
+        ui.add_enabled_ui(self.stats_log.is_none(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Stats file:");
+                ui.text_edit_singleline(&mut self.settings.stats_log_path);
+            })
+            .response
+            .on_hover_text("CSV file the stats are appended to");
+        });
+        let mut logging = self.stats_log.is_some();
+        if ui
+            .checkbox(&mut logging, "Log to CSV")
+            .on_hover_text(
+                "Append the population, bounding box size and state (active, \
+                 stable or extinct) of every rendered generation",
+            )
+            .changed()
+        {
+            let result = if logging {
+                StatsLogger::start(Path::new(&self.settings.stats_log_path))
+                    .map(|started| self.stats_log = Some(started))
+            } else {
+                self.stats_log.take().map_or(Ok(()), StatsLogger::finish)
+            };
+            if let Err(err) = result {
+                self.notifications.notify(err.to_string(), Level::Error);
+            }
+        }
+
+        if start_macro_recording {
+            self.start_macro_recording();
+        }
+        if png_sequence_clicked {
+            match self.png_sequence.take() {
+                Some(sequence) => self.finish_png_sequence(sequence, Ok(())),
+                None => self.start_png_sequence(),
+            }
+        }
+    }
+
+    /// The benchmark, statistics and device controls of the settings panel
+    fn debug_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.settings.render.debug_tiles, "Workgroup tiles")
+            .on_hover_text(
+                "Outline the 8x8 blocks of cells each compute workgroup \
+                 simulates",
+            );
+        if benchmark_ui(
+            ui,
+            &mut self.settings.benchmark_generations,
+            self.grid_benchmark,
+        ) {
+            let population = self
+                .pipeline
+                .snapshot()
+                .iter()
+                .filter(|&&cell| cell != 0)
+                .count() as u64;
+            let per_generation = self.pipeline.benchmark(
+                self.settings.benchmark_generations,
+                &self.settings.rule,
+                &self.settings.render,
+            );
+            self.grid_benchmark = Some((population, per_generation));
+        }
+        ui.checkbox(&mut self.inspect_cells, "Inspect cells")
+            .on_hover_text("Show the state of the cell under the cursor");
+        if self.inspect_cells {
+            match self.inspected {
+                Some(([x, y], info)) => {
+                    ui.label(format!(
+                        "Cell {}, {}: {}, {} live neighbors",
+                        x,
+                        y,
+                        if info.alive { "alive" } else { "dead" },
+                        info.live_neighbors
+                    ));
+                }
+                None => {
+                    ui.label("Hover over a cell");
+                }
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.count_population, "Count population")
+                .on_hover_text(
+                    "Number of live cells, read back from the GPU without \
+                     waiting so it lags a frame or two behind",
+                );
+            if let (true, Some((_, stats))) = (self.settings.count_population, self.latest_stats) {
+                ui.label(stats.population.to_string());
+            }
+        });
+        ui.checkbox(
+            &mut self.settings.population_sparkline,
+            "Population sparkline",
+        )
+        .on_hover_text(
+            "Plot the live cells of recent generations under the frame \
+             rate, read back without waiting like the population count",
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.count_gliders, "Count gliders")
+                .on_hover_text("Approximate, only isolated gliders are counted");
+            if let Some(gliders) = self.pipeline.glider_count() {
+                ui.label(gliders.to_string());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.detect_escape, "Detect escapes")
+                .on_hover_text(
+                    "Watch for live cells coming within the margin of an edge, \
+                     usually a spaceship escaping. Checked once per frame, so \
+                     the generation is the last one of the frame",
+                );
+            ui.add(
+                egui::DragValue::new(&mut self.settings.escape_margin)
+                    .clamp_range(0..=1024)
+                    .suffix(" cells"),
+            )
+            .on_hover_text("Distance from the edges that counts as escaping");
+        });
+        ui.add_enabled_ui(self.settings.detect_escape, |ui| {
+            ui.checkbox(&mut self.settings.pause_on_escape, "Pause on escape")
+                .on_hover_text("Pause the simulation the first time it happens");
+            if let Some(generation) = self.escape_generation {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Escaped at generation {}", generation));
+                    if ui
+                        .small_button("Clear")
+                        .on_hover_text("Watch for the next escape")
+                        .clicked()
+                    {
+                        self.escape_generation = None;
+                    }
+                });
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.settings.measure_growth, "Measure growth")
+                .on_hover_text(
+                    "Fit the population against the generations to tell bounded \
+                     patterns from linear growth (guns) and quadratic growth \
+                     (breeders). Start measuring when the pattern starts",
+                )
+                .changed()
+            {
+                self.growth.reset();
+            }
+            if ui
+                .add_enabled(self.settings.measure_growth, egui::Button::new("Reset"))
+                .on_hover_text("Start the measurement over from this generation")
+                .clicked()
+            {
+                self.growth.reset();
+            }
+        });
+        if self.settings.measure_growth {
+            let estimate = match self.growth.estimate() {
+                Some(growth) => growth.to_string(),
+                None => "measuring...".to_string(),
+            };
+            ui.label(format!(
+                "{} (over {} generations)",
+                estimate,
+                self.growth.generations()
+            ));
+        }
+        ui.separator();
+        ui.collapsing("Device info", |ui| device_info_ui(ui, &self.device_limits));
+    }
+
+    /// Pause at the target generation, finishing the recording if asked to
+    fn reach_target(&mut self) {
+        self.paused = true;
+        let generation = self.pipeline.generation();
+        let recorder = if self.settings.stop_recording_at_target {
+            self.recorder.take()
+        } else {
+            None
+        };
+        let message = match recorder.map(|recorder| recorder.finish()) {
+            Some(Ok(())) => format!("Reached generation {}, recording saved", generation),
+            Some(Err(err)) => {
+                self.notifications.notify(err.to_string(), Level::Error);
+                format!("Reached generation {}", generation)
+            }
+            None => format!("Reached generation {}", generation),
+        };
+        self.notifications.notify(message, Level::Info);
+    }
+
+    /// Run the sprite sheet's generations right away, capturing the whole image every stride,
+    /// and save the tiles as one PNG. The grid is left at the last generation.
+    fn export_sprite_sheet(&mut self, context: &VulkanoContext) -> Result<(), String> {
+        let settings = self.settings.sprite_sheet.clone();
+        let mut sheet = SpriteSheet::new(self.pipeline.image_size(), &settings)?;
+        let stride = settings.stride.max(1);
+
+        self.pipeline.set_color(true);
+        self.pipeline.set_capture_frame(true);
+        for tile in 0..settings.tile_count() {
+            // The first tile is the current generation
+            let generations = if tile == 0 { 0 } else { stride };
+            let before = sync::now(context.device()).boxed();
+            let _ = self.pipeline.compute_n(
+                before,
+                generations,
+                &self.settings.rule,
+                &self.settings.render,
+            );
+            let frame = self
+                .pipeline
+                .captured_frame()
+                .ok_or("the frame couldn't be captured")?;
+            sheet.add(&frame)?;
+        }
+        self.pipeline.set_capture_frame(false);
+
+        let generations = (settings.tile_count() - 1) * stride;
+        if let (Some(recorder), true) = (&mut self.macro_recorder, generations > 0) {
+            recorder.push(Action::Generations(generations));
+        }
+        sheet.save(&settings.path)
+    }
+
+    fn start_png_sequence(&mut self) {
+        match PngSequence::start(&self.settings.png_sequence, self.pipeline.image_size()) {
+            Ok(sequence) => self.png_sequence = Some(sequence),
+            Err(err) => self.notifications.notify(
+                format!("Failed to start the PNG sequence: {}", err),
+                Level::Error,
+            ),
+        }
+    }
+
+    /// Run and capture the sequence's next frames for up to `PNG_SEQUENCE_BUDGET`, finishing it
+    /// once every frame is queued or capturing fails
+    fn export_png_frames(&mut self, context: &VulkanoContext) {
+        let sequence = match &mut self.png_sequence {
+            Some(sequence) => sequence,
+            None => return,
+        };
+
+        let start = Instant::now();
+        let mut result = Ok(());
+        self.pipeline.set_color(true);
+        self.pipeline.set_capture_frame(true);
+        while !sequence.is_complete() && start.elapsed() < PNG_SEQUENCE_BUDGET {
+            let before = sync::now(context.device()).boxed();
+            let _ = self.pipeline.compute_n(
+                before,
+                sequence.next_generations(),
+                &self.settings.rule,
+                &self.settings.render,
+            );
+            result = match self.pipeline.captured_frame() {
+                Some(frame) => sequence.add(frame),
+                None => Err(io::Error::other("the frame couldn't be captured")),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        self.pipeline.set_capture_frame(false);
+
+        if result.is_err() || sequence.is_complete() {
+            let sequence = self.png_sequence.take().unwrap();
+            self.finish_png_sequence(sequence, result);
+        }
+    }
+
+    /// Write the frames queued so far and report how the export went, `result` is the error
+    /// that stopped it early if any
+    fn finish_png_sequence(&mut self, sequence: PngSequence, result: io::Result<()>) {
+        let (queued, frames) = sequence.progress();
+        let generations = sequence.generations();
+        if let (Some(recorder), true) = (&mut self.macro_recorder, generations > 0) {
+            recorder.push(Action::Generations(generations));
+        }
+        // A failed write stops the worker, its error says more than the failed send
+        match sequence.finish().and(result) {
+            Ok(()) if queued == frames => self.notifications.notify(
+                format!(
+                    "{} frames saved to {}",
+                    frames, self.settings.png_sequence.directory
+                ),
+                Level::Info,
+            ),
+            Ok(()) => self.notifications.notify(
+                format!(
+                    "PNG sequence cancelled after {} of {} frames",
+                    queued, frames
+                ),
+                Level::Info,
+            ),
+            Err(err) => self.notifications.notify(
+                format!("PNG sequence stopped after {} frames: {}", queued, err),
+                Level::Error,
+            ),
+        }
+    }
+
+    /// Save a captured frame as a PNG. With pixel perfect export only the visible part of the
+    /// image is kept, scaled up by the whole number of pixels per image pixel closest to the view.
+    fn save_screenshot(&self, frame: &[u8]) -> Result<(), String> {
+        let [width, height] = self.pipeline.image_size();
+        let image = image::RgbaImage::from_raw(width, height, frame.to_vec())
+            .ok_or("captured frame doesn't match the image size")?;
+
+        let image = if self.settings.pixel_perfect {
+            let transform = self.displayed_transform();
+            let corners = [
+                transform.tex_coords([-1.0, -1.0]),
+                transform.tex_coords([1.0, 1.0]),
+            ];
+            let size = [width, height];
+            let [min, max] = [0, 1].map(|corner| {
+                [0, 1].map(|i| {
+                    let pixel = corners[corner][i] * size[i] as f32;
+                    let pixel = if corner == 0 {
+                        pixel.floor()
+                    } else {
+                        pixel.ceil()
+                    };
+                    pixel.clamp(0.0, size[i] as f32) as u32
+                })
+            });
+            if min[0] >= max[0] || min[1] >= max[1] {
+                return Err("no cells are visible".to_string());
+            }
+
+            // Screen pixels per image pixel, the same on both axes
+            let visible_width = (corners[1][0] - corners[0][0]) * width as f32;
+            let zoom = (self.viewport_bounds.dimensions[0] / visible_width)
+                .round()
+                .max(1.0) as u32;
+
+            let cropped =
+                image::imageops::crop_imm(&image, min[0], min[1], max[0] - min[0], max[1] - min[1])
+                    .to_image();
+            image::imageops::resize(
+                &cropped,
+                cropped.width() * zoom,
+                cropped.height() * zoom,
+                image::imageops::FilterType::Nearest,
+            )
+        } else {
+            image
+        };
+
+        image
+            .save(&self.settings.screenshot_path)
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Load a pattern file into the center of a cleared grid, see `place_pattern`. Returns the
+/// trimmed pattern too, for stamping it again.
+fn load_pattern_file(
+    pipeline: &mut GameComputePipeline,
+    path: &Path,
+    resize_to_fit: bool,
+    limits: &DeviceLimits,
+) -> Result<(Pattern, Option<String>), PatternError> {
+    let pattern = Pattern::from_file(path, limits.max_grid_side())?.trimmed();
+    let warning = place_pattern(pipeline, &pattern, resize_to_fit, limits)?;
+    Ok((pattern, warning))
+}
+
+/// Parse the pattern given with `--stdin` in either format, input without live cells is an error
+fn parse_stdin_pattern(text: &str, max_side: u32) -> Result<Pattern, PatternError> {
+    let pattern = pattern::parse(text, max_side)?.trimmed();
+    if pattern.size().contains(&0) {
+        return Err(PatternError::Parse("no live cells on stdin".to_string()));
+    }
+    Ok(pattern)
+}
+
+/// Place a pattern in the center of a cleared grid. If the pattern doesn't fit the grid is either
+/// grown to fit it (up to the largest grid the device supports) or the pattern is clipped,
+/// returns a warning describing what happened.
+fn place_pattern(
+    pipeline: &mut GameComputePipeline,
+    pattern: &Pattern,
+    resize_to_fit: bool,
+    limits: &DeviceLimits,
+) -> Result<Option<String>, PatternError> {
+    let pattern = pattern.trimmed();
+    let [width, height] = pattern.size();
+    let grid_size = pipeline.size();
+
+    let mut warning = None;
+    if width > grid_size[0] || height > grid_size[1] {
+        if resize_to_fit {
+            // Keep the grid square and a multiple of the workgroup size
+            let side = (width.max(height) + 2 * PATTERN_MARGIN).next_multiple_of(8);
+            let side = side.max(grid_size[0]).max(grid_size[1]);
+            let max_side = limits.max_grid_side();
+            if side > max_side {
+                pipeline.resize([max_side, max_side])?;
+                warning = Some(format!(
+                    "Pattern ({}x{}) was clipped to the largest grid the device supports \
+                     ({}x{})",
+                    width, height, max_side, max_side
+                ));
+            } else {
+                pipeline.resize([side, side])?;
+                warning = Some(format!(
+                    "Grid resized to {}x{} to fit the pattern",
+                    side, side
+                ));
+            }
+        } else {
+            warning = Some(format!(
+                "Pattern ({}x{}) was clipped to the grid",
+                width, height
+            ));
+        }
+    }
+
+    pipeline.clear();
+    let grid_size = pipeline.size();
+    let offset = [
+        (grid_size[0] as i32 - width as i32) / 2,
+        (grid_size[1] as i32 - height as i32) / 2,
+    ];
+    pipeline.load_pattern(&pattern, offset);
+
+    Ok(warning)
+}
+
+/// Replace the grid with an image, one cell per pixel, padded to a multiple of 8
+fn import_image(
+    pipeline: &mut GameComputePipeline,
+    path: &Path,
+    threshold: f32,
+) -> Result<(), PatternError> {
+    let pattern = Pattern::from_image(path, threshold)?;
+    let [width, height] = pattern.size();
+    if width == 0 || height == 0 {
+        return Err(PatternError::Parse("image is empty".to_string()));
+    }
+
+    let size = [width.next_multiple_of(8), height.next_multiple_of(8)];
+    pipeline.resize(size)?;
+    pipeline.clear();
+    pipeline.load_pattern(&pattern, [0, 0]);
+    Ok(())
+}
+
+fn save_session(
+    path: &Path,
+    settings: &AppSettings,
+    pipeline: &GameComputePipeline,
+) -> Result<(), SessionError> {
+    session::save(
+        path,
+        &Session {
+            settings: settings.clone(),
+            generation: pipeline.generation(),
+            grid: Pattern::new(pipeline.size(), pipeline.snapshot()),
+        },
+    )
+}
+
+/// Restore the settings and grid from a session file, nothing is changed if loading fails
+fn load_session(
+    path: &Path,
+    settings: &mut AppSettings,
+    pipeline: &mut GameComputePipeline,
+) -> Result<(), SessionError> {
+    let session = session::load(path)?;
+
+    if pipeline.size() != session.grid.size() {
+        pipeline
+            .resize(session.grid.size())
+            .map_err(SessionError::Grid)?;
+    }
+    pipeline.clear();
+    pipeline.load_pattern(&session.grid, [0, 0]);
+    pipeline.set_generation(session.generation);
+    *settings = session.settings;
+
+    Ok(())
+}
+
+/// Export and import the rule and palette, or apply a built-in preset. Returns whether one was
+/// applied.
+fn preset_ui(
+    ui: &mut egui::Ui,
+    path: &mut String,
+    builtin: &[Preset],
+    settings: &mut AppSettings,
+    notifications: &mut Notifications,
+) -> bool {
+    let mut applied = None;
+    ui.horizontal(|ui| {
+        ui.label("Preset file:");
+        ui.text_edit_singleline(path);
+    })
+    .response
+    .on_hover_text(
+        "A preset stores the rule, topology, edges, render mode and colors, not the grid",
+    );
+    ui.horizontal(|ui| {
+        if ui
+            .button("Export preset")
+            .on_hover_text("Write the current rule and palette to the file, to share")
+            .clicked()
+        {
+            let name = Path::new(path.as_str())
+                .file_stem()
+                .map_or("Preset".into(), |stem| stem.to_string_lossy());
+            match preset::save(Path::new(path.as_str()), &Preset::capture(&name, settings)) {
+                Ok(()) => notifications.notify("Preset exported", Level::Info),
+                Err(err) => notifications.notify(err.to_string(), Level::Error),
+            }
+        }
+        if ui
+            .button("Import preset")
+            .on_hover_text("Replace the rule and palette with the file's, the grid is kept")
+            .clicked()
+        {
+            match preset::load(Path::new(path.as_str())) {
+                Ok(preset) => applied = Some(preset),
+                Err(err) => notifications.notify(err.to_string(), Level::Error),
+            }
+        }
+        ui.menu_button("Built-in", |ui| {
+            for preset in builtin {
+                if ui.button(&preset.name).clicked() {
+                    applied = Some(preset.clone());
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("Apply one of the presets shipped with the app");
+    });
+
+    match applied {
+        Some(preset) => {
+            preset.apply(settings);
+            notifications.notify(format!("Applied the {} preset", preset.name), Level::Info);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Clear the grid and put the soup for `seed` in the center
+fn place_soup(pipeline: &mut GameComputePipeline, seed: u64, size: u32, density: f32) {
+    let grid_size = pipeline.size();
+    pipeline.clear();
+    pipeline.set_generation(0);
+    pipeline.load_pattern(
+        &soup::soup(seed, size, density),
+        [
+            (grid_size[0] as i32 - size as i32) / 2,
+            (grid_size[1] as i32 - size as i32) / 2,
+        ],
+    );
+}
+
+/// Show, hide, solo and fade each color channel of the displayed image
+fn channel_mask_ui(ui: &mut egui::Ui, render: &mut RenderSettings) {
+    ui.collapsing("Channels", |ui| {
+        for (i, name) in ["Red", "Green", "Blue"].into_iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut render.channels_visible[i], name)
+                    .on_hover_text("Show the channel, hidden it takes the grid color");
+                let solo = render.channels_visible == [0, 1, 2].map(|j| j == i);
+                if ui
+                    .selectable_label(solo, "Solo")
+                    .on_hover_text("Hide the other channels, again to show them all")
+                    .clicked()
+                {
+                    render.channels_visible = if solo {
+                        [true; 3]
+                    } else {
+                        [0, 1, 2].map(|j| j == i)
+                    };
+                }
+                ui.add_enabled(
+                    render.channels_visible[i],
+                    egui::Slider::new(&mut render.channel_opacity[i], 0.0..=1.0),
+                )
+                .on_hover_text("Opacity over the grid color");
+            });
+        }
+    });
+}
+
+/// Buffering of new windows, counts past what the surface supports can't be picked
+fn swapchain_images_ui(
+    ui: &mut egui::Ui,
+    swapchain_images: &mut SwapchainImages,
+    (min, max): (u32, Option<u32>),
+) {
+    egui::ComboBox::from_label("Buffering")
+        .selected_text(swapchain_images.name())
+        .show_ui(ui, |ui| {
+            for option in SwapchainImages::ALL {
+                let supported =
+                    !matches!(option.count().zip(max), Some((count, max)) if count > max);
+                let selected = *swapchain_images == option;
+                if ui
+                    .add_enabled(
+                        supported,
+                        egui::SelectableLabel::new(selected, option.name()),
+                    )
+                    .on_disabled_hover_text(format!(
+                        "The surface supports at most {} images",
+                        max.unwrap_or_default()
+                    ))
+                    .clicked()
+                {
+                    *swapchain_images = option;
+                }
+            }
+        })
+        .response
+        .on_hover_text(format!(
+            "Swapchain images of windows opened from here on, more can smooth out stutter at the \
+             cost of latency (open windows can't change theirs, start with --swapchain-images). \
+             The surface needs at least {}",
+            min
+        ));
+}
+
+/// Raised look for shaped cells, the bevel width and where the light comes from
+fn bevel_ui(ui: &mut egui::Ui, render: &mut RenderSettings) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut render.bevel, "Bevel").on_hover_text(
+            "Shade the cell edges as if the cells were raised, dark cells stay flat. Fades out \
+             when cells are too small on screen",
+        );
+        ui.add_enabled_ui(render.bevel, |ui| {
+            ui.add(egui::Slider::new(&mut render.bevel_depth, 0.05..=0.45).text("Depth"))
+                .on_hover_text("Width of the shaded edge, as a fraction of a cell");
+        });
+    });
+    ui.add_enabled_ui(render.bevel, |ui| {
+        ui.add(
+            egui::Slider::new(&mut render.light_angle, 0.0..=360.0)
+                .suffix("°")
+                .text("Light angle"),
+        )
+        .on_hover_text("Direction the light comes from, counterclockwise from the right");
+    });
+}
+
+/// Darkened viewport edges for presenting
+fn vignette_ui(ui: &mut egui::Ui, render: &mut RenderSettings) {
+    ui.checkbox(&mut render.vignette, "Vignette")
+        .on_hover_text("Darken the edges of the view to draw the eye to the center");
+    if render.vignette {
+        ui.add(egui::Slider::new(&mut render.vignette_intensity, 0.0..=1.0).text("Strength"))
+            .on_hover_text("How dark the corners get");
+        ui.add(egui::Slider::new(&mut render.vignette_radius, 0.0..=0.95).text("Radius"))
+            .on_hover_text("How far out from the center the darkening starts, 1 is the corners");
+    }
+}
+
+/// Colors of the diff view, editable in place
+fn diff_legend_ui(ui: &mut egui::Ui, render: &mut RenderSettings) {
+    let RenderSettings {
+        life_color,
+        born_color,
+        died_color,
+        ..
+    } = render;
+    ui.horizontal(|ui| {
+        for (color, label) in [
+            (born_color, "Born"),
+            (died_color, "Died"),
+            (life_color, "Survived"),
+        ] {
+            ui.color_edit_button_rgba_unmultiplied(color);
+            ui.label(label);
+        }
+    })
+    .response
+    .on_hover_text("Cells that changed in the last step, empty cells keep the dead color");
+}
+
+/// Edit the Lenia rule, returns whether the orbium preset was clicked
+fn lenia_rule_ui(ui: &mut egui::Ui, lenia: &mut LeniaRule) -> bool {
+    ui.horizontal(|ui| {
+        ui.label("Radius:");
+        ui.add(egui::Slider::new(
+            &mut lenia.radius,
+            1..=rule::MAX_LENIA_RADIUS,
+        ));
+    })
+    .response
+    .on_hover_text("Radius of the ring shaped kernel averaging the neighborhood");
+    ui.horizontal(|ui| {
+        ui.label("Growth:");
+        ui.add(
+            egui::DragValue::new(&mut lenia.growth_center)
+                .speed(0.001)
+                .clamp_range(0.0..=1.0),
+        )
+        .on_hover_text("Neighborhood average that grows a cell the most");
+        ui.label("±");
+        ui.add(
+            egui::DragValue::new(&mut lenia.growth_width)
+                .speed(0.001)
+                .clamp_range(0.001..=1.0),
+        )
+        .on_hover_text("Width of the growth bell, averages further off shrink the cell");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Time step:");
+        ui.add(
+            egui::DragValue::new(&mut lenia.time_step)
+                .speed(0.01)
+                .clamp_range(0.01..=1.0),
+        );
+    })
+    .response
+    .on_hover_text("Fraction of the growth applied each generation");
+    ui.button("Orbium")
+        .on_hover_text("Clear the grid and place an orbium, a glider, with its rule")
+        .clicked()
+}
+
+/// Radius, birth and survival ranges and weights of a Larger than Life rule
+fn kernel_rule_ui(ui: &mut egui::Ui, kernel_rule: &mut KernelRule, toggles: &mut bool) {
+    let mut radius = kernel_rule.radius;
+    ui.horizontal(|ui| {
+        ui.label("Radius:");
+        ui.add(egui::Slider::new(&mut radius, 1..=rule::MAX_KERNEL_RADIUS));
+    })
+    .response
+    .on_hover_text("Cells this far away count, changing it resets the weights");
+    if radius != kernel_rule.radius {
+        kernel_rule.set_radius(radius);
+    }
+
+    for (label, range, hover) in [
+        (
+            "Birth:",
+            &mut kernel_rule.birth,
+            "A dead cell is born if the sum is in this range",
+        ),
+        (
+            "Survival:",
+            &mut kernel_rule.survival,
+            "A live cell survives if the sum is in this range",
+        ),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(egui::DragValue::new(&mut range[0]).speed(0.1));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut range[1]).speed(0.1));
+        })
+        .response
+        .on_hover_text(hover);
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Weights:");
+        egui::ComboBox::from_id_source("kernel_neighborhood")
+            .selected_text("Neighborhood")
+            .show_ui(ui, |ui| {
+                for neighborhood in Neighborhood::ALL {
+                    if ui.selectable_label(false, neighborhood.name()).clicked() {
+                        kernel_rule.weights = neighborhood.weights(kernel_rule.radius);
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Replace the weights with 1 for each neighbor of a standard shape");
+        if ui
+            .small_button("Conway")
+            .on_hover_text("Radius 1 with unit weights, B3/S23")
+            .clicked()
+        {
+            *kernel_rule = KernelRule::default();
+        }
+        ui.checkbox(toggles, "Toggles").on_hover_text(
+            "Edit the weights as a grid of neighbors that count (1) or don't (0), click one to flip \
+             it. Off to type in any weight",
+        );
+    });
+    let side = kernel_rule.side() as usize;
+    egui::Grid::new("kernel_weights")
+        .spacing([2.0, 2.0])
+        .show(ui, |ui| {
+            for (y, row) in kernel_rule.weights.chunks_mut(side).enumerate() {
+                for (x, weight) in row.iter_mut().enumerate() {
+                    if *toggles {
+                        kernel_toggle(ui, weight, [x, y] == [side / 2; 2]);
+                    } else {
+                        ui.add(egui::DragValue::new(weight).speed(0.05).max_decimals(2));
+                    }
+                }
+                ui.end_row();
+            }
+        })
+        .response
+        .on_hover_text("Weight of the live cell at each offset, the middle one is the cell itself");
+}
+
+/// Square flipping a kernel weight between 0 and 1, weights other than those show half filled
+fn kernel_toggle(ui: &mut egui::Ui, weight: &mut f32, center: bool) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::click());
+    if response.clicked() {
+        *weight = if *weight != 0.0 { 0.0 } else { 1.0 };
+    }
+
+    let visuals = ui.style().interact(&response);
+    let fill = if *weight == 1.0 {
+        visuals.fg_stroke.color
+    } else if *weight == 0.0 {
+        visuals.bg_fill
+    } else {
+        visuals.fg_stroke.color.linear_multiply(0.5)
+    };
+    ui.painter().rect_filled(rect, 2.0, fill);
+    if center {
+        // Mark the cell itself, it counts towards its own sum like any other weight
+        ui.painter()
+            .rect_stroke(rect, 2.0, Stroke::new(1.5, Color32::RED));
+    }
+}
+
+/// Editor for a gradient with a preview strip, returns true if it was changed
+fn gradient_ui(ui: &mut egui::Ui, gradient: &mut Gradient) -> bool {
+    let mut changed = false;
+
+    // Preview strip
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 16.0), egui::Sense::hover());
+    let segments = 64;
+    for i in 0..segments {
+        let [r, g, b] = gradient.sample((i as f32 + 0.5) / segments as f32);
+        let x0 = egui::lerp(rect.x_range(), i as f32 / segments as f32);
+        let x1 = egui::lerp(rect.x_range(), (i + 1) as f32 / segments as f32);
+        ui.painter().rect_filled(
+            Rect::from_x_y_ranges(x0..=x1, rect.y_range()),
+            0.0,
+            Color32::from(egui::Rgba::from_rgb(r, g, b)),
+        );
+    }
+
+    // Edited as a copy, rebuilding the gradient keeps the stops sorted
+    let mut stops = gradient.stops().to_vec();
+    let mut remove = None;
+    let can_remove = stops.len() > 1;
+    for (index, stop) in stops.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            changed |= ui.color_edit_button_rgb(&mut stop.color).changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut stop.position, 0.0..=1.0))
+                .changed();
+            if can_remove && ui.small_button("x").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+
+    if let Some(index) = remove {
+        stops.remove(index);
+        changed = true;
+    }
+
+    if stops.len() < MAX_GRADIENT_STOPS && ui.button("Add stop").clicked() {
+        // Insert halfway between the last two stops so the new stop is visible
+        let position = match stops.as_slice() {
+            [.., a, b] => (a.position + b.position) / 2.0,
+            _ => 0.5,
+        };
+        stops.push(GradientStop {
+            position,
+            color: gradient.sample(position),
+        });
+        changed = true;
+    }
+
+    if changed {
+        *gradient = Gradient::new(stops);
+    }
+    changed
+}
+
+/// Limits of the device, which explain why a grid size is too large
+fn device_info_ui(ui: &mut egui::Ui, limits: &DeviceLimits) {
+    egui::Grid::new("device_info").striped(true).show(ui, |ui| {
+        for (label, value) in limits.rows() {
+            ui.label(label);
+            ui.label(value);
+            ui.end_row();
+        }
+    });
+}
+
+/// Mode, clock and period of the live and dead color animation
+fn palette_animation_ui(ui: &mut egui::Ui, settings: &mut PaletteAnimationSettings) {
+    ui.horizontal(|ui| {
+        ui.label("Animate:");
+        egui::ComboBox::from_id_source("palette_animation")
+            .selected_text(settings.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in PaletteAnimation::ALL {
+                    ui.selectable_value(&mut settings.mode, mode, mode.name());
+                }
+            })
+            .response
+            .on_hover_text(
+                "Hue cycle turns the colors around the color wheel, pulse dims and brightens them",
+            );
+        if settings.mode == PaletteAnimation::Static {
+            return;
+        }
+        ui.checkbox(&mut settings.animate_life, "Live")
+            .on_hover_text("Animate the live cell color");
+        ui.checkbox(&mut settings.animate_dead, "Dead")
+            .on_hover_text("Animate the dead cell color");
+    });
+    if settings.mode == PaletteAnimation::Static {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Period:");
+        ui.add(
+            egui::DragValue::new(&mut settings.period)
+                .speed(0.1)
+                .clamp_range(0.1..=10_000.0),
+        )
+        .on_hover_text("Length of one cycle");
+        egui::ComboBox::from_id_source("animation_clock")
+            .selected_text(settings.clock.name())
+            .show_ui(ui, |ui| {
+                for clock in AnimationClock::ALL {
+                    ui.selectable_value(&mut settings.clock, clock, clock.name());
+                }
+            })
+            .response
+            .on_hover_text(
+                "Seconds keep the colors moving while paused, generations tie them to the \
+                 simulation speed",
+            );
+    });
+    if settings.mode == PaletteAnimation::Pulse {
+        ui.add(egui::Slider::new(&mut settings.pulse_depth, 0.0..=1.0).text("Pulse depth"))
+            .on_hover_text("Fraction of the brightness lost at the dimmest point");
+    }
+}
+
+/// Generation to pause at, set some way past the current one when enabled
+fn stop_at_ui(
+    ui: &mut egui::Ui,
+    stop_at: &mut Option<u64>,
+    stop_recording: &mut bool,
+    generation: u64,
+) {
+    ui.horizontal(|ui| {
+        let mut enabled = stop_at.is_some();
+        ui.checkbox(&mut enabled, "Stop at")
+            .on_hover_text("Pause on reaching a generation, off runs without a limit");
+        if enabled != stop_at.is_some() {
+            *stop_at = enabled.then(|| generation + 100);
+        }
+        if let Some(target) = stop_at {
+            ui.add(egui::DragValue::new(target).speed(1.0))
+                .on_hover_text(if *target > generation {
+                    format!("{} generations to go", *target - generation)
+                } else {
+                    "Already reached, running on without a limit".to_string()
+                });
+            ui.checkbox(stop_recording, "Stop recording")
+                .on_hover_text("Also finish the recording there, for clips of exact lengths");
+        }
+    });
+}
+
+/// Line of the sampled populations scaled between their minimum and maximum, the latest at the
+/// right edge
+fn sparkline_ui(ui: &mut egui::Ui, samples: &VecDeque<(u64, u64)>) {
+    let size = egui::vec2(ui.available_width(), SPARKLINE_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let ((first, _), (last, latest)) = match (samples.front(), samples.back()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => {
+            response.on_hover_text("Waiting for the population to be read back");
+            return;
+        }
+    };
+
+    let min = samples
+        .iter()
+        .map(|&(_, population)| population)
+        .min()
+        .unwrap();
+    let max = samples
+        .iter()
+        .map(|&(_, population)| population)
+        .max()
+        .unwrap();
+    let range = (max - min).max(1) as f32;
+    let step = rect.width() / (SPARKLINE_SAMPLES - 1) as f32;
+    // Fills in from the right while there are fewer samples than fit
+    let start = rect.max.x - step * (samples.len() - 1) as f32;
+    let points = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, population))| {
+            let y = rect.max.y - (population - min) as f32 / range * rect.height();
+            Pos2::new(start + step * i as f32, y)
+        })
+        .collect();
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    painter.add(egui::Shape::line(
+        points,
+        Stroke::new(1.0, ui.visuals().text_color()),
+    ));
+    response.on_hover_text(format!(
+        "{} live cells at generation {}, between {} and {} since generation {}",
+        latest, last, min, max, first
+    ));
+}
+
+/// Time the current grid's generations, returns whether the button was clicked
+fn benchmark_ui(ui: &mut egui::Ui, generations: &mut u32, result: Option<(u64, Duration)>) -> bool {
+    let mut clicked = false;
+    ui.horizontal(|ui| {
+        clicked = ui
+            .button("Benchmark grid")
+            .on_hover_text(
+                "Time this many generations of the current grid, then put it back as it was. The \
+                 window stalls while it runs",
+            )
+            .clicked();
+        ui.add(
+            egui::DragValue::new(generations)
+                .clamp_range(16..=100_000)
+                .suffix(" gen"),
+        );
+    });
+    if let Some((population, per_generation)) = result {
+        let seconds = per_generation.as_secs_f64();
+        ui.label(format!(
+            "{:.3} ms per generation ({:.0}/s) with {} live cells",
+            seconds * 1000.0,
+            1.0 / seconds.max(1e-9),
+            population
+        ))
+        .on_hover_text("Averaged over the generations, including the wait for each batch");
+    }
+    clicked
+}
+
+/// Slow motion toggle, morph duration and the button to morph to the next generation, returns
+/// whether the button was clicked
+fn slow_motion_ui(ui: &mut egui::Ui, slow_motion: &mut bool, duration: &mut f32) -> bool {
+    let mut next_step = false;
+    ui.horizontal(|ui| {
+        ui.checkbox(slow_motion, "Slow motion").on_hover_text(
+            "Run one generation at a time, fading cells in and out between them. Overrides the \
+             speed",
+        );
+        ui.add_enabled_ui(*slow_motion, |ui| {
+            ui.add(
+                egui::Slider::new(duration, 0.1..=10.0)
+                    .logarithmic(true)
+                    .suffix(" s"),
+            )
+            .on_hover_text("How long each morph from one generation to the next takes");
+            next_step = ui
+                .button("Next step")
+                .on_hover_text("Pause and morph to the next generation")
+                .clicked();
+        });
+    });
+    next_step
+}
+
+/// A collapsible group of panel controls, open or closed as it was last left
+fn panel_section(
+    ui: &mut egui::Ui,
+    open_sections: &mut Vec<PanelSection>,
+    section: PanelSection,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) {
+    let open = open_sections.contains(&section);
+    let response = egui::CollapsingHeader::new(section.name())
+        .open(Some(open))
+        .show(ui, add_contents);
+    if response.header_response.clicked() {
+        if open {
+            open_sections.retain(|&open| open != section);
+        } else {
+            open_sections.push(section);
+        }
+    }
+}
+
+/// Whether and how past generations are kept to step back to, with the memory they take
+fn history_ui(
+    ui: &mut egui::Ui,
+    settings: &mut HistorySettings,
+    history: &GridHistory,
+    grid_size: [u32; 2],
+) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.enabled, "History")
+            .on_hover_text("Keep the grid of past generations to step back to");
+        ui.add(egui::DragValue::new(&mut settings.generations).clamp_range(1..=10_000))
+            .on_hover_text("Past generations kept, fewer if they don't fit the budget");
+        egui::ComboBox::from_id_source("history_storage")
+            .selected_text(settings.storage.name())
+            .show_ui(ui, |ui| {
+                for storage in HistoryStorage::ALL {
+                    ui.selectable_value(&mut settings.storage, storage, storage.name());
+                }
+            })
+            .response
+            .on_hover_text("Bit packed takes an eighth of the memory of full");
+    });
+    if !settings.enabled {
+        return;
+    }
+
+    let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let per_generation = settings.storage.bytes_per_generation(grid_size);
+    let readback = history::readback_bytes(grid_size);
+    ui.horizontal(|ui| {
+        ui.label("Budget:");
+        ui.add(
+            egui::DragValue::new(&mut settings.budget_mib)
+                .clamp_range(1..=65_536)
+                .suffix(" MiB"),
+        );
+    })
+    .response
+    .on_hover_text("Most memory the history may take, fewer generations are kept past it");
+    ui.label(format!(
+        "{} of {} generations kept, {:.1} of {:.1} MiB",
+        history.len(),
+        settings.capacity(grid_size),
+        mib(history.memory()),
+        mib(per_generation * settings.generations as u64 + readback),
+    ))
+    .on_hover_text(format!(
+        "{:.2} MiB per generation and {:.1} MiB of buffers to read the grid back through, the \
+         history is kept in system memory",
+        mib(per_generation),
+        mib(readback)
+    ));
+}
+
+/// Choose the generations and layout of a sprite sheet, `pending` is set to export one
+fn sprite_sheet_ui(ui: &mut egui::Ui, settings: &mut SpriteSheetSettings, pending: &mut bool) {
+    ui.horizontal(|ui| {
+        ui.label("Sprite sheet:");
+        ui.text_edit_singleline(&mut settings.path);
+    })
+    .response
+    .on_hover_text("PNG file sprite sheets are saved to");
+    ui.horizontal(|ui| {
+        ui.label("Generations:");
+        ui.add(egui::DragValue::new(&mut settings.generations).clamp_range(0..=10_000));
+        ui.label("Stride:");
+        ui.add(egui::DragValue::new(&mut settings.stride).clamp_range(1..=1_000));
+    })
+    .response
+    .on_hover_text("Generations run, with a tile every stride generations");
+    ui.horizontal(|ui| {
+        ui.label("Columns:");
+        ui.add(egui::DragValue::new(&mut settings.columns).clamp_range(1..=64));
+        ui.label("Spacing:");
+        ui.add(egui::DragValue::new(&mut settings.spacing).clamp_range(0..=64));
+    })
+    .response
+    .on_hover_text("Tiles per row, and transparent pixels between tiles");
+    let [columns, rows] = settings.layout();
+    if ui
+        .button("Export sprite sheet")
+        .on_hover_text(format!(
+            "Run the generations and save every stride's whole grid as one image, {} tiles in \
+             {}x{}. The grid is left at the last generation",
+            settings.tile_count(),
+            columns,
+            rows
+        ))
+        .clicked()
+    {
+        *pending = true;
+    }
+}
+
+/// Directory, generations and resolution of a PNG sequence, with its progress while exporting.
+/// Returns whether to start exporting, or cancel the running export.
+fn png_sequence_ui(
+    ui: &mut egui::Ui,
+    settings: &mut PngSequenceSettings,
+    progress: Option<(u32, u32)>,
+) -> bool {
+    ui.add_enabled_ui(progress.is_none(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Frames:");
+            ui.text_edit_singleline(&mut settings.directory);
+        })
+        .response
+        .on_hover_text("Directory the numbered PNG frames are written to, created if missing");
+        ui.horizontal(|ui| {
+            ui.label("Generations:");
+            ui.add(egui::DragValue::new(&mut settings.generations).clamp_range(0..=1_000_000));
+            ui.label("Stride:");
+            ui.add(egui::DragValue::new(&mut settings.stride).clamp_range(1..=1_000));
+        })
+        .response
+        .on_hover_text("Generations run, with a frame every stride generations");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut settings.native_resolution, "Native")
+                .on_hover_text("One pixel per cell, the size of the simulation image");
+            ui.add_enabled_ui(!settings.native_resolution, |ui| {
+                for side in &mut settings.resolution {
+                    ui.add(
+                        egui::DragValue::new(side)
+                            .clamp_range(16..=8192)
+                            .suffix(" px"),
+                    );
+                }
+            });
+        })
+        .response
+        .on_hover_text(
+            "Frame width and height, independent of the window. Cells stay sharp when scaled up",
+        );
+    });
+
+    match progress {
+        Some((queued, frames)) => {
+            let mut clicked = false;
+            ui.horizontal(|ui| {
+                clicked = ui
+                    .button("Cancel")
+                    .on_hover_text("Stop exporting, the frames so far are kept")
+                    .clicked();
+                ui.add(
+                    egui::ProgressBar::new(queued as f32 / frames.max(1) as f32)
+                        .text(format!("{} / {}", queued, frames)),
+                );
+            });
+            clicked
+        }
+        None => ui
+            .button("Export PNG sequence")
+            .on_hover_text(format!(
+                "Run the generations as fast as the GPU allows and save {} numbered frames \
+                 (frame_00001.png and on) of the whole grid. The simulation is held while \
+                 exporting and left at the last generation",
+                settings.frame_count()
+            ))
+            .clicked(),
+    }
+}
+
+/// Record interactions to the macro file, or replay them from it. Returns whether recording
+/// should start, which needs the whole window.
+fn macro_ui(
+    ui: &mut egui::Ui,
+    path: &mut String,
+    recorder: &mut Option<MacroRecorder>,
+    player: &mut Option<MacroPlayer>,
+    notifications: &mut Notifications,
+) -> bool {
+    let mut start_recording = false;
+    ui.horizontal(|ui| {
+        ui.label("Macro:");
+        ui.text_edit_singleline(path);
+    })
+    .response
+    .on_hover_text("JSON file macros are saved to and replayed from");
+
+    ui.horizontal(|ui| {
+        let path = Path::new(path.as_str()).to_owned();
+        if let Some(active) = recorder.take() {
+            if ui
+                .button("Stop recording")
+                .on_hover_text("Save the macro")
+                .clicked()
+            {
+                match active.finish().save(&path) {
+                    Ok(()) => notifications.notify("Macro saved", Level::Info),
+                    Err(err) => {
+                        notifications.notify(format!("failed to save macro: {}", err), Level::Error)
+                    }
+                }
+            } else {
+                *recorder = Some(active);
+            }
+        } else if player.is_some() {
+            if ui.button("Stop replay").clicked() {
+                *player = None;
+            }
+        } else {
+            if ui
+                .button("Record")
+                .on_hover_text(
+                    "Fill the grid from the seed, then record drawing, stamps, the rule, the \
+                     view and the generations simulated. Pattern files, images and sessions \
+                     aren't recorded",
+                )
+                .clicked()
+            {
+                start_recording = true;
+            }
+            if ui
+                .button("Replay")
+                .on_hover_text(
+                    "Replay the macro, giving the same grid as when it was recorded (on a \
+                     grid of the same size)",
+                )
+                .clicked()
+            {
+                match InputMacro::load(&path) {
+                    Ok(input_macro) => *player = Some(MacroPlayer::start(input_macro)),
+                    Err(err) => {
+                        notifications.notify(format!("failed to load macro: {}", err), Level::Error)
+                    }
+                }
+            }
+        }
+    });
+    if let Some(active) = player {
+        ui.add(egui::ProgressBar::new(active.progress()).show_percentage());
+    }
+    start_recording
+}
+
+/// Returns whether the grid should be resized to `resize_to`
+fn resize_ui(
+    ui: &mut egui::Ui,
+    resize_to: &mut Option<[u32; 2]>,
+    size: [u32; 2],
+    settings: &mut AppSettings,
+) -> bool {
+    let mut clicked = false;
+    let mut edited = resize_to.unwrap_or(size);
+    ui.horizontal(|ui| {
+        ui.label("Grid:");
+        for i in 0..2 {
+            if ui
+                .add(
+                    egui::DragValue::new(&mut edited[i])
+                        .clamp_range(8..=65536)
+                        .speed(8.0),
+                )
+                .changed()
+            {
+                *resize_to = Some(edited);
+            }
+        }
+        clicked = ui
+            .add_enabled(edited != size, egui::Button::new("Resize"))
+            .on_hover_text(format!(
+                "Change the grid from {}x{} cells, rounded up to multiples of 8",
+                size[0], size[1]
+            ))
+            .clicked();
+    })
+    .response
+    .on_hover_text("Grid width and height in cells");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.preserve_on_resize, "Keep cells")
+            .on_hover_text(
+                "Keep the cells when resizing, new space starts dead and shrinking crops (with a \
+                 warning if live cells are lost). Off fills the resized grid randomly",
+            );
+        ui.add_enabled_ui(settings.preserve_on_resize, |ui| {
+            egui::ComboBox::from_id_source("resize_anchor")
+                .selected_text(settings.resize_anchor.name())
+                .show_ui(ui, |ui| {
+                    for anchor in ResizeAnchor::ALL {
+                        ui.selectable_value(&mut settings.resize_anchor, anchor, anchor.name());
+                    }
+                })
+                .response
+                .on_hover_text("Where the kept cells go in the resized grid");
+        });
+    });
+    clicked
+}
+
+/// The suggested grid size, returns whether to resize the grid to it
+fn grid_suggestion_ui(ui: &mut egui::Ui, suggestion: GridSuggestion, size: [u32; 2]) -> bool {
+    let mut clicked = false;
+    ui.horizontal(|ui| {
+        ui.label(format!("Suggested: {0}x{0}", suggestion.side))
+            .on_hover_text(format!(
+            "Largest grid measured on the first start to keep up with {} fps on this GPU ({:.0} \
+                 generations/s), leaving half of each frame for rendering. Larger grids work \
+                 but may slow the app down",
+            suggestion.target_fps, suggestion.generations_per_second
+        ));
+        clicked = ui
+            .add_enabled(size != [suggestion.side; 2], egui::Button::new("Use"))
+            .on_hover_text("Resize the grid to the suggested size")
+            .clicked();
+    });
+    clicked
+}
+
+/// Returns whether the grid should be filled from the seed
+fn seeding_ui(ui: &mut egui::Ui, settings: &mut seeding::SeedSettings) -> bool {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("Fill")
+            .selected_text(settings.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in SeedMode::ALL {
+                    ui.selectable_value(&mut settings.mode, mode, mode.name());
+                }
+            })
+            .response
+            .on_hover_text("Uniform scatters cells evenly, noise clusters them into blobs");
+        ui.label("Seed:");
+        ui.add_enabled(
+            settings.source == SeedSource::Fixed,
+            egui::DragValue::new(&mut settings.seed),
+        )
+        .on_hover_text("The same seed always gives the same grid");
+        egui::ComboBox::from_id_source("seed_source")
+            .selected_text(settings.source.name())
+            .show_ui(ui, |ui| {
+                for source in SeedSource::ALL {
+                    ui.selectable_value(&mut settings.source, source, source.name());
+                }
+            })
+            .response
+            .on_hover_text(
+                "Fixed uses the seed as it is, date takes a new one each day (the same all \
+                 day) and time a new one every fill",
+            );
+    });
+    ui.add(egui::Slider::new(&mut settings.density, 0.0..=1.0).text("Density"))
+        .on_hover_text("Chance of a cell starting alive");
+    if settings.mode == SeedMode::Noise {
+        ui.add(
+            egui::Slider::new(&mut settings.noise_scale, 4.0..=256.0)
+                .logarithmic(true)
+                .text("Blob size"),
+        )
+        .on_hover_text("Rough size of the blobs in cells");
+        ui.add(egui::Slider::new(&mut settings.noise_threshold, -1.0..=1.0).text("Threshold"))
+            .on_hover_text("Higher values make fewer, smaller blobs");
+    }
+    ui.button("Randomize")
+        .on_hover_text("Fill the grid from the seed and start over from generation 0")
+        .clicked()
+}
+
+/// Returns whether the soft brush should start over from its seed
+fn brush_ui(ui: &mut egui::Ui, settings: &mut BrushSettings) -> bool {
+    let mut reseed = false;
+    ui.horizontal(|ui| {
+        ui.label("Brush radius:");
+        ui.add(egui::DragValue::new(&mut settings.radius).clamp_range(0..=64))
+            .on_hover_text("Cells drawn around each point of a line, 0 draws single cells");
+        ui.checkbox(&mut settings.soft, "Soft").on_hover_text(
+            "Set cells at random, more likely near the center, for natural looking soups \
+             instead of solid blobs. Erasing and walls stay solid",
+        );
+    });
+    if settings.soft {
+        ui.add(egui::Slider::new(&mut settings.hardness, 0.0..=1.0).text("Hardness"))
+            .on_hover_text("How far out the brush keeps its full density before fading to the rim");
+        ui.add(egui::Slider::new(&mut settings.density, 0.0..=1.0).text("Brush density"))
+            .on_hover_text("Chance of a cell being set where the brush is at full strength");
+        ui.horizontal(|ui| {
+            ui.label("Brush seed:");
+            reseed |= ui
+                .add(egui::DragValue::new(&mut settings.seed))
+                .on_hover_text("The same seed draws the same cells for the same strokes")
+                .changed();
+            reseed |= ui
+                .button("Restart")
+                .on_hover_text("Start over from the seed, repeating the strokes repeats the cells")
+                .clicked();
+        });
+    }
+    reseed
+}
+
+/// Returns the brush picked, the loaded pattern is only offered once there is one
+fn brush_palette_ui(ui: &mut egui::Ui, selected: Brush, has_loaded: bool) -> Option<Brush> {
+    let mut picked = None;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Brush:");
+        for brush in Brush::ALL {
+            if brush == Brush::Loaded && !has_loaded {
+                continue;
+            }
+            let hover = match brush {
+                Brush::Paint => "Drag to draw lines with the brush radius".to_string(),
+                Brush::Disc => "Click to stamp a filled disc of the brush radius".to_string(),
+                Brush::Square => "Click to stamp a filled square of the brush radius".to_string(),
+                Brush::Named(name) => format!("Click to stamp a {}", name.to_lowercase()),
+                Brush::Loaded => "Click to stamp the pattern last loaded or pasted".to_string(),
+            };
+            if ui
+                .selectable_label(brush == selected, brush.name())
+                .on_hover_text(hover)
+                .clicked()
+            {
+                picked = Some(brush);
+            }
+        }
+    });
+    picked
+}
+
+/// Returns the rotate or flip command clicked for the pattern being placed
+fn stamp_orientation_ui(ui: &mut egui::Ui, bindings: &KeyBindings) -> Option<Command> {
+    let mut command = None;
+    ui.horizontal(|ui| {
+        if ui
+            .button("Rotate")
+            .on_hover_text(format!(
+                "Turn the pattern a quarter turn clockwise ({})",
+                bindings.get(Command::RotateStamp)
+            ))
+            .clicked()
+        {
+            command = Some(Command::RotateStamp);
+        }
+        if ui
+            .button("Flip")
+            .on_hover_text(format!(
+                "Mirror the pattern left to right ({})",
+                bindings.get(Command::FlipStamp)
+            ))
+            .clicked()
+        {
+            command = Some(Command::FlipStamp);
+        }
+    });
+    command
+}
+
+fn soup_search_ui(
+    ui: &mut egui::Ui,
+    settings: &mut soup::SoupSettings,
+    search: &mut Option<SoupSearch>,
+    running: &mut bool,
+    seed: &mut u64,
+    notifications: &mut Notifications,
+    pipeline: &mut GameComputePipeline,
+) {
+    ui.add_enabled_ui(!*running, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("First seed:");
+            ui.add(egui::DragValue::new(seed));
+        })
+        .response
+        .on_hover_text("Soups use successive seeds starting from this one");
+        ui.horizontal(|ui| {
+            ui.label("Soup size:");
+            ui.add(egui::Slider::new(&mut settings.size, 4..=128));
+        })
+        .response
+        .on_hover_text("Side length of the randomized square");
+        ui.horizontal(|ui| {
+            ui.label("Density:");
+            ui.add(egui::Slider::new(&mut settings.density, 0.05..=0.95));
+        })
+        .response
+        .on_hover_text("Chance of each soup cell starting alive");
+        ui.horizontal(|ui| {
+            ui.label("Max generations:");
+            ui.add(
+                egui::Slider::new(&mut settings.max_generations, 100..=100_000).logarithmic(true),
+            );
+        })
+        .response
+        .on_hover_text("Soups that haven't settled by then are stopped");
+        ui.horizontal(|ui| {
+            ui.label("Leaderboard file:");
+            ui.text_edit_singleline(&mut settings.leaderboard_path);
+        });
+    });
+
+    let label = if *running {
+        "Stop search"
+    } else {
+        "Start search"
+    };
+    if ui.button(label).clicked() {
+        if *running {
+            if let Some(search) = search {
+                // Resume from the unfinished soup next time
+                *seed = search.current_seed().unwrap_or_else(|| search.next_seed());
+            }
+            *running = false;
+        } else {
+            let path = Path::new(&settings.leaderboard_path);
+            let leaderboard = match soup::load_leaderboard(path) {
+                Ok(leaderboard) => leaderboard,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+                Err(err) => {
+                    notifications
+                        .notify(format!("failed to load leaderboard: {}", err), Level::Error);
+                    vec![]
+                }
+            };
+            *search = Some(SoupSearch::new(*seed, leaderboard));
+            *running = true;
+        }
+    }
+
+    if let Some(seed) = search.as_ref().and_then(|search| search.current_seed()) {
+        if *running {
+            ui.label(format!(
+                "Running seed {} (generation {})",
+                seed,
+                pipeline.generation()
+            ));
+        }
+    }
+
+    let leaderboard = match search {
+        Some(search) if !search.leaderboard().is_empty() => search.leaderboard(),
+        _ => return,
+    };
+    egui::Grid::new("soup_leaderboard")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Seed");
+            ui.label("Lifespan");
+            ui.label("Population");
+            ui.end_row();
+
+            for result in leaderboard {
+                if ui
+                    .button(result.seed.to_string())
+                    .on_hover_text("Stop searching and replay this soup")
+                    .clicked()
+                {
+                    *running = false;
+                    place_soup(pipeline, result.seed, result.size, result.density);
+                }
+                ui.label(result.lifespan.to_string());
+                ui.label(result.final_population.to_string());
+                ui.end_row();
+            }
+        });
+}
+
+/// Key of each command, rebound by clicking it and pressing the new key
+fn keybindings_ui(ui: &mut egui::Ui, bindings: &mut KeyBindings, rebinding: &mut Option<Command>) {
+    egui::Grid::new("keybindings").show(ui, |ui| {
+        for command in Command::ALL {
+            ui.label(command.name());
+            let text = if *rebinding == Some(command) {
+                "Press a key...".to_string()
+            } else {
+                bindings.get(command).to_string()
+            };
+            if ui
+                .button(text)
+                .on_hover_text("Click, then press the key (with Ctrl or Cmd if wanted)")
+                .clicked()
+            {
+                *rebinding = Some(command);
+            }
+            ui.end_row();
+        }
+    });
+    for (first, second) in bindings.conflicts() {
+        ui.colored_label(
+            Color32::YELLOW,
+            format!(
+                "{} and {} share {}, only {} works",
+                first.name(),
+                second.name(),
+                bindings.get(first),
+                first.name()
+            ),
+        );
+    }
+    if ui
+        .button("Reset to defaults")
+        .on_hover_text("Restore the original key of every command")
+        .clicked()
+    {
+        bindings.reset();
+        *rebinding = None;
+    }
+}
+
+/// Contents of the help section at the top of the settings panel
+fn help_ui(ui: &mut egui::Ui, bindings: &KeyBindings) {
+    let key = |command| bindings.get(command);
+    ui.label(
+        "The Game of Life is played on a grid of cells that are either alive or dead. Every \
+         generation each cell looks at its 8 neighbors: a dead cell with exactly 3 live \
+         neighbors is born, a live cell with 2 or 3 live neighbors survives and every other \
+         cell dies or stays dead.",
+    );
+    ui.label(
+        "The rule field changes this using birth/survival notation, Conway's rules are B3/S23. \
+         Try B36/S23 (HighLife) or B2/S (Seeds).",
+    );
+    ui.separator();
+    ui.label("Controls:");
+    ui.label("• Hover over any setting to see what it does");
+    ui.label(format!(
+        "• {} pastes an RLE pattern, click to place it and {} to cancel",
+        key(Command::Paste),
+        key(Command::Cancel)
+    ));
+    ui.label(format!(
+        "• Pick a shape or pattern under Brush to stamp it, {} rotates and {} flips it",
+        key(Command::RotateStamp),
+        key(Command::FlipStamp)
+    ));
+    ui.label(format!(
+        "• Select a rectangle of cells and {} copies them as RLE",
+        key(Command::Copy)
+    ));
+    ui.label("• Draw sim region, then drag in the viewport to freeze everything outside");
+    ui.label("• Drag in the viewport to draw a line of cells, hold Shift to erase instead");
+    ui.label(format!(
+        "• {} opens another window with its own simulation",
+        key(Command::NewWindow)
+    ));
+    ui.label(format!(
+        "• {} hides or shows this panel",
+        key(Command::TogglePanel)
+    ));
+    ui.label(format!(
+        "• {} cycles through the color themes",
+        key(Command::CycleTheme)
+    ));
+    ui.label(format!(
+        "• {} pauses or resumes the simulation",
+        key(Command::TogglePause)
+    ));
+    ui.label("• The keys can be changed under Keybindings");
+}
+
+/// Cells covered by a drag between two cells, as min (inclusive) and max (exclusive)
+fn cell_rect(a: [i32; 2], b: [i32; 2]) -> ([u32; 2], [u32; 2]) {
+    let min = [0, 1].map(|i| a[i].min(b[i]).max(0) as u32);
+    let max = [0, 1].map(|i| (a[i].max(b[i]) + 1).max(0) as u32);
+    (min, max)
+}
+
+/// Cells on the line between two cells (both included), using Bresenham's algorithm
+/// (https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm)
+fn bresenham_line(start: [i32; 2], end: [i32; 2]) -> Vec<[i32; 2]> {
+    let dx = (end[0] - start[0]).abs();
+    let dy = -(end[1] - start[1]).abs();
+    let step_x = if start[0] < end[0] { 1 } else { -1 };
+    let step_y = if start[1] < end[1] { 1 } else { -1 };
+
+    let mut cells = vec![];
+    let [mut x, mut y] = start;
+    let mut error = dx + dy;
+    loop {
+        cells.push([x, y]);
+        if [x, y] == end {
+            return cells;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Read a view transform copied with `copy_view` from the system clipboard
+fn paste_view() -> Result<ViewportTransform, String> {
+    let text = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| format!("failed to read clipboard: {}", err))?;
+    let transform: ViewportTransform = serde_json::from_str(text.trim())
+        .map_err(|_| "clipboard doesn't contain a copied view".to_string())?;
+    if !(transform.offset.iter().all(|v| v.is_finite()) && transform.scale > 0.0) {
+        return Err("copied view has an invalid offset or scale".to_string());
+    }
+    Ok(transform)
+}
+
+/// Read an RLE pattern from the system clipboard, at most `max_side` cells wide and tall
+fn paste_rle(max_side: u32) -> Result<Pattern, String> {
+    let text = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| format!("failed to read clipboard: {}", err))?;
+
+    // parse_rle accepts almost any text, so insist on the header line
+    let has_header = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_some_and(|line| line.starts_with('x'));
+    if !has_header {
+        return Err("clipboard doesn't contain an RLE pattern".to_string());
+    }
+
+    let pattern = pattern::parse_rle(&text, max_side)
+        .map_err(|err| err.to_string())?
+        .trimmed();
+    if pattern.size() == [0, 0] {
+        return Err("pasted pattern has no live cells".to_string());
+    }
+    Ok(pattern)
+}
+
+/// The area egui's layout leaves free, in physical pixels rounded to whole ones. Uses egui's own
+/// scale so it lines up with the panels drawn around it.
+fn calculate_viewport(context: &Context) -> Viewport {
+    let rect = context.available_rect();
+    let scale_factor = context.pixels_per_point();
+    let min = [rect.min.x, rect.min.y].map(|v| (v * scale_factor).round());
+    let max = [rect.max.x, rect.max.y].map(|v| (v * scale_factor).round());
+
+    Viewport {
+        origin: min,
+        dimensions: [max[0] - min[0], max[1] - min[1]],
+        depth_range: 0.0..1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every cell one step (diagonals included) from the one before
+    fn connected(cells: &[[i32; 2]]) -> bool {
+        cells.windows(2).all(|pair| {
+            let [dx, dy] = [0, 1].map(|i| (pair[1][i] - pair[0][i]).abs());
+            dx <= 1 && dy <= 1 && dx + dy > 0
+        })
+    }
+
+    #[test]
+    fn shallow_and_diagonal_lines() {
+        assert_eq!(
+            bresenham_line([0, 0], [3, 0]),
+            vec![[0, 0], [1, 0], [2, 0], [3, 0]]
+        );
+        assert_eq!(
+            bresenham_line([0, 0], [3, 3]),
+            vec![[0, 0], [1, 1], [2, 2], [3, 3]]
+        );
+    }
+
+    #[test]
+    fn steep_line_has_a_cell_per_row() {
+        let cells = bresenham_line([1, 0], [3, 7]);
+        assert_eq!(cells.len(), 8);
+        assert_eq!(cells.first(), Some(&[1, 0]));
+        assert_eq!(cells.last(), Some(&[3, 7]));
+        assert!(cells
+            .iter()
+            .enumerate()
+            .all(|(i, cell)| cell[1] == i as i32));
+        assert!(connected(&cells));
+    }
+
+    #[test]
+    fn reversed_endpoints() {
+        for (start, end) in [([5, 2], [-3, 0]), ([2, 9], [0, -4]), ([4, 4], [0, 0])] {
+            let forward = bresenham_line(start, end);
+            let backward = bresenham_line(end, start);
+            assert_eq!(backward.first(), Some(&end));
+            assert_eq!(backward.last(), Some(&start));
+            assert_eq!(backward.len(), forward.len());
+            assert!(connected(&backward));
+        }
+    }
+
+    #[test]
+    fn single_point_line() {
+        assert_eq!(bresenham_line([4, -2], [4, -2]), vec![[4, -2]]);
+    }
+
+    #[test]
+    fn rect_dragged_in_any_direction() {
+        let expected = ([2, 3], [6, 8]);
+        assert_eq!(cell_rect([2, 3], [5, 7]), expected);
+        assert_eq!(cell_rect([5, 7], [2, 3]), expected);
+        assert_eq!(cell_rect([2, 7], [5, 3]), expected);
+        assert_eq!(cell_rect([5, 3], [2, 7]), expected);
+        assert_eq!(cell_rect([4, 4], [4, 4]), ([4, 4], [5, 5]));
+    }
+
+    #[test]
+    fn rect_clamped_at_the_grid_origin() {
+        assert_eq!(cell_rect([-3, 2], [1, -5]), ([0, 0], [2, 3]));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{fs, io, path::Path};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::pattern::Pattern;
+
+/// Generations simulated between population samples, divisible by the periods of all the common
+/// oscillators so a settled soup shows the same population every sample
+pub const SAMPLE_INTERVAL: u32 = 60;
+
+/// Samples with an unchanged population before a soup counts as settled
+pub const STABLE_SAMPLES: u32 = 3;
+
+// Entries kept on the leaderboard
+const LEADERBOARD_SIZE: usize = 20;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoupSettings {
+    /// Side length of the randomized square
+    pub size: u32,
+    /// Chance of each cell in the soup starting alive
+    pub density: f32,
+    /// Soups still active after this many generations are stopped
+    pub max_generations: u64,
+    pub leaderboard_path: String,
+}
+
+impl Default for SoupSettings {
+    fn default() -> Self {
+        Self {
+            size: 16,
+            density: 0.5,
+            max_generations: 10_000,
+            leaderboard_path: "soups.json".to_string(),
+        }
+    }
+}
+
+/// Outcome of running one soup
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SoupResult {
+    pub seed: u64,
+    pub size: u32,
+    pub density: f32,
+    /// Generations until the soup died out or settled
+    pub lifespan: u64,
+    pub final_population: u64,
+}
+
+/// Square of random cells, the same seed always gives the same soup
+pub fn soup(seed: u64, size: u32, density: f32) -> Pattern {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let cells = (0..size * size)
+        .map(|_| rng.gen_bool(density as f64) as u8)
+        .collect();
+    Pattern::new([size, size], cells)
+}
+
+/// Progress of the soup currently being run
+struct Run {
+    seed: u64,
+    last_population: Option<u64>,
+    stable_samples: u32,
+    // Generation of the first sample with the current population
+    stable_since: u64,
+}
+
+/// Runs soups with successive seeds and keeps the longest lived ones
+pub struct SoupSearch {
+    next_seed: u64,
+    run: Option<Run>,
+    leaderboard: Vec<SoupResult>,
+}
+
+impl SoupSearch {
+    pub fn new(first_seed: u64, leaderboard: Vec<SoupResult>) -> Self {
+        Self {
+            next_seed: first_seed,
+            run: None,
+            leaderboard,
+        }
+    }
+
+    /// Longest lived soups first, ties broken by the final population
+    pub fn leaderboard(&self) -> &[SoupResult] {
+        &self.leaderboard
+    }
+
+    /// Seed the next soup will use
+    pub fn next_seed(&self) -> u64 {
+        self.next_seed
+    }
+
+    /// Seed of the soup being run
+    pub fn current_seed(&self) -> Option<u64> {
+        self.run.as_ref().map(|run| run.seed)
+    }
+
+    /// Start the next soup if none is running, returns its seed
+    pub fn next_soup(&mut self) -> Option<u64> {
+        if self.run.is_some() {
+            return None;
+        }
+
+        let seed = self.next_seed;
+        self.next_seed += 1;
+        self.run = Some(Run {
+            seed,
+            last_population: None,
+            stable_samples: 0,
+            stable_since: 0,
+        });
+        Some(seed)
+    }
+
+    /// Record the population of the running soup, returns its result once it has died out,
+    /// settled or hit the generation limit
+    pub fn sample(
+        &mut self,
+        generation: u64,
+        population: u64,
+        settings: &SoupSettings,
+    ) -> Option<SoupResult> {
+        let run = self.run.as_mut()?;
+
+        if run.last_population == Some(population) {
+            run.stable_samples += 1;
+        } else {
+            run.last_population = Some(population);
+            run.stable_samples = 0;
+            run.stable_since = generation;
+        }
+
+        let lifespan = if population == 0 || run.stable_samples >= STABLE_SAMPLES {
+            run.stable_since
+        } else if generation >= settings.max_generations {
+            generation
+        } else {
+            return None;
+        };
+
+        let result = SoupResult {
+            seed: run.seed,
+            size: settings.size,
+            density: settings.density,
+            lifespan,
+            final_population: population,
+        };
+        self.run = None;
+        self.insert(result);
+        Some(result)
+    }
+
+    fn insert(&mut self, result: SoupResult) {
+        self.leaderboard.push(result);
+        self.leaderboard.sort_by(|a, b| {
+            (b.lifespan, b.final_population).cmp(&(a.lifespan, a.final_population))
+        });
+        self.leaderboard.truncate(LEADERBOARD_SIZE);
+    }
+}
+
+pub fn load_leaderboard(path: &Path) -> io::Result<Vec<SoupResult>> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn save_leaderboard(path: &Path, leaderboard: &[SoupResult]) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(leaderboard)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::write(path, json)
+}
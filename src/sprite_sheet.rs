@@ -0,0 +1,98 @@
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+// Largest sheet exported, 1 GiB of RGBA
+const MAX_SHEET_PIXELS: u64 = 1 << 28;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpriteSheetSettings {
+    pub path: String,
+    /// Generations run, the first tile is the generation the export starts from
+    pub generations: u32,
+    /// Generations between tiles
+    pub stride: u32,
+    /// Tiles per row
+    pub columns: u32,
+    /// Transparent pixels between tiles
+    pub spacing: u32,
+}
+
+impl Default for SpriteSheetSettings {
+    fn default() -> Self {
+        Self {
+            path: "sprite_sheet.png".to_string(),
+            generations: 30,
+            stride: 1,
+            columns: 8,
+            spacing: 2,
+        }
+    }
+}
+
+impl SpriteSheetSettings {
+    /// Tiles in the sheet, the starting generation and every `stride`th one after it
+    pub fn tile_count(&self) -> u32 {
+        self.generations / self.stride.max(1) + 1
+    }
+
+    /// Tiles per row and rows
+    pub fn layout(&self) -> [u32; 2] {
+        let tiles = self.tile_count();
+        let columns = self.columns.clamp(1, tiles);
+        [columns, tiles.div_ceil(columns)]
+    }
+}
+
+/// Frames of a pattern's evolution laid out row by row in one image
+pub struct SpriteSheet {
+    image: RgbaImage,
+    tile_size: [u32; 2],
+    columns: u32,
+    spacing: u32,
+    tiles: u32,
+}
+
+impl SpriteSheet {
+    /// An empty sheet with room for `settings.tile_count()` frames of `tile_size`
+    pub fn new(tile_size: [u32; 2], settings: &SpriteSheetSettings) -> Result<Self, String> {
+        let [columns, rows] = settings.layout();
+        let [width, height] = [0, 1].map(|i| {
+            let tiles = [columns, rows][i] as u64;
+            tiles * tile_size[i] as u64 + (tiles - 1) * settings.spacing as u64
+        });
+        if width * height > MAX_SHEET_PIXELS {
+            return Err(format!(
+                "a {}x{} sheet is too large, use fewer tiles or a smaller grid",
+                width, height
+            ));
+        }
+        Ok(Self {
+            image: RgbaImage::new(width as u32, height as u32),
+            tile_size,
+            columns,
+            spacing: settings.spacing,
+            tiles: 0,
+        })
+    }
+
+    /// Place the next frame, 8 bit RGBA pixels row by row
+    pub fn add(&mut self, frame: &[u8]) -> Result<(), String> {
+        let [width, height] = self.tile_size;
+        let tile = RgbaImage::from_raw(width, height, frame.to_vec())
+            .ok_or("captured frame doesn't match the image size")?;
+        let [column, row] = [self.tiles % self.columns, self.tiles / self.columns];
+        let x = column * (width + self.spacing);
+        let y = row * (height + self.spacing);
+        if y + height > self.image.height() {
+            return Err("the sheet is already full".to_string());
+        }
+        imageops::replace(&mut self.image, &tile, x, y);
+        self.tiles += 1;
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        self.image.save(path).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::game_compute_pipeline::GridStats;
+
+// How often buffered rows are flushed to the file
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+const HEADER: &str = "generation,population,width,height,state";
+
+/// Appends a row of stats per logged generation to a CSV file. Rows are written by a worker
+/// thread so the render loop never waits on the disk.
+pub struct StatsLogger {
+    sender: Sender<String>,
+    worker: JoinHandle<io::Result<()>>,
+    last_generation: Option<u64>,
+    // Stats of the last row, the same population and bounding box again counts as stable
+    last: Option<GridStats>,
+}
+
+impl StatsLogger {
+    /// Open `path` for appending, the header is only written to a new (or empty) file
+    pub fn start(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if is_empty {
+            writeln!(writer, "{}", HEADER)?;
+        }
+
+        let (sender, receiver) = mpsc::channel::<String>();
+        let worker = thread::spawn(move || loop {
+            match receiver.recv_timeout(FLUSH_INTERVAL) {
+                Ok(row) => writeln!(writer, "{}", row)?,
+                Err(RecvTimeoutError::Timeout) => writer.flush()?,
+                Err(RecvTimeoutError::Disconnected) => return writer.flush(),
+            }
+        });
+
+        Ok(Self {
+            sender,
+            worker,
+            last_generation: None,
+            last: None,
+        })
+    }
+
+    /// Queue a row, a generation that was already logged is skipped. Fails if the worker stopped
+    /// because of a write error, `finish` returns the error.
+    pub fn log(&mut self, generation: u64, stats: &GridStats) -> io::Result<()> {
+        if self.last_generation == Some(generation) {
+            return Ok(());
+        }
+        self.last_generation = Some(generation);
+
+        let [width, height] = match stats.bounding_box {
+            Some((min, max)) => [max[0] - min[0] + 1, max[1] - min[1] + 1],
+            None => [0, 0],
+        };
+        let state = if stats.population == 0 {
+            "extinct"
+        } else if self.last.is_some_and(|last| {
+            last.population == stats.population && last.bounding_box == stats.bounding_box
+        }) {
+            "stable"
+        } else {
+            "active"
+        };
+        self.last = Some(*stats);
+
+        let row = format!(
+            "{},{},{},{},{}",
+            generation, stats.population, width, height, state
+        );
+        self.sender
+            .send(row)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "stats logging stopped"))
+    }
+
+    /// Write the remaining rows and close the file
+    pub fn finish(self) -> io::Result<()> {
+        drop(self.sender);
+        self.worker
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("stats logging thread panicked")))
+    }
+}
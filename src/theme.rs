@@ -0,0 +1,44 @@
+use lifetime::game_compute_pipeline::RenderSettings;
+
+/// Named set of colors the theme hotkey cycles through
+pub struct Theme {
+    pub name: &'static str,
+    pub life_color: [f32; 4],
+    pub dead_color: [f32; 4],
+    pub grid_color: [f32; 4],
+}
+
+pub const THEMES: [Theme; 4] = [
+    Theme {
+        name: "Classic",
+        life_color: [0.2, 1.0, 0.2, 1.0],
+        dead_color: [0.0, 0.0, 0.0, 1.0],
+        grid_color: [0.0, 0.08, 0.0, 1.0],
+    },
+    Theme {
+        name: "Amber",
+        life_color: [1.0, 0.7, 0.0, 1.0],
+        dead_color: [0.08, 0.04, 0.0, 1.0],
+        grid_color: [0.16, 0.08, 0.0, 1.0],
+    },
+    Theme {
+        name: "Blueprint",
+        life_color: [0.9, 0.95, 1.0, 1.0],
+        dead_color: [0.05, 0.2, 0.5, 1.0],
+        grid_color: [0.15, 0.35, 0.7, 1.0],
+    },
+    Theme {
+        name: "High contrast",
+        life_color: [1.0, 1.0, 1.0, 1.0],
+        dead_color: [0.0, 0.0, 0.0, 1.0],
+        grid_color: [0.3, 0.3, 0.3, 1.0],
+    },
+];
+
+impl Theme {
+    pub fn apply(&self, render: &mut RenderSettings) {
+        render.life_color = self.life_color;
+        render.dead_color = self.dead_color;
+        render.grid_color = self.grid_color;
+    }
+}
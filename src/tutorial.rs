@@ -0,0 +1,201 @@
+use egui::{Color32, Context, Rect, Stroke};
+
+use crate::keybindings::{Command, KeyBindings};
+
+// Width of the step popups, in points
+const POPUP_WIDTH: f32 = 280.0;
+
+// Gap between a highlighted control and the popup pointing at it
+const POPUP_GAP: f32 = 16.0;
+
+const HIGHLIGHT_COLOR: Color32 = Color32::YELLOW;
+
+/// What a tutorial step points at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Pause,
+    Step,
+    Randomize,
+    /// The area the grid is drawn in
+    Viewport,
+}
+
+impl Target {
+    const ALL: [Target; 4] = [
+        Target::Pause,
+        Target::Step,
+        Target::Randomize,
+        Target::Viewport,
+    ];
+
+    fn index(&self) -> usize {
+        Target::ALL
+            .iter()
+            .position(|target| target == self)
+            .unwrap()
+    }
+}
+
+struct Step {
+    title: &'static str,
+    text: &'static str,
+    /// Shown centered without a target
+    target: Option<Target>,
+    /// Command whose key is mentioned after the text
+    command: Option<Command>,
+}
+
+const STEPS: [Step; 7] = [
+    Step {
+        title: "Welcome",
+        text: "This is Conway's Game of Life, a grid of cells that live and die by a few simple \
+               rules. This short tour shows the basics, it can be replayed from Help in \
+               the panel.",
+        target: None,
+        command: None,
+    },
+    Step {
+        title: "The rules",
+        text: "Every cell is alive (lit) or dead. Each generation a dead cell with exactly 3 live \
+               neighbors is born, a live cell with 2 or 3 live neighbors survives and every other \
+               cell dies or stays dead.",
+        target: Some(Target::Viewport),
+        command: None,
+    },
+    Step {
+        title: "Pause",
+        text: "Stops and resumes the simulation. The grid can still be edited while paused.",
+        target: Some(Target::Pause),
+        command: Some(Command::TogglePause),
+    },
+    Step {
+        title: "Step",
+        text: "While paused, runs a single generation so the rules can be followed cell by cell.",
+        target: Some(Target::Step),
+        command: None,
+    },
+    Step {
+        title: "Randomize",
+        text: "Fills the grid with random cells from the seed, a quick way to start over.",
+        target: Some(Target::Randomize),
+        command: None,
+    },
+    Step {
+        title: "Painting cells",
+        text: "Drag in the grid to draw a line of live cells, hold Shift to erase instead. Pause \
+               first to draw a pattern and watch what it turns into.",
+        target: Some(Target::Viewport),
+        command: None,
+    },
+    Step {
+        title: "That's it",
+        text: "Everything else is in the panel sections, hover over any control to see what it \
+               does. Help lists the rest of the controls.",
+        target: None,
+        command: None,
+    },
+];
+
+/// First run walkthrough of the main controls, a popup per step next to the control it's about.
+/// The panel records where those controls are with `mark` as it's drawn, before `show`.
+#[derive(Default)]
+pub struct Tutorial {
+    /// Index into `STEPS`, `None` while not running
+    step: Option<usize>,
+    targets: [Option<Rect>; Target::ALL.len()],
+}
+
+impl Tutorial {
+    pub fn start(&mut self) {
+        self.step = Some(0);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Where `target` was drawn this frame
+    pub fn mark(&mut self, target: Target, rect: Rect) {
+        if self.is_running() {
+            self.targets[target.index()] = Some(rect);
+        }
+    }
+
+    /// Draw the current step, returns true once the last step is done or the tutorial skipped
+    pub fn show(&mut self, context: &Context, bindings: &KeyBindings) -> bool {
+        let index = match self.step {
+            Some(index) => index,
+            None => return false,
+        };
+        let step = &STEPS[index];
+        // Controls in a collapsed section or a hidden panel aren't marked
+        let targets = std::mem::take(&mut self.targets);
+        let target = step.target.and_then(|target| match target {
+            Target::Viewport => Some(context.available_rect()),
+            target => targets[target.index()],
+        });
+
+        let mut window = egui::Window::new(step.title)
+            .id(egui::Id::new("tutorial"))
+            .collapsible(false)
+            .resizable(false)
+            .default_width(POPUP_WIDTH);
+        window = match target {
+            // Beside controls, on whichever side has room
+            Some(rect) if step.target != Some(Target::Viewport) => {
+                let x = if rect.center().x < context.available_rect().center().x {
+                    rect.max.x + POPUP_GAP
+                } else {
+                    rect.min.x - POPUP_GAP - POPUP_WIDTH
+                };
+                window.fixed_pos([x, rect.min.y])
+            }
+            Some(rect) => {
+                window.fixed_pos([rect.center().x - POPUP_WIDTH / 2.0, rect.min.y + POPUP_GAP])
+            }
+            None => window.anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0]),
+        };
+
+        if let Some(rect) = target {
+            context
+                .layer_painter(egui::LayerId::new(
+                    egui::Order::Foreground,
+                    egui::Id::new("tutorial_highlight"),
+                ))
+                .rect_stroke(rect.shrink(1.0), 4.0, Stroke::new(2.0, HIGHLIGHT_COLOR));
+        }
+
+        let mut skipped = false;
+        let mut next = false;
+        window.show(context, |ui| {
+            match step.command {
+                Some(command) => ui.label(format!("{} ({})", step.text, bindings.get(command))),
+                None => ui.label(step.text),
+            };
+            ui.horizontal(|ui| {
+                ui.label(format!("{} of {}", index + 1, STEPS.len()));
+                let last = index + 1 == STEPS.len();
+                next = ui
+                    .button(if last { "Finish" } else { "Next" })
+                    .on_hover_text("Go on to the next step")
+                    .clicked();
+                if !last {
+                    skipped = ui
+                        .button("Skip")
+                        .on_hover_text(
+                            "Close the tutorial, Show tutorial under Help brings it back",
+                        )
+                        .clicked();
+                }
+            });
+        });
+
+        if next {
+            self.step = Some(index + 1).filter(|&index| index < STEPS.len());
+        }
+        if skipped {
+            self.step = None;
+        }
+        (next || skipped) && self.step.is_none()
+    }
+}